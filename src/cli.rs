@@ -1,4 +1,13 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::diagnostics::hardware::HardwareFormat;
+use crate::operations::capture::CapturePurpose;
+use crate::operations::enroll::DEFAULT_MAX_SAMPLES;
+use crate::operations::enum_databases::DatabaseFormat;
+use crate::operations::list::ListFormat;
+use crate::winbio_helpers::{BiometricType, SensorPool};
 
 #[derive(Parser)]
 #[command(
@@ -6,9 +15,82 @@ use clap::{Parser, Subcommand};
     about = "Windows Fingerprint Reader Diagnostic Tool",
     long_about = "Diagnoses fingerprint reader issues on Windows at multiple levels:\n\
                   hardware detection, driver/service status, WinBio subsystem\n\
-                  enumeration, and interactive operations."
+                  enumeration, and interactive operations.",
+    after_help = "Exit codes:\n  \
+                  0  success\n  \
+                  1  general error or any [FAIL] result\n  \
+                  2  no biometric hardware / sensor unit found\n  \
+                  3  WbioSrvc is not running\n  \
+                  4  finger did not match\n  \
+                  5  Administrator privileges required\n  \
+                  6  Windows Biometric Framework not available"
 )]
 pub struct Cli {
+    /// Emit a single JSON document instead of colored text
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// With --json, print the document on one line instead of indented
+    #[arg(long, global = true, requires = "json")]
+    pub compact: bool,
+
+    /// Disable colored output (also honors NO_COLOR and is automatic when not a terminal)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Log every WinBio API call with its raw HRESULT
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Print only PASS/FAIL/WARN lines (headers, info, and steps still go to --log-file)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Retries (with backoff) when the sensor reports DEVICE_BUSY/SESSION_BUSY
+    #[arg(long, global = true, value_name = "N", default_value_t = 3)]
+    pub retries: u32,
+
+    /// Open every WinBio session with these flags (hex), overriding the command's default
+    #[arg(
+        long,
+        global = true,
+        value_name = "0xFLAGS",
+        value_parser = parse_hex_u32,
+        hide_short_help = true,
+        long_help = "Advanced: open every WinBio session with these flags (hex), overriding \
+                     the command's default, to reproduce specific behavior.\n\
+                     Flags may be OR-ed together. Known values:\n  \
+                     0x00000000  WINBIO_FLAG_DEFAULT\n  \
+                     0x00010000  WINBIO_FLAG_BASIC (basic sensor mode)\n  \
+                     0x00020000  WINBIO_FLAG_ADVANCED (advanced sensor mode)\n  \
+                     0x20000000  WINBIO_FLAG_RAW (as used by capture/watch/export-template)"
+    )]
+    pub session_flags: Option<u32>,
+
+    /// Biometric type to enumerate units and databases and open sessions for
+    #[arg(long = "type", global = true, value_enum, default_value_t = BiometricType::Fingerprint)]
+    pub biometric_type: BiometricType,
+
+    /// Sensor pool to open WinBio sessions against
+    #[arg(long, global = true, value_enum, default_value_t = SensorPool::System)]
+    pub pool: SensorPool,
+
+    /// Before running the command, wait up to this long for a sensor of the --type to enumerate
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub wait_for_sensor: Option<u64>,
+
+    /// Run interactive commands even in a Remote Desktop session
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// Also write a plain-text (uncolored) copy of all output to this file
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Print how long each step took (enumeration, sessions, registry, PowerShell)
+    #[arg(long, global = true)]
+    pub timings: bool,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -16,49 +98,262 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Command {
     /// Run all 3 diagnostic levels sequentially (hardware → driver → sensor)
-    Diagnose,
+    Diagnose {
+        #[command(flatten)]
+        events: EventLogArgs,
+        /// Afterwards, offer to fix what was found (start the service, remove phantom devices, reinstall the driver)
+        #[arg(long)]
+        fix: bool,
+        /// Re-run every DURATION (e.g. 30s, 5m) until Ctrl+C, printing what changed since the previous run
+        #[arg(long, value_name = "DURATION", value_parser = parse_repeat_interval, conflicts_with = "fix")]
+        repeat: Option<Duration>,
+    },
+
+    /// Look for vendor fingerprint software (HP SimplePass, ...) that can hold the sensor
+    CheckConflicts,
+
+    /// Level 1: PnP biometric device detection via SetupAPI
+    CheckHardware {
+        /// Only show devices whose instance ID starts with this (case-insensitive)
+        #[arg(long, value_name = "ID")]
+        instance_id: Option<String>,
+        /// Output format (json prints only the device inventory)
+        #[arg(long, value_enum, default_value_t = HardwareFormat::Text)]
+        format: HardwareFormat,
+    },
+
+    /// Report biometric Group Policy settings that disable fingerprint sign-in
+    CheckPolicy,
 
-    /// Level 1: PnP biometric device detection via PowerShell
-    CheckHardware,
+    /// Check that the TPM holding Windows Hello keys is present, enabled, owned, and ready
+    CheckTpm,
 
     /// Level 2: WbioSrvc service status and configuration
     CheckDriver,
 
     /// Level 3: WinBio unit enumeration + session test
-    CheckSensor,
+    CheckSensor {
+        #[command(flatten)]
+        events: EventLogArgs,
+        /// Hide units that duplicate an earlier one (same serial or device instance)
+        #[arg(long)]
+        unique: bool,
+        /// Only show devices whose instance ID starts with this (case-insensitive)
+        #[arg(long, value_name = "ID")]
+        instance_id: Option<String>,
+        /// Tally units per pool, merge units listed in both pools, and test a session on each
+        #[arg(long)]
+        all_pools: bool,
+    },
+
+    /// List biometric units one per line (with --json: every schema field, for picking --unit)
+    ListUnits,
 
     /// List enrolled fingerprints (requires finger touch to identify user)
-    ListFingerprints,
+    ListFingerprints {
+        /// Give up after this many seconds without a finger touch
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<u64>,
+        /// List every user's enrollments on every unit (requires admin, no touch)
+        #[arg(long, conflicts_with = "timeout")]
+        all_users: bool,
+        /// Output layout: text (default) or csv (one row per enrolled finger)
+        #[arg(long, value_enum, default_value_t = ListFormat::Text)]
+        format: ListFormat,
+        /// With --format csv, write the rows to this file instead of after the report
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+
+    /// One touch: report your identity, enrolled fingers, and credential state
+    Whoami {
+        /// Give up after this many seconds without a finger touch
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<u64>,
+    },
 
     /// Touch sensor to identify the current user (blocks until touch)
-    Identify,
+    Identify {
+        /// Biometric unit ID to use (from check-sensor output; default: first unit)
+        #[arg(long)]
+        unit: Option<u32>,
+        /// Select the unit by its serial number instead of --unit
+        #[arg(long, value_name = "SN", conflicts_with = "unit")]
+        serial: Option<String>,
+        /// Give up after this many seconds without a finger touch
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<u64>,
+        /// Identify this many times in one session and tally the outcomes
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        count: u32,
+        /// Use an asynchronous session; any key cancels the touch
+        #[arg(long = "async", conflicts_with_all = ["timeout", "count"])]
+        use_async: bool,
+        /// On WINBIO_E_DATABASE_LOCKED, restart WbioSrvc and retry without asking
+        #[arg(long)]
+        restart_on_lock: bool,
+    },
+
+    /// Touch a reader to find out which unit ID it is
+    LocateSensor {
+        /// Give up after this many seconds without a finger touch
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<u64>,
+    },
 
     /// Verify a specific finger matches the enrolled template
     Verify {
-        /// Finger position (1–10): 1=RThumb, 2=RIndex, … 6=LThumb, 7=LIndex, …
-        #[arg(long)]
+        /// Finger: 1–10, a name (right-thumb, left-index, r-index, li, …), or any (0xFF)
+        #[arg(long, value_parser = parse_verify_finger)]
         finger: u8,
+        /// Biometric unit ID to use (from check-sensor output; default: first unit)
+        #[arg(long)]
+        unit: Option<u32>,
+        /// Select the unit by its serial number instead of --unit
+        #[arg(long, value_name = "SN", conflicts_with = "unit")]
+        serial: Option<String>,
+        /// Give up after this many seconds without a finger touch
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<u64>,
+        /// Skip the identify touch and verify against the logged-in user's SID
+        #[arg(long)]
+        no_identify: bool,
     },
 
     /// Capture a raw fingerprint sample and display metadata
-    Capture,
+    Capture {
+        /// Biometric unit ID to use (from check-sensor output; default: first unit)
+        #[arg(long)]
+        unit: Option<u32>,
+        /// Select the unit by its serial number instead of --unit
+        #[arg(long, value_name = "SN", conflicts_with = "unit")]
+        serial: Option<String>,
+        /// Write the raw BIR sample to this file (block layout goes to <path>.json)
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Capture this many samples in one session and summarize their sizes
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        count: u32,
+        /// WINBIO_PURPOSE_* to capture with, to match what Windows Hello uses
+        #[arg(long, value_enum, default_value_t = CapturePurpose::None)]
+        purpose: CapturePurpose,
+        /// Write the parsed BIR structure (block layout and header fields) as JSON to this file
+        #[arg(long, value_name = "PATH")]
+        output_bir_info: Option<PathBuf>,
+        /// Time each successful capture and report min/median/max/mean latency
+        #[arg(long)]
+        bench: bool,
+    },
+
+    /// Compare two raw samples (fresh captures or saved files) with a byte-level heuristic
+    CompareTemplates {
+        /// Biometric unit ID to use (from check-sensor output; default: first unit)
+        #[arg(long, conflicts_with = "files")]
+        unit: Option<u32>,
+        /// Select the unit by its serial number instead of --unit
+        #[arg(long, value_name = "SN", conflicts_with_all = ["unit", "files"])]
+        serial: Option<String>,
+        /// Compare two files saved by `capture --output` instead of capturing
+        #[arg(long, num_args = 2, value_names = ["A", "B"])]
+        files: Vec<PathBuf>,
+    },
+
+    /// Identify an enrolled finger and export its data (fails for on-chip template storage)
+    ExportTemplate {
+        /// Finger to export: 1–10 or a name (right-thumb, left-index, r-index, li, …)
+        #[arg(long, value_parser = parse_finger)]
+        finger: u8,
+        /// Biometric unit ID to use (from check-sensor output; default: first unit)
+        #[arg(long)]
+        unit: Option<u32>,
+        /// Select the unit by its serial number instead of --unit
+        #[arg(long, value_name = "SN", conflicts_with = "unit")]
+        serial: Option<String>,
+        /// File to write the exported data to (block layout goes to <path>.json)
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Capture touches in a loop, printing reject reasons until Ctrl+C
+    Watch,
+
+    /// Stream live WinBio framework events (unclaimed touches, errors, unit arrival/removal) until Ctrl+C
+    MonitorEvents,
 
     /// Delete a fingerprint template for a specific finger
     Delete {
-        /// Finger position (1–10) to delete
+        /// Finger to delete: 1–10 or a name (right-thumb, left-index, r-index, li, …)
+        #[arg(long, value_parser = parse_finger, required_unless_present = "all", conflicts_with = "all")]
+        finger: Option<u8>,
+        /// Delete every finger enrolled for the identified user
         #[arg(long)]
-        finger: u8,
+        all: bool,
+        /// Biometric unit ID to use (from check-sensor output; default: first unit)
+        #[arg(long)]
+        unit: Option<u32>,
+        /// Select the unit by its serial number instead of --unit
+        #[arg(long, value_name = "SN", conflicts_with = "unit")]
+        serial: Option<String>,
+        /// Give up after this many seconds without a finger touch
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<u64>,
+        /// Show what would be deleted without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation prompt (required when stdin is not a terminal)
+        #[arg(short, long)]
+        yes: bool,
+        /// On WINBIO_E_DATABASE_LOCKED, restart WbioSrvc and retry without asking
+        #[arg(long)]
+        restart_on_lock: bool,
     },
 
     /// Enroll a new fingerprint (requires repeated touches)
     Enroll {
-        /// Finger position (1–10): 1=RThumb, 2=RIndex, … 6=LThumb, 7=LIndex, …
+        /// Finger: 1–10 or a name (right-thumb, left-index, r-index, li, …)
+        #[arg(long, value_parser = parse_finger, required_unless_present = "fingers", conflicts_with = "fingers")]
+        finger: Option<u8>,
+        /// Enroll several fingers in one session, e.g. 1,2,6,7 or ri,li
+        #[arg(long, value_parser = parse_finger, value_delimiter = ',')]
+        fingers: Vec<u8>,
+        /// Biometric unit ID to use (from check-sensor output; default: first unit)
         #[arg(long)]
-        finger: u8,
+        unit: Option<u32>,
+        /// Select the unit by its serial number instead of --unit
+        #[arg(long, value_name = "SN", conflicts_with = "unit")]
+        serial: Option<String>,
+        /// Continue an enrollment left in progress by an interrupted run
+        #[arg(long, conflicts_with = "discard")]
+        resume: bool,
+        /// Discard an enrollment left in progress and start over
+        #[arg(long, conflicts_with = "resume")]
+        discard: bool,
+        /// Offer to discard a template that completes with fewer good samples than this
+        #[arg(long, value_name = "N")]
+        min_samples: Option<u32>,
+        /// Capture attempts per finger, good or bad, before the enrollment is discarded
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_SAMPLES, value_parser = clap::value_parser!(u32).range(1..))]
+        max_samples: u32,
+        /// Cancel and discard the enrollment when no touch arrives within this many seconds
+        #[arg(long, value_name = "SECONDS")]
+        sample_timeout: Option<u64>,
+        /// On a duplicate, delete your existing template for the finger and enroll it again
+        #[arg(long)]
+        overwrite: bool,
+        /// On WINBIO_E_DATABASE_LOCKED, restart WbioSrvc and retry without asking
+        #[arg(long)]
+        restart_on_lock: bool,
     },
 
     /// List biometric storage databases (paths, GUIDs, attributes)
-    EnumDatabases,
+    EnumDatabases {
+        /// Output layout: text (default), json, or csv (one row per database)
+        #[arg(long, value_enum, default_value_t = DatabaseFormat::Text)]
+        format: DatabaseFormat,
+        /// Only show databases used by a device whose instance ID starts with this (case-insensitive)
+        #[arg(long, value_name = "ID")]
+        instance_id: Option<String>,
+    },
 
     /// Delete a biometric database by number, or all databases with --all
     DeleteDatabase {
@@ -74,6 +369,22 @@ pub enum Command {
         /// Delete the registry entry (fully unregisters the database)
         #[arg(long)]
         registry: bool,
+        /// Leave WbioSrvc stopped after deleting files so it can't recreate them
+        #[arg(long)]
+        stop_service: bool,
+        /// Show what would be deleted without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation prompt (required when stdin is not a terminal)
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Copy every database's .DAT file and registry entry into a directory
+    BackupDatabases {
+        /// Directory to write the backup and manifest.json into (created if missing)
+        #[arg(long)]
+        output: PathBuf,
     },
 
     /// Stop the WbioSrvc (Windows Biometric Service)
@@ -82,11 +393,90 @@ pub enum Command {
     /// Start the WbioSrvc (Windows Biometric Service)
     StartService,
 
+    /// Stop (if running) and then start the WbioSrvc (Windows Biometric Service)
+    RestartService,
+
+    /// Print a timestamped line each time the WbioSrvc state changes, until Ctrl+C
+    MonitorService,
+
     /// Check if a Windows Hello credential (password hash) is linked to the biometric identity
-    CredentialState,
+    CredentialState {
+        /// Check the logged-in user's SID directly instead of identifying with a touch
+        #[arg(long)]
+        no_identify: bool,
+    },
+
+    /// List .DAT files no database references and registered databases whose file is missing
+    CheckDatabases,
+
+    /// Check that SYSTEM/WbioSrvc can read and write the WinBioDatabase folder and .DAT files
+    CheckDatabaseAcl,
+
+    /// Report sensor firmware versions and flag readers with a recommended update
+    CheckFirmware,
+
+    /// Query live unit properties (sample hint, extended sensor/engine/storage info, unit status)
+    SensorProperties {
+        /// Biometric unit ID to query (from check-sensor output; default: first unit)
+        #[arg(long)]
+        unit: Option<u32>,
+        /// Select the unit by its serial number instead of --unit
+        #[arg(long, value_name = "SN", conflicts_with = "unit")]
+        serial: Option<String>,
+    },
+
+    /// Switch a reader's indicator LED on or off (needs the Indicator capability)
+    SetIndicator {
+        /// Biometric unit ID to use (from check-sensor output; default: first unit)
+        #[arg(long)]
+        unit: Option<u32>,
+        /// Select the unit by its serial number instead of --unit
+        #[arg(long, value_name = "SN", conflicts_with = "unit")]
+        serial: Option<String>,
+        /// Turn the indicator on
+        #[arg(long, conflicts_with = "off", required_unless_present = "off")]
+        on: bool,
+        /// Turn the indicator off
+        #[arg(long)]
+        off: bool,
+        /// Vendor-specific sensor control code (hex) that sets the indicator
+        #[arg(long, value_name = "CODE", value_parser = parse_hex_u32)]
+        control_code: u32,
+    },
+
+    /// List installed WinBio service providers and every adapter DLL, flagging missing ones
+    EnumAdapters,
+
+    /// Print the tool version, Windows build, WinBio version, and adapter DLL versions
+    VersionInfo,
+
+    /// Write a support bundle (checks as JSON, event log, registry) to a timestamped zip
+    Collect {
+        /// Directory to write the bundle into
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        output_dir: PathBuf,
+        /// Mask local, domain, and Entra ID user SIDs in the reports (keeping the RID) and leave out the raw event log
+        #[arg(long)]
+        redact: bool,
+        #[command(flatten)]
+        events: EventLogArgs,
+    },
+
+    /// Run every non-interactive check and print a PASS/FAIL summary with a health score
+    SelfTest,
 
     /// Remove and rescan the biometric device to force driver reinstallation
-    ReinstallDriver,
+    ReinstallDriver {
+        /// Instance ID of the device to reinstall (required when several are present)
+        #[arg(long)]
+        device: Option<String>,
+        /// Show what would be uninstalled and reinstalled without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Keep the exported driver package in %TEMP% and print its path
+        #[arg(long)]
+        keep_backup: bool,
+    },
 
     /// Remove a PnP device entry by instance ID (requires admin)
     RemoveDevice {
@@ -96,5 +486,115 @@ pub enum Command {
         /// Remove all phantom (ghost) biometric devices
         #[arg(long, conflicts_with = "instance_id")]
         phantom: bool,
+        /// Show what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation prompt (required when stdin is not a terminal)
+        #[arg(short, long)]
+        yes: bool,
     },
 }
+
+/// Filters for the WinBio event log check run by `diagnose` and `check-sensor`.
+#[derive(Args)]
+pub struct EventLogArgs {
+    /// Only report events newer than this (e.g. 30m, 24h, 7d)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub since: Option<Duration>,
+    /// Comma-separated event IDs to report instead of the known failure IDs
+    #[arg(long, value_name = "IDS", value_delimiter = ',')]
+    pub event_ids: Vec<u32>,
+}
+
+/// Parse a 32-bit hex value such as `0x20000000` (the `0x` prefix is optional).
+fn parse_hex_u32(s: &str) -> Result<u32, String> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u32::from_str_radix(digits, 16)
+        .map_err(|_| format!("expected a hex value like 0x20000000, got '{}'", s))
+}
+
+/// Parse a duration like `90s`, `30m`, `24h`, or `7d`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("expected a number followed by s/m/h/d, got '{}'", s))?;
+    let multiplier: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}' (use s, m, h, or d)", unit)),
+    };
+    value
+        .checked_mul(multiplier)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration too large: '{}'", s))
+}
+
+/// `parse_duration` for `diagnose --repeat`, which needs a pause between runs.
+fn parse_repeat_interval(s: &str) -> Result<Duration, String> {
+    match parse_duration(s)? {
+        d if d.is_zero() => Err("the interval must be at least 1s".to_string()),
+        d => Ok(d),
+    }
+}
+
+const FINGER_HELP: &str = "use 1–10 or <side>-<finger>: side right/r or left/l, \
+     finger thumb/t, index/i, middle/m, ring/r, little/l/pinky (e.g. right-thumb, l-index, ri)";
+
+/// Like `parse_finger`, but also accepts `any` / `0xFF` (`WINBIO_SUBTYPE_ANY`):
+/// only verify can match against any enrolled finger.
+fn parse_verify_finger(s: &str) -> Result<u8, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "any" | "0xff" | "255" => Ok(crate::winbio_helpers::WINBIO_SUBTYPE_ANY),
+        _ => parse_finger(s),
+    }
+}
+
+/// Parse a finger as a WinBio subfactor: either `1`–`10` or a name such as
+/// `right-thumb`, `left-index`, `r-index`, or `li`.
+fn parse_finger(s: &str) -> Result<u8, String> {
+    if let Ok(n) = s.parse::<u8>() {
+        return if (1..=10).contains(&n) {
+            Ok(n)
+        } else {
+            Err(format!("finger {} is out of range — {}", n, FINGER_HELP))
+        };
+    }
+
+    let name = s.trim().to_ascii_lowercase().replace(['_', ' '], "-");
+    let (side, finger) = match name.split_once('-') {
+        Some(parts) => parts,
+        None if name.len() == 2 && name.is_ascii() => name.split_at(1),
+        None if matches!(
+            name.as_str(),
+            "thumb" | "index" | "middle" | "ring" | "little" | "pinky"
+        ) =>
+        {
+            return Err(format!(
+                "'{}' is ambiguous — add a side (right-{} or left-{})",
+                s, name, name
+            ))
+        }
+        None => return Err(format!("unknown finger '{}' — {}", s, FINGER_HELP)),
+    };
+
+    let base = match side {
+        "right" | "r" => 0,
+        "left" | "l" => 5,
+        _ => return Err(format!("unknown side in '{}' — {}", s, FINGER_HELP)),
+    };
+    let offset = match finger {
+        "thumb" | "t" => 1,
+        "index" | "i" => 2,
+        "middle" | "m" => 3,
+        "ring" | "r" => 4,
+        "little" | "l" | "pinky" | "p" => 5,
+        _ => return Err(format!("unknown finger in '{}' — {}", s, FINGER_HELP)),
+    };
+    Ok(base + offset)
+}
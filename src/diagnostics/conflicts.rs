@@ -0,0 +1,172 @@
+use anyhow::Result;
+use serde_json::json;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::System::Diagnostics::ToolHelp::*;
+use windows::Win32::System::Services::*;
+
+use super::findings::Finding;
+use crate::output::*;
+use crate::winbio_helpers::wchar_to_string;
+
+/// Third-party fingerprint software known to claim the sensor for itself.
+struct ConflictingSoftware {
+    /// Product name, as shown in the report.
+    product: &'static str,
+    /// Case-insensitive substring matched against running process image names
+    /// and installed service names / display names.
+    pattern: &'static str,
+}
+
+/// Vendor suites that predate (or bypass) Windows Hello and can hold the
+/// sensor so WinBio calls hang or report busy. Matched by product name, so add
+/// a new entry by the name the product's service or process carries.
+const CONFLICTING_SOFTWARE: &[ConflictingSoftware] = &[
+    ConflictingSoftware {
+        product: "HP SimplePass",
+        pattern: "simplepass",
+    },
+    ConflictingSoftware {
+        product: "HP ProtectTools",
+        pattern: "protecttools",
+    },
+    ConflictingSoftware {
+        product: "DigitalPersona",
+        pattern: "digitalpersona",
+    },
+    ConflictingSoftware {
+        product: "AuthenTec TrueSuite",
+        pattern: "truesuite",
+    },
+    ConflictingSoftware {
+        product: "Lenovo ThinkVantage Fingerprint Software",
+        pattern: "thinkvantage fingerprint",
+    },
+];
+
+fn find_conflict(name: &str) -> Option<&'static ConflictingSoftware> {
+    let name = name.to_lowercase();
+    CONFLICTING_SOFTWARE
+        .iter()
+        .find(|s| name.contains(s.pattern))
+}
+
+/// Image names (e.g. `explorer.exe`) of all running processes.
+fn running_processes() -> Result<Vec<String>> {
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)
+            .map_err(|e| anyhow::anyhow!("CreateToolhelp32Snapshot failed: {}", e))?;
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut names = Vec::new();
+        let mut next = Process32FirstW(snapshot, &mut entry);
+        while next.is_ok() {
+            names.push(wchar_to_string(&entry.szExeFile));
+            next = Process32NextW(snapshot, &mut entry);
+        }
+        let _ = CloseHandle(snapshot);
+        Ok(names)
+    }
+}
+
+/// (service name, display name, running) for every installed Win32 service.
+fn installed_services() -> Result<Vec<(String, String, bool)>> {
+    unsafe {
+        let scm = OpenSCManagerW(None, None, SC_MANAGER_ENUMERATE_SERVICE)
+            .map_err(|e| anyhow::anyhow!("OpenSCManager failed: {}", e))?;
+
+        // Two-call buffer pattern; u64 backing keeps the entries' pointers aligned
+        let mut bytes_needed = 0u32;
+        let mut count = 0u32;
+        let _ = EnumServicesStatusExW(
+            scm,
+            SC_ENUM_PROCESS_INFO,
+            SERVICE_WIN32,
+            SERVICE_STATE_ALL,
+            None,
+            &mut bytes_needed,
+            &mut count,
+            None,
+            None,
+        );
+        let mut buf = vec![0u64; (bytes_needed as usize).div_ceil(8)];
+        let bytes = std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, buf.len() * 8);
+        let result = EnumServicesStatusExW(
+            scm,
+            SC_ENUM_PROCESS_INFO,
+            SERVICE_WIN32,
+            SERVICE_STATE_ALL,
+            Some(bytes),
+            &mut bytes_needed,
+            &mut count,
+            None,
+            None,
+        );
+        let _ = CloseServiceHandle(scm);
+        result.map_err(|e| anyhow::anyhow!("EnumServicesStatusEx failed: {}", e))?;
+
+        let entries = std::slice::from_raw_parts(
+            buf.as_ptr() as *const ENUM_SERVICE_STATUS_PROCESSW,
+            count as usize,
+        );
+        Ok(entries
+            .iter()
+            .map(|s| {
+                (
+                    s.lpServiceName.to_string().unwrap_or_default(),
+                    s.lpDisplayName.to_string().unwrap_or_default(),
+                    s.ServiceStatusProcess.dwCurrentState == SERVICE_RUNNING,
+                )
+            })
+            .collect())
+    }
+}
+
+/// Scan running processes and installed services for `CONFLICTING_SOFTWARE`.
+pub fn check_conflicting_software() -> Result<Vec<Finding>> {
+    print_header("Conflicting Fingerprint Software");
+    let _timer = time_step("Conflicting software scan");
+
+    let mut hits: Vec<(&'static str, String)> = Vec::new();
+    for process in running_processes()? {
+        if let Some(software) = find_conflict(&process) {
+            hits.push((software.product, format!("process {} is running", process)));
+        }
+    }
+    for (name, display_name, running) in installed_services()? {
+        let Some(software) = find_conflict(&name).or_else(|| find_conflict(&display_name)) else {
+            continue;
+        };
+        let state = if running { "running" } else { "installed" };
+        hits.push((
+            software.product,
+            format!("service {} ({}) is {}", display_name, name, state),
+        ));
+    }
+
+    if hits.is_empty() {
+        print_pass("No known conflicting fingerprint software found");
+        return Ok(Vec::new());
+    }
+
+    let mut findings = Vec::new();
+    for (product, detail) in &hits {
+        print_warn(&format!("{}: {}", product, detail));
+        record(
+            "conflicting_software",
+            json!({ "product": product, "detail": detail }),
+        );
+        if !findings
+            .iter()
+            .any(|f: &Finding| f.problem.starts_with(product))
+        {
+            findings.push(Finding::warning(
+                format!("{} is installed and may be holding the sensor", product),
+                "Uninstall it (Windows Hello replaces it), or stop its service, then retry",
+            ));
+        }
+    }
+    print_step("Vendor fingerprint suites can claim the sensor and make WinBio calls hang");
+    Ok(findings)
+}
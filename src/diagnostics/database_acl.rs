@@ -0,0 +1,329 @@
+use anyhow::Result;
+use serde_json::json;
+use windows::core::{w, PCWSTR, PWSTR};
+use windows::Win32::Foundation::{LocalFree, HLOCAL};
+use windows::Win32::Security::Authorization::{
+    ConvertSidToStringSidW, GetNamedSecurityInfoW, SE_FILE_OBJECT,
+};
+use windows::Win32::Security::{
+    GetAce, GetLengthSid, LookupAccountNameW, ACCESS_ALLOWED_ACE, ACL, DACL_SECURITY_INFORMATION,
+    PSECURITY_DESCRIPTOR, PSID, SID_NAME_USE,
+};
+
+use super::findings::{Finding, Severity};
+use crate::operations::delete_database::enumerate_targets;
+use crate::output::*;
+
+const DATABASE_DIR: &str = r"C:\WINDOWS\SYSTEM32\WINBIODATABASE";
+
+// ACE_HEADER.AceType / AceFlags (winnt.h)
+const ACCESS_ALLOWED_ACE_TYPE: u8 = 0;
+const ACCESS_DENIED_ACE_TYPE: u8 = 1;
+const INHERIT_ONLY_ACE: u8 = 0x08;
+
+// File access rights (winnt.h)
+const FILE_GENERIC_READ: u32 = 0x0012_0089;
+const FILE_GENERIC_WRITE: u32 = 0x0012_0116;
+const FILE_ALL_ACCESS: u32 = 0x001F_01FF;
+const GENERIC_ALL: u32 = 0x1000_0000;
+const GENERIC_WRITE: u32 = 0x4000_0000;
+const GENERIC_READ: u32 = 0x8000_0000;
+/// FILE_WRITE_DATA | FILE_APPEND_DATA | DELETE | WRITE_DAC | WRITE_OWNER
+const ANY_WRITE: u32 = 0x0000_0002 | 0x0000_0004 | 0x0001_0000 | 0x0004_0000 | 0x0008_0000;
+
+/// What WbioSrvc needs on the folder and on each `.DAT` file to read and
+/// rewrite the databases.
+const REQUIRED_ACCESS: u32 = FILE_GENERIC_READ | FILE_GENERIC_WRITE;
+
+const SYSTEM_SID: &str = "S-1-5-18";
+
+/// Broad groups that should never be able to modify the template store.
+const BROAD_SIDS: &[&str] = &[
+    "S-1-1-0",      // Everyone
+    "S-1-5-4",      // INTERACTIVE
+    "S-1-5-11",     // Authenticated Users
+    "S-1-5-32-545", // BUILTIN\Users
+];
+
+/// One access control entry that applies to the object itself.
+struct Ace {
+    allow: bool,
+    mask: u32,
+    sid: String,
+    account: String,
+}
+
+/// Expand the generic rights an ACE may carry into file-specific ones.
+fn map_generic(mask: u32) -> u32 {
+    let mut mapped = mask & !(GENERIC_ALL | GENERIC_WRITE | GENERIC_READ);
+    if mask & GENERIC_ALL != 0 {
+        mapped |= FILE_ALL_ACCESS;
+    }
+    if mask & GENERIC_WRITE != 0 {
+        mapped |= FILE_GENERIC_WRITE;
+    }
+    if mask & GENERIC_READ != 0 {
+        mapped |= FILE_GENERIC_READ;
+    }
+    mapped
+}
+
+unsafe fn sid_string(psid: PSID) -> Option<String> {
+    let mut string_sid = PWSTR::null();
+    ConvertSidToStringSidW(psid, &mut string_sid).ok()?;
+    let result = string_sid.to_string().ok();
+    let _ = LocalFree(Some(HLOCAL(string_sid.0 as *mut _)));
+    result
+}
+
+/// String SID of WbioSrvc's per-service SID (`NT SERVICE\WbioSrvc`).
+fn service_sid() -> Option<String> {
+    unsafe {
+        let mut sid = [0u8; 68]; // SECURITY_MAX_SID_SIZE
+        let mut sid_len = sid.len() as u32;
+        let mut domain = [0u16; 256];
+        let mut domain_len = domain.len() as u32;
+        let mut sid_use = SID_NAME_USE::default();
+        LookupAccountNameW(
+            PCWSTR::null(),
+            w!("NT SERVICE\\WbioSrvc"),
+            Some(PSID(sid.as_mut_ptr() as *mut _)),
+            &mut sid_len,
+            Some(PWSTR(domain.as_mut_ptr())),
+            &mut domain_len,
+            &mut sid_use,
+        )
+        .ok()?;
+        sid_string(PSID(sid.as_mut_ptr() as *mut _))
+    }
+}
+
+/// The DACL entries of `path` that apply to it (inherit-only entries are
+/// skipped). `Ok(None)` is a NULL DACL, which grants everyone full access.
+fn read_dacl(path: &str) -> Result<Option<Vec<Ace>>> {
+    let path_wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        let mut dacl: *mut ACL = std::ptr::null_mut();
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        let status = GetNamedSecurityInfoW(
+            PCWSTR(path_wide.as_ptr()),
+            SE_FILE_OBJECT,
+            DACL_SECURITY_INFORMATION,
+            None,
+            None,
+            Some(&mut dacl),
+            None,
+            &mut descriptor,
+        );
+        if status.is_err() {
+            anyhow::bail!(
+                "GetNamedSecurityInfo failed: {}",
+                windows::core::Error::from(status.to_hresult())
+            );
+        }
+
+        let aces = (!dacl.is_null()).then(|| {
+            let mut aces = Vec::new();
+            for index in 0..(*dacl).AceCount as u32 {
+                let mut ace_ptr: *mut core::ffi::c_void = std::ptr::null_mut();
+                if GetAce(dacl, index, &mut ace_ptr).is_err() {
+                    continue;
+                }
+                // Allowed and denied ACEs share the ACCESS_ALLOWED_ACE layout
+                let ace = &*(ace_ptr as *const ACCESS_ALLOWED_ACE);
+                let ace_type = ace.Header.AceType;
+                if (ace_type != ACCESS_ALLOWED_ACE_TYPE && ace_type != ACCESS_DENIED_ACE_TYPE)
+                    || ace.Header.AceFlags & INHERIT_ONLY_ACE != 0
+                {
+                    continue;
+                }
+                let psid = PSID(&ace.SidStart as *const u32 as *mut _);
+                let sid_bytes =
+                    std::slice::from_raw_parts(psid.0 as *const u8, GetLengthSid(psid) as usize);
+                aces.push(Ace {
+                    allow: ace_type == ACCESS_ALLOWED_ACE_TYPE,
+                    mask: map_generic(ace.Mask),
+                    sid: sid_string(psid).unwrap_or_default(),
+                    account: crate::winbio_helpers::sid_to_account_name(sid_bytes),
+                });
+            }
+            aces
+        });
+
+        let _ = LocalFree(Some(HLOCAL(descriptor.0)));
+        Ok(aces)
+    }
+}
+
+/// Problems with one object's DACL: critical when WbioSrvc cannot read or
+/// write it, a warning for deny entries and for broad groups that can modify it.
+fn acl_problems(aces: &[Ace], service_sids: &[String]) -> Vec<(Severity, String)> {
+    let mut problems = Vec::new();
+    let is_service = |ace: &Ace| service_sids.contains(&ace.sid);
+
+    // WbioSrvc runs as SYSTEM with its service SID; either may carry the grant
+    let allowed = aces
+        .iter()
+        .filter(|a| a.allow && is_service(a))
+        .fold(0, |mask, a| mask | a.mask);
+    let denied = aces
+        .iter()
+        .filter(|a| !a.allow && is_service(a))
+        .fold(0, |mask, a| mask | a.mask);
+    let missing = REQUIRED_ACCESS & !(allowed & !denied);
+    if missing != 0 {
+        problems.push((
+            Severity::Critical,
+            format!(
+                "SYSTEM/WbioSrvc lack read/write access (missing rights 0x{:08X})",
+                missing
+            ),
+        ));
+    }
+    for ace in aces.iter().filter(|a| !a.allow && is_service(a)) {
+        problems.push((
+            Severity::Warning,
+            format!("Deny entry for {}: 0x{:08X}", ace.account, ace.mask),
+        ));
+    }
+    for ace in aces
+        .iter()
+        .filter(|a| a.allow && a.mask & ANY_WRITE != 0 && BROAD_SIDS.contains(&a.sid.as_str()))
+    {
+        problems.push((
+            Severity::Warning,
+            format!(
+                "{} can modify it (0x{:08X}) — templates could be tampered with",
+                ace.account, ace.mask
+            ),
+        ));
+    }
+    problems
+}
+
+/// Check the WinBioDatabase folder and each registered `.DAT` file: SYSTEM
+/// (or WbioSrvc's service SID) must be able to read and write them, nothing
+/// may deny it, and broad groups must not be able to write. GPOs that
+/// "harden" System32 commonly break this and corrupt the databases.
+pub fn check_database_acls() -> Result<Vec<Finding>> {
+    print_header("Database Folder and File Permissions");
+    let _timer = time_step("Database ACL check");
+
+    let mut service_sids = vec![SYSTEM_SID.to_string()];
+    match service_sid() {
+        Some(sid) => service_sids.push(sid),
+        None => print_warn("Could not resolve the NT SERVICE\\WbioSrvc SID; checking SYSTEM only"),
+    }
+
+    let mut paths = vec![DATABASE_DIR.to_string()];
+    match enumerate_targets(None, true) {
+        Ok(targets) => paths.extend(
+            targets
+                .into_iter()
+                .map(|t| t.file_path)
+                .filter(|p| !p.is_empty() && std::path::Path::new(p).exists()),
+        ),
+        Err(e) => print_warn(&format!(
+            "Could not enumerate databases, checking the folder only: {:#}",
+            e
+        )),
+    }
+
+    let mut broken = Vec::new();
+    let mut exposed = Vec::new();
+    for path in &paths {
+        let problems = match read_dacl(path) {
+            Ok(Some(aces)) => {
+                let problems = acl_problems(&aces, &service_sids);
+                if problems.iter().any(|(s, _)| *s == Severity::Critical) {
+                    print_fail(path);
+                } else if !problems.is_empty() {
+                    print_warn(path);
+                }
+                if !problems.is_empty() {
+                    for (_, problem) in &problems {
+                        print_info("  Problem", problem);
+                    }
+                    for ace in &aces {
+                        print_info(
+                            &format!("  {}", if ace.allow { "Allow" } else { "Deny" }),
+                            &format!("{} ({}) 0x{:08X}", ace.account, ace.sid, ace.mask),
+                        );
+                    }
+                }
+                problems
+            }
+            Ok(None) => {
+                print_warn(path);
+                print_info("  Problem", "NULL DACL — everyone has full access");
+                vec![(
+                    Severity::Warning,
+                    "NULL DACL — everyone has full access".to_string(),
+                )]
+            }
+            Err(e) => {
+                print_warn(&format!("{}: could not read the ACL: {:#}", path, e));
+                print_step("Run as Administrator to read the permissions");
+                continue;
+            }
+        };
+        if problems.is_empty() {
+            print_pass(path);
+        } else if problems.iter().any(|(s, _)| *s == Severity::Critical) {
+            broken.push(path.as_str());
+        } else {
+            exposed.push(path.as_str());
+        }
+        record(
+            "database_acls",
+            json!({
+                "path": path,
+                "problems": problems.iter().map(|(_, p)| p).collect::<Vec<_>>(),
+            }),
+        );
+    }
+
+    if broken.is_empty() && exposed.is_empty() {
+        print_blank();
+        print_pass("Database folder and file permissions look correct");
+        return Ok(Vec::new());
+    }
+
+    print_blank();
+    print_step(
+        "The default ACL gives SYSTEM full control of the folder and its files. Restore it with:",
+    );
+    print_step(&format!(
+        "  icacls \"{}\" /grant *{}:(OI)(CI)F /T",
+        DATABASE_DIR, SYSTEM_SID
+    ));
+    print_step(
+        "Then remove any deny or broad-write entries listed above, and check which GPO set them",
+    );
+    let mut findings = Vec::new();
+    if !broken.is_empty() {
+        findings.push(Finding::critical(
+            format!(
+                "WbioSrvc cannot read or write {} WinBioDatabase object(s): {}",
+                broken.len(),
+                broken.join(", ")
+            ),
+            format!(
+                "Run icacls \"{}\" /grant *{}:(OI)(CI)F /T, remove any deny entries, \
+                 then restart WbioSrvc",
+                DATABASE_DIR, SYSTEM_SID
+            ),
+        ));
+    }
+    if !exposed.is_empty() {
+        findings.push(Finding::warning(
+            format!(
+                "Unusual permissions on {} WinBioDatabase object(s): {}",
+                exposed.len(),
+                exposed.join(", ")
+            ),
+            "Remove the deny and Everyone/Users write entries listed under check-database-acl",
+        ));
+    }
+    Ok(findings)
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use serde_json::json;
+
+use crate::operations::delete_database::{enumerate_targets, find_orphaned_dat_files};
+use crate::output::*;
+
+/// Cross-check registered databases against the .DAT files on disk, without
+/// changing anything: registered databases whose file is gone, and .DAT files
+/// in the WinBioDatabase directory that no database references.
+pub fn check_databases() -> Result<()> {
+    print_header("Database Consistency");
+
+    let targets = enumerate_targets(None, true)?;
+    print_info("Registered databases", &targets.len().to_string());
+
+    let mut missing = 0usize;
+    let mut unchecked = 0usize;
+    for target in &targets {
+        if target.file_path.is_empty() {
+            print_info(
+                &format!("  Database {}", target.index),
+                &format!("{} — no file (on-chip storage)", target.db_id),
+            );
+            continue;
+        }
+        // exists() reads access denied as "missing"; the folder is SYSTEM-only
+        match std::path::Path::new(&target.file_path).try_exists() {
+            Ok(true) => print_pass(&format!(
+                "Database {} — {} — {}",
+                target.index, target.db_id, target.file_path
+            )),
+            Err(e) => {
+                unchecked += 1;
+                print_warn(&format!(
+                    "Database {} — {} — cannot check {} (run as Administrator): {}",
+                    target.index, target.db_id, target.file_path, e
+                ));
+            }
+            Ok(false) => {
+                missing += 1;
+                print_warn(&format!(
+                    "Database {} — {} — file missing: {}",
+                    target.index, target.db_id, target.file_path
+                ));
+                record(
+                    "missing_files",
+                    json!({
+                        "index": target.index,
+                        "database_id": target.db_id,
+                        "file_path": target.file_path,
+                    }),
+                );
+            }
+        }
+    }
+
+    let registered: Vec<&str> = targets.iter().map(|t| t.file_path.as_str()).collect();
+    let orphans = find_orphaned_dat_files(&registered);
+    if !orphans.is_empty() {
+        print_blank();
+        print_warn(&format!(
+            "{} orphaned .DAT file(s) (on disk but not registered)",
+            orphans.len()
+        ));
+        for path in &orphans {
+            print_info("  Orphan", &path.to_string_lossy());
+            record("orphaned_files", json!(path.to_string_lossy()));
+        }
+    }
+
+    print_blank();
+    if missing == 0 && unchecked == 0 && orphans.is_empty() {
+        print_pass("Registered databases and .DAT files are consistent");
+    } else {
+        if missing > 0 {
+            print_step("A missing file is recreated empty for active sensors when WbioSrvc starts; otherwise unregister it with delete-database --registry");
+        }
+        if !orphans.is_empty() {
+            print_step(
+                "Orphaned files are unused; to remove them, run stop-service, delete the files \
+                 listed above by hand, then run start-service",
+            );
+        }
+    }
+
+    Ok(())
+}
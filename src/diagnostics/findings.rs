@@ -0,0 +1,142 @@
+use serde_json::json;
+
+use crate::output::*;
+
+/// How urgent a `Finding` is. Ordered most severe first, so sorting ranks them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Fingerprint sign-in cannot work until this is fixed.
+    Critical,
+    /// Likely to cause failures, or worth a look.
+    Warning,
+    /// Unusual but harmless on its own.
+    Info,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Critical => "critical",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+}
+
+/// A problem a diagnostic check detected, with the action that usually fixes it.
+pub struct Finding {
+    pub severity: Severity,
+    pub problem: String,
+    pub action: String,
+}
+
+impl Finding {
+    pub fn critical(problem: impl Into<String>, action: impl Into<String>) -> Self {
+        Self::new(Severity::Critical, problem, action)
+    }
+
+    pub fn warning(problem: impl Into<String>, action: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, problem, action)
+    }
+
+    pub fn info(problem: impl Into<String>, action: impl Into<String>) -> Self {
+        Self::new(Severity::Info, problem, action)
+    }
+
+    pub fn new(severity: Severity, problem: impl Into<String>, action: impl Into<String>) -> Self {
+        Self {
+            severity,
+            problem: problem.into(),
+            action: action.into(),
+        }
+    }
+}
+
+fn count_label(count: usize, singular: &str) -> String {
+    if count == 1 {
+        format!("1 {}", singular)
+    } else {
+        format!("{} {}s", count, singular)
+    }
+}
+
+/// Print `findings` most severe first, each with its suggested action, under a
+/// one-line tally such as "1 critical, 2 warnings". Used to close `diagnose`.
+pub fn print_findings(findings: &mut [Finding]) {
+    print_header("Problems Found");
+
+    // Stable sort: findings of equal severity keep the order the checks ran in
+    findings.sort_by_key(|f| f.severity);
+
+    if findings.is_empty() {
+        print_pass("No problems detected");
+        return;
+    }
+
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    print_step(&format!(
+        "{} critical, {}, {}",
+        count(Severity::Critical),
+        count_label(count(Severity::Warning), "warning"),
+        count_label(count(Severity::Info), "note")
+    ));
+
+    for (i, finding) in findings.iter().enumerate() {
+        print_blank();
+        let line = format!(
+            "{}. [{}] {}",
+            i + 1,
+            finding.severity.label(),
+            finding.problem
+        );
+        match finding.severity {
+            Severity::Critical => print_fail(&line),
+            Severity::Warning => print_warn(&line),
+            Severity::Info => print_step(&line),
+        }
+        print_step(&format!("   Fix: {}", finding.action));
+
+        record(
+            "findings",
+            json!({
+                "rank": i + 1,
+                "severity": finding.severity.label(),
+                "problem": finding.problem,
+                "action": finding.action,
+            }),
+        );
+    }
+}
+
+/// Print how the problems changed since the previous run of `diagnose --repeat`,
+/// so a reader can spot problems that come and go between runs.
+pub fn print_findings_delta(previous: &[Finding], current: &[Finding]) {
+    print_header("Changes Since Previous Run");
+
+    let missing_from =
+        |findings: &[Finding], problem: &str| !findings.iter().any(|f| f.problem == problem);
+    let cleared: Vec<_> = previous
+        .iter()
+        .filter(|f| missing_from(current, &f.problem))
+        .collect();
+    let appeared: Vec<_> = current
+        .iter()
+        .filter(|f| missing_from(previous, &f.problem))
+        .collect();
+
+    if cleared.is_empty() && appeared.is_empty() {
+        print_pass("No change");
+        return;
+    }
+    for finding in cleared {
+        print_pass(&format!("Cleared: {}", finding.problem));
+    }
+    for finding in appeared {
+        let line = format!("New [{}]: {}", finding.severity.label(), finding.problem);
+        match finding.severity {
+            Severity::Critical => print_fail(&line),
+            Severity::Warning => print_warn(&line),
+            Severity::Info => print_step(&line),
+        }
+    }
+}
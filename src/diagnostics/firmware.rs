@@ -0,0 +1,100 @@
+use anyhow::Result;
+use serde_json::json;
+
+use crate::output::*;
+use crate::winbio_helpers;
+
+/// A reader whose firmware below `min_version` should be updated.
+struct FirmwareAdvisory {
+    /// Case-insensitive substring of the unit's reported manufacturer.
+    manufacturer: &'static str,
+    /// Case-insensitive substring of the reported model; empty matches any model.
+    model: &'static str,
+    /// First (major, minor) version that doesn't need the update.
+    min_version: (u32, u32),
+    /// Where to get the update.
+    url: &'static str,
+}
+
+/// Readers known to need a firmware update. Add an entry only once the vendor
+/// has published a fixed version. Units that match no entry are just reported,
+/// with no judgment.
+const FIRMWARE_ADVISORIES: &[FirmwareAdvisory] = &[];
+
+fn find_advisory(manufacturer: &str, model: &str) -> Option<&'static FirmwareAdvisory> {
+    let manufacturer = manufacturer.to_lowercase();
+    let model = model.to_lowercase();
+    FIRMWARE_ADVISORIES.iter().find(|a| {
+        manufacturer.contains(&a.manufacturer.to_lowercase())
+            && model.contains(&a.model.to_lowercase())
+    })
+}
+
+/// Compare each unit's `FirmwareVersion` against `FIRMWARE_ADVISORIES`.
+pub fn check_firmware() -> Result<()> {
+    print_header("Sensor Firmware");
+
+    let units = winbio_helpers::enum_units()?;
+    if units.is_empty() {
+        crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
+        print_fail(&format!(
+            "No {} biometric units found",
+            crate::winbio_helpers::biometric_type().name()
+        ));
+        return Ok(());
+    }
+
+    for unit in &units {
+        let manufacturer = winbio_helpers::wchar_to_string(&unit.Manufacturer);
+        let model = winbio_helpers::wchar_to_string(&unit.Model);
+        let version = (
+            unit.FirmwareVersion.MajorVersion,
+            unit.FirmwareVersion.MinorVersion,
+        );
+
+        print_blank();
+        print_step(&format!(
+            "Unit {} — {} {}",
+            unit.UnitId, manufacturer, model
+        ));
+        print_info(
+            "  Firmware version",
+            &format!("{}.{}", version.0, version.1),
+        );
+
+        let advisory = find_advisory(&manufacturer, &model);
+        let update_recommended = match advisory {
+            Some(a) if version < a.min_version => {
+                print_warn(&format!(
+                    "Firmware update recommended — {}.{} or later fixes known issues with this reader",
+                    a.min_version.0, a.min_version.1
+                ));
+                print_info("  Vendor", a.url);
+                true
+            }
+            Some(_) => {
+                print_pass("Firmware is at or above the recommended version");
+                false
+            }
+            None => {
+                print_info("  Assessment", "(no known advisories for this reader)");
+                false
+            }
+        };
+
+        record(
+            "firmware",
+            json!({
+                "unit_id": unit.UnitId,
+                "manufacturer": manufacturer,
+                "model": model,
+                "version": format!("{}.{}", version.0, version.1),
+                "known_reader": advisory.is_some(),
+                "update_recommended": update_recommended,
+                "vendor_url": advisory.map(|a| a.url),
+            }),
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use std::io::IsTerminal;
+use windows::Win32::System::Services::SERVICE_RUNNING;
+
+use crate::operations;
+use crate::operations::enum_databases::{device_database_ids, registered_database_ids};
+use crate::output::*;
+
+/// Describe a remediation and ask for confirmation. Without a terminal to ask
+/// on, the fix is skipped rather than applied unattended.
+fn offer(problem: &str, remedy: &str) -> Result<bool> {
+    print_blank();
+    print_warn(problem);
+    print_step(&format!("Fix: {}", remedy));
+    if !std::io::stdin().is_terminal() {
+        print_info("  Skipped", "stdin is not a terminal");
+        return Ok(false);
+    }
+    operations::confirm_destructive("yes", false)
+}
+
+/// Report a fix's outcome without aborting the remaining ones.
+fn report(result: Result<()>) {
+    if let Err(e) = result {
+        print_fail(&format!("Fix failed: {:#}", e));
+    }
+}
+
+/// After `diagnose`, detect the problems the crate knows how to remediate and
+/// offer each fix in turn, re-running the relevant check afterwards.
+pub fn offer_fixes() -> Result<()> {
+    print_header("Guided Repair (--fix)");
+    let mut offered = 0;
+
+    // WbioSrvc stopped
+    let running = unsafe { operations::service::query_service_state() }
+        .map(|state| state == SERVICE_RUNNING.0)
+        .unwrap_or(false);
+    if !running {
+        offered += 1;
+        if offer("WbioSrvc is not running", "start the service")? {
+            report(operations::service::run_start_service());
+            report(super::service::check_service().map(drop));
+        }
+    }
+
+    let devices = crate::pnp::enum_biometric_devices().unwrap_or_default();
+
+    // Phantom (disconnected) biometric devices
+    let phantoms = devices.iter().filter(|d| d.is_phantom()).count();
+    if phantoms > 0 {
+        offered += 1;
+        if offer(
+            &format!("{} phantom biometric device(s) present", phantoms),
+            "remove the phantom device entries",
+        )? {
+            report(operations::remove_device::run_remove_device(
+                None, true, false, true,
+            ));
+            report(super::hardware::check_hardware(None).map(drop));
+        }
+    }
+
+    // Present devices whose WinBio configuration names an unregistered database
+    let registered = registered_database_ids();
+    for dev in devices.iter().filter(|d| !d.is_phantom()) {
+        let unregistered = device_database_ids(&dev.instance_id)
+            .iter()
+            .any(|(_, db_id)| !registered.contains(db_id));
+        if !unregistered {
+            continue;
+        }
+        offered += 1;
+        if offer(
+            &format!(
+                "{} references a database that is not registered with WbioSrvc",
+                dev.friendly_name
+            ),
+            "reinstall the driver so its INF recreates the database entries",
+        )? {
+            report(operations::reinstall_driver::run_reinstall_driver(
+                Some(&dev.instance_id),
+                false,
+                false,
+            ));
+            super::winbio::check_database_config();
+        }
+    }
+
+    if offered == 0 {
+        print_pass("No problems with an automatic fix were found");
+    }
+    Ok(())
+}
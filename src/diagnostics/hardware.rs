@@ -1,84 +1,139 @@
 use anyhow::Result;
-use std::process::Command;
+use serde_json::json;
 
+use super::findings::Finding;
 use crate::output::*;
 
-pub fn check_hardware() -> Result<()> {
+/// `instance_id` limits the report to matching devices (`--instance-id`).
+pub fn check_hardware(instance_id: Option<&str>) -> Result<Vec<Finding>> {
     print_header("Level 1: Hardware Detection (PnP Biometric Devices)");
+    let _timer = time_step("Level 1: hardware");
 
-    // Use Format-List and string conversion to avoid JSON enum serialization issues
-    let ps_script = r#"
-        $devs = Get-PnpDevice -Class Biometric -ErrorAction SilentlyContinue
-        if ($null -eq $devs) { exit 0 }
-        $devs | ForEach-Object {
-            [PSCustomObject]@{
-                FriendlyName = [string]$_.FriendlyName
-                InstanceId   = [string]$_.InstanceId
-                Status       = [string]$_.Status
-                Problem      = [string]$_.Problem
-                Class        = [string]$_.Class
-                Manufacturer = [string]$_.Manufacturer
-            }
-        } | ConvertTo-Json -Compress
-    "#;
-
-    let output = Command::new("powershell")
-        .args(["-NoProfile", "-NonInteractive", "-Command", ps_script])
-        .output();
-
-    let output = match output {
-        Ok(o) => o,
-        Err(e) => {
-            print_fail(&format!("Failed to run PowerShell: {}", e));
-            return Ok(());
-        }
-    };
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stdout = stdout.trim();
-
-    if stdout.is_empty() {
-        print_fail("No biometric PnP devices found");
-        print_step("Check Device Manager > Biometric devices");
-        return Ok(());
-    }
-
-    // Parse JSON — PowerShell returns a single object (not array) for one device
-    let devices: Vec<serde_json::Value> = if stdout.starts_with('[') {
-        serde_json::from_str(stdout)?
-    } else {
-        vec![serde_json::from_str(stdout)?]
-    };
+    let devices = crate::pnp::enum_biometric_devices()?;
+    let mut findings = Vec::new();
 
     if devices.is_empty() {
+        crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
         print_fail("No biometric PnP devices found");
         print_step("Check Device Manager > Biometric devices");
-        return Ok(());
+        findings.push(Finding::critical(
+            "No fingerprint reader is visible to Windows",
+            "Check Device Manager > Biometric devices; if the reader is missing, install the \
+             manufacturer's driver or check that it is enabled in the firmware setup",
+        ));
+        return Ok(findings);
     }
 
     print_pass(&format!("Found {} biometric device(s)", devices.len()));
 
+    let total = devices.len();
+    let devices: Vec<_> = devices
+        .into_iter()
+        .filter(|d| crate::pnp::instance_id_matches(instance_id, &d.instance_id))
+        .collect();
+    if let Some(filter) = instance_id {
+        if devices.is_empty() {
+            print_warn(&format!(
+                "None of the {} device(s) has an instance ID starting with '{}'",
+                total, filter
+            ));
+        } else {
+            print_info("Matching --instance-id", &devices.len().to_string());
+        }
+    }
+
     for (i, dev) in devices.iter().enumerate() {
-        let name = dev["FriendlyName"].as_str().unwrap_or("(unknown)");
-        let manufacturer = dev["Manufacturer"].as_str().unwrap_or("(unknown)");
-        let instance_id = dev["InstanceId"].as_str().unwrap_or("(unknown)");
-        let status = dev["Status"].as_str().unwrap_or("Unknown");
-        let problem = dev["Problem"].as_str().unwrap_or("");
+        let name = if dev.friendly_name.is_empty() {
+            "(unknown)"
+        } else {
+            &dev.friendly_name
+        };
+        let manufacturer = if dev.manufacturer.is_empty() {
+            "(unknown)"
+        } else {
+            &dev.manufacturer
+        };
 
-        println!();
+        print_blank();
         print_info(&format!("  Device {}", i + 1), name);
         print_info("    Manufacturer", manufacturer);
-        print_info("    Instance ID", instance_id);
+        print_info("    Instance ID", &dev.instance_id);
 
-        if status == "OK" {
-            print_pass(&format!("    Status: {}", status));
+        if dev.status == "OK" {
+            print_pass(&format!("    Status: {}", dev.status));
         } else {
-            print_fail(&format!("    Status: {}", status));
-            if !problem.is_empty() && problem != "0" {
-                print_info("    Problem", problem);
+            print_fail(&format!("    Status: {}", dev.status));
+            if dev.problem != 0 {
+                print_info("    Problem", &format!("CM_PROB {}", dev.problem));
             }
+            findings.push(if dev.is_phantom() {
+                Finding::warning(
+                    format!("Phantom (disconnected) device entry: {}", name),
+                    "Run remove-device --phantom, or diagnose --fix",
+                )
+            } else {
+                Finding::critical(
+                    format!("{} reports status {}", name, dev.status),
+                    "Reinstall the driver with reinstall-driver, then re-run diagnose",
+                )
+            });
         }
+
+        record(
+            "devices",
+            json!({
+                "FriendlyName": dev.friendly_name,
+                "InstanceId": dev.instance_id,
+                "Status": dev.status,
+                "Problem": dev.problem,
+                "Class": "Biometric",
+                "Manufacturer": dev.manufacturer,
+            }),
+        );
+    }
+
+    Ok(findings)
+}
+
+/// Output layout for `check-hardware`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum HardwareFormat {
+    /// Human-readable report
+    Text,
+    /// A JSON array with one object per device, for inventory scripts
+    Json,
+}
+
+/// `check-hardware`: the Level 1 report, or with `--format json` just the
+/// device inventory as a JSON array.
+pub fn run_check_hardware(format: HardwareFormat, instance_id: Option<&str>) -> Result<()> {
+    if format == HardwareFormat::Text {
+        return check_hardware(instance_id).map(drop);
+    }
+    if json_mode() {
+        anyhow::bail!("--format json cannot be combined with --json");
+    }
+
+    let devices: Vec<_> = crate::pnp::enum_biometric_devices()?
+        .into_iter()
+        .filter(|d| crate::pnp::instance_id_matches(instance_id, &d.instance_id))
+        .collect();
+    if devices.is_empty() {
+        crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
     }
 
+    let doc: Vec<_> = devices
+        .iter()
+        .map(|dev| {
+            json!({
+                "friendly_name": dev.friendly_name,
+                "instance_id": dev.instance_id,
+                "status": dev.status,
+                "problem": dev.problem,
+                "manufacturer": dev.manufacturer,
+            })
+        })
+        .collect();
+    print_raw(&serde_json::to_string_pretty(&doc)?);
     Ok(())
 }
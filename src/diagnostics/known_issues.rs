@@ -0,0 +1,83 @@
+use serde_json::json;
+
+use crate::output::*;
+use crate::winbio_helpers;
+
+/// A reader with a known problem and what to do about it.
+struct KnownIssue {
+    /// Case-insensitive substring of the unit's reported manufacturer.
+    manufacturer: &'static str,
+    /// Case-insensitive substring of the reported model; empty matches any model.
+    model: &'static str,
+    /// One line: the symptom and the fix.
+    note: &'static str,
+    /// Where to read more.
+    url: &'static str,
+}
+
+/// Readers known to misbehave. Add an entry only with a public source to link;
+/// units that match no entry are just named in the summary.
+const KNOWN_ISSUES: &[KnownIssue] = &[];
+
+fn find_known_issues(manufacturer: &str, model: &str) -> Vec<&'static KnownIssue> {
+    let manufacturer = manufacturer.to_lowercase();
+    let model = model.to_lowercase();
+    KNOWN_ISSUES
+        .iter()
+        .filter(|i| {
+            manufacturer.contains(&i.manufacturer.to_lowercase())
+                && model.contains(&i.model.to_lowercase())
+        })
+        .collect()
+}
+
+/// Name each detected sensor and list any `KNOWN_ISSUES` that match it. Used
+/// as the closing summary of `diagnose`.
+pub fn print_sensor_summary() {
+    print_header("Summary");
+
+    let units = match winbio_helpers::enum_units() {
+        Ok(units) => units,
+        Err(e) => {
+            print_info("Sensor", &format!("(could not enumerate: {})", e));
+            return;
+        }
+    };
+    if units.is_empty() {
+        print_info("Sensor", "(none detected)");
+        return;
+    }
+
+    for unit in &units {
+        let manufacturer = winbio_helpers::wchar_to_string(&unit.Manufacturer);
+        let model = winbio_helpers::wchar_to_string(&unit.Model);
+        let subtype = winbio_helpers::sensor_subtype_name(unit.SensorSubType);
+        print_info(
+            &format!("Unit {}", unit.UnitId),
+            &format!("{} {} ({})", manufacturer, model, subtype),
+        );
+
+        let issues = find_known_issues(&manufacturer, &model);
+        for issue in &issues {
+            print_warn(&format!("  Known issue: {}", issue.note));
+            print_info("    More", issue.url);
+        }
+        if issues.is_empty() {
+            print_info("  Known issues", "(none on record for this reader)");
+        }
+
+        record(
+            "sensor_summary",
+            json!({
+                "unit_id": unit.UnitId,
+                "manufacturer": manufacturer,
+                "model": model,
+                "sensor_subtype": subtype,
+                "known_issues": issues
+                    .iter()
+                    .map(|i| json!({ "note": i.note, "url": i.url }))
+                    .collect::<Vec<_>>(),
+            }),
+        );
+    }
+}
@@ -1,3 +1,14 @@
+pub mod conflicts;
+pub mod database_acl;
+pub mod databases;
+pub mod findings;
+pub mod firmware;
+pub mod fix;
 pub mod hardware;
+pub mod known_issues;
+pub mod policy;
+pub mod self_test;
 pub mod service;
+pub mod tpm;
+pub mod version_info;
 pub mod winbio;
@@ -0,0 +1,122 @@
+use serde_json::json;
+use windows::core::PCWSTR;
+use windows::Win32::System::Registry::*;
+
+use super::findings::{Finding, Severity};
+use crate::operations::enum_databases::read_registry_string;
+use crate::output::*;
+
+/// A policy value that turns off fingerprint sign-in when set to 0.
+/// Values that are not set leave the feature at its default (enabled).
+struct PolicySetting {
+    key: &'static str,
+    value: &'static str,
+    /// Group Policy setting name, as shown in the Group Policy editor.
+    name: &'static str,
+    severity: Severity,
+    problem: &'static str,
+}
+
+const POLICY_SETTINGS: &[PolicySetting] = &[
+    PolicySetting {
+        key: "SOFTWARE\\Policies\\Microsoft\\Biometrics",
+        value: "Enabled",
+        name: "Biometrics > Allow the use of biometrics",
+        severity: Severity::Critical,
+        problem: "Biometrics are disabled by Group Policy",
+    },
+    PolicySetting {
+        key: "SOFTWARE\\Policies\\Microsoft\\Biometrics\\Credential Provider",
+        value: "Enabled",
+        name: "Biometrics > Allow users to log on using biometrics",
+        severity: Severity::Critical,
+        problem: "Biometric sign-in is disabled by Group Policy",
+    },
+    PolicySetting {
+        key: "SOFTWARE\\Policies\\Microsoft\\Biometrics\\Credential Provider",
+        value: "Domain Accounts",
+        name: "Biometrics > Allow domain users to log on using biometrics",
+        severity: Severity::Warning,
+        problem: "Domain users are not allowed to sign in with biometrics by Group Policy",
+    },
+    PolicySetting {
+        key: "SOFTWARE\\Policies\\Microsoft\\PassportForWork\\Biometrics",
+        value: "UseBiometrics",
+        name: "Windows Hello for Business > Allow use of biometrics",
+        severity: Severity::Warning,
+        problem: "Windows Hello for Business may not use biometrics (Group Policy)",
+    },
+];
+
+/// DWORD `value` under `HKLM\subkey`, if the key and value exist.
+fn read_policy_dword(subkey: &str, value: &str) -> Option<u32> {
+    let subkey_wide: Vec<u16> = subkey.encode_utf16().chain(std::iter::once(0)).collect();
+    let mut hkey = HKEY::default();
+    unsafe {
+        let status = RegOpenKeyExW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(subkey_wide.as_ptr()),
+            None,
+            KEY_READ,
+            &mut hkey,
+        );
+        if status.is_err() {
+            return None;
+        }
+        let data = read_registry_string(hkey, value);
+        let _ = RegCloseKey(hkey);
+        data?.parse().ok()
+    }
+}
+
+/// Report biometric Group Policy values that switch fingerprint sign-in off.
+/// A policy-disabled reader works in every hardware check yet can't sign in,
+/// so this explains "enrolled but can't sign in" on managed machines.
+pub fn check_biometric_policy() -> Vec<Finding> {
+    print_header("Biometric Group Policy");
+
+    let mut findings = Vec::new();
+    for setting in POLICY_SETTINGS {
+        let data = read_policy_dword(setting.key, setting.value);
+        let label = format!("  {}", setting.name);
+        match data {
+            None => print_info(&label, "not configured"),
+            Some(0) => {
+                let line = format!("{}: disabled", setting.name);
+                if setting.severity == Severity::Critical {
+                    print_fail(&line);
+                } else {
+                    print_warn(&line);
+                }
+                print_info(
+                    "    Registry",
+                    &format!("HKLM\\{}\\{} = 0", setting.key, setting.value),
+                );
+                findings.push(Finding::new(
+                    setting.severity,
+                    setting.problem,
+                    format!(
+                        "Ask your administrator to enable Computer Configuration > \
+                         Administrative Templates > Windows Components > {}",
+                        setting.name
+                    ),
+                ));
+            }
+            Some(other) => print_info(&label, &format!("enabled ({})", other)),
+        }
+        record(
+            "policies",
+            json!({
+                "key": setting.key,
+                "value": setting.value,
+                "name": setting.name,
+                "data": data,
+            }),
+        );
+    }
+
+    if findings.is_empty() {
+        print_pass("No biometric Group Policy blocks fingerprint sign-in");
+    }
+    findings
+}
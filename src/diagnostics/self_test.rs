@@ -0,0 +1,119 @@
+use anyhow::Result;
+use serde_json::json;
+
+use crate::output::*;
+use crate::winbio_helpers::SensorPool;
+
+/// Outcome of one self-test step.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    /// Not run, because the step needs a finger on the sensor.
+    Skipped,
+}
+
+impl CheckStatus {
+    fn label(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skipped => "SKIPPED",
+        }
+    }
+}
+
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// Run one existing check. It fails if it returns an error or prints any
+/// `[FAIL]` line of its own.
+fn run_check(name: &'static str, check: impl FnOnce() -> Result<()>) -> CheckResult {
+    let fails_before = fail_count();
+    let (status, detail) = match check() {
+        Err(e) => {
+            print_fail(&format!("{}: {}", name, e));
+            (CheckStatus::Fail, e.to_string())
+        }
+        Ok(()) => match fail_count() - fails_before {
+            0 => (CheckStatus::Pass, String::new()),
+            n => (CheckStatus::Fail, format!("{} failure(s)", n)),
+        },
+    };
+    CheckResult {
+        name,
+        status,
+        detail,
+    }
+}
+
+/// Non-interactive health check of every level that doesn't need a touch.
+/// Prints a summary plus a health score (passed / checks run) and returns the
+/// individual results.
+pub fn run_self_test(pool: SensorPool) -> Result<Vec<CheckResult>> {
+    print_header("Self-Test");
+    crate::elevation::warn_if_not_elevated();
+
+    let mut results = vec![
+        run_check("Hardware", || {
+            super::hardware::check_hardware(None).map(drop)
+        }),
+        run_check("Service", || super::service::check_service().map(drop)),
+        run_check("Sensor", || {
+            super::winbio::check_sensor(pool, None, &[], false, None, false).map(drop)
+        }),
+        run_check("Database config", || {
+            super::winbio::check_database_config();
+            Ok(())
+        }),
+        run_check("Credential state", || {
+            super::winbio::check_credential().map(drop)
+        }),
+    ];
+    for name in ["Identify", "Verify"] {
+        results.push(CheckResult {
+            name,
+            status: CheckStatus::Skipped,
+            detail: "requires a finger on the sensor".to_string(),
+        });
+    }
+
+    print_header("Self-Test Summary");
+    for result in &results {
+        let line = if result.detail.is_empty() {
+            result.status.label().to_string()
+        } else {
+            format!("{} — {}", result.status.label(), result.detail)
+        };
+        print_info(result.name, &line);
+        record(
+            "checks",
+            json!({
+                "name": result.name,
+                "status": result.status.label(),
+                "detail": result.detail,
+            }),
+        );
+    }
+
+    let run = results
+        .iter()
+        .filter(|r| r.status != CheckStatus::Skipped)
+        .count();
+    let passed = results
+        .iter()
+        .filter(|r| r.status == CheckStatus::Pass)
+        .count();
+    let score = passed * 100 / run.max(1);
+    print_blank();
+    print_info(
+        "Health score",
+        &format!("{}% ({}/{} checks passed)", score, passed, run),
+    );
+    record("health_score", json!(score));
+
+    Ok(results)
+}
@@ -1,11 +1,92 @@
 use anyhow::Result;
-use windows::core::w;
+use windows::core::{w, PWSTR};
 use windows::Win32::System::Services::*;
 
+use super::findings::Finding;
 use crate::output::*;
 
-pub fn check_service() -> Result<()> {
+/// Split a double-NUL-terminated multi-string (e.g. `lpDependencies`).
+/// Service-group dependencies keep the SCM's `+` prefix.
+unsafe fn multi_string(ptr: PWSTR) -> Vec<String> {
+    let mut items = Vec::new();
+    if ptr.is_null() {
+        return items;
+    }
+    let mut cursor = ptr.0;
+    while *cursor != 0 {
+        let mut len = 0;
+        while *cursor.add(len) != 0 {
+            len += 1;
+        }
+        items.push(String::from_utf16_lossy(std::slice::from_raw_parts(
+            cursor, len,
+        )));
+        cursor = cursor.add(len + 1);
+    }
+    items
+}
+
+fn trigger_type_name(trigger_type: SERVICE_TRIGGER_TYPE) -> &'static str {
+    match trigger_type {
+        SERVICE_TRIGGER_TYPE_DEVICE_INTERFACE_ARRIVAL => "Device interface arrival",
+        SERVICE_TRIGGER_TYPE_IP_ADDRESS_AVAILABILITY => "IP address availability",
+        SERVICE_TRIGGER_TYPE_DOMAIN_JOIN => "Domain join",
+        SERVICE_TRIGGER_TYPE_FIREWALL_PORT_EVENT => "Firewall port event",
+        SERVICE_TRIGGER_TYPE_GROUP_POLICY => "Group policy",
+        SERVICE_TRIGGER_TYPE_NETWORK_ENDPOINT => "Network endpoint",
+        SERVICE_TRIGGER_TYPE_CUSTOM => "Custom (ETW event)",
+        _ => "Other",
+    }
+}
+
+/// Describe the service's start triggers, e.g. "Start on Device interface arrival".
+unsafe fn query_triggers(service: SC_HANDLE) -> Vec<String> {
+    let mut bytes_needed = 0u32;
+    let _ = QueryServiceConfig2W(
+        service,
+        SERVICE_CONFIG_TRIGGER_INFO,
+        None,
+        &mut bytes_needed,
+    );
+    if bytes_needed == 0 {
+        return Vec::new();
+    }
+
+    // u64 backing keeps SERVICE_TRIGGER_INFO's pointers aligned
+    let mut buf = vec![0u64; (bytes_needed as usize).div_ceil(8)];
+    let bytes = std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, buf.len() * 8);
+    if QueryServiceConfig2W(
+        service,
+        SERVICE_CONFIG_TRIGGER_INFO,
+        Some(bytes),
+        &mut bytes_needed,
+    )
+    .is_err()
+    {
+        return Vec::new();
+    }
+
+    let info = &*(buf.as_ptr() as *const SERVICE_TRIGGER_INFO);
+    if info.pTriggers.is_null() {
+        return Vec::new();
+    }
+    std::slice::from_raw_parts(info.pTriggers, info.cTriggers as usize)
+        .iter()
+        .map(|t| {
+            let action = if t.dwAction == SERVICE_TRIGGER_ACTION_SERVICE_STOP {
+                "Stop"
+            } else {
+                "Start"
+            };
+            format!("{} on {}", action, trigger_type_name(t.dwTriggerType))
+        })
+        .collect()
+}
+
+pub fn check_service() -> Result<Vec<Finding>> {
     print_header("Level 2: WbioSrvc Service Status");
+    let _timer = time_step("Level 2: service");
+    let mut findings = Vec::new();
 
     unsafe {
         // Open the Service Control Manager
@@ -22,14 +103,39 @@ pub fn check_service() -> Result<()> {
             Ok(s) => s,
             Err(e) => {
                 let _ = CloseServiceHandle(scm);
+                crate::error::set_exit_code(crate::error::ExitCode::ServiceNotRunning);
                 print_fail(&format!(
                     "Cannot open WbioSrvc service: {} — is the Biometric Service installed?",
                     e
                 ));
-                return Ok(());
+                findings.push(Finding::critical(
+                    "The Windows Biometric Service (WbioSrvc) is missing",
+                    "This Windows image lacks the biometric framework; use an edition that \
+                     includes Windows Hello",
+                ));
+                return Ok(findings);
             }
         };
 
+        // Query service configuration (two-call buffer pattern)
+        let mut config_buf = Vec::new();
+        let mut bytes_needed = 0u32;
+        let _ = QueryServiceConfigW(service, None, 0, &mut bytes_needed);
+        if bytes_needed > 0 {
+            config_buf = vec![0u8; bytes_needed as usize];
+            let config_ptr = config_buf.as_mut_ptr() as *mut QUERY_SERVICE_CONFIGW;
+            if QueryServiceConfigW(service, Some(config_ptr), bytes_needed, &mut bytes_needed)
+                .is_err()
+            {
+                config_buf.clear();
+            }
+        }
+        let config = (!config_buf.is_empty())
+            .then(|| &*(config_buf.as_ptr() as *const QUERY_SERVICE_CONFIGW));
+        let triggers = query_triggers(service);
+        let start_on_demand =
+            config.is_some_and(|c| c.dwStartType == SERVICE_DEMAND_START) || !triggers.is_empty();
+
         // Query service status
         let mut status = SERVICE_STATUS::default();
         let query_ok = QueryServiceStatus(service, &mut status);
@@ -37,66 +143,88 @@ pub fn check_service() -> Result<()> {
         if let Err(e) = query_ok {
             print_fail(&format!("QueryServiceStatus failed: {}", e));
         } else {
-            let state_str = match status.dwCurrentState {
-                SERVICE_STOPPED => "Stopped",
-                SERVICE_START_PENDING => "Start Pending",
-                SERVICE_STOP_PENDING => "Stop Pending",
-                SERVICE_RUNNING => "Running",
-                SERVICE_CONTINUE_PENDING => "Continue Pending",
-                SERVICE_PAUSE_PENDING => "Pause Pending",
-                SERVICE_PAUSED => "Paused",
-                _ => "Unknown",
-            };
+            let state_str = crate::operations::service::state_name(status.dwCurrentState);
 
             if status.dwCurrentState == SERVICE_RUNNING {
                 print_pass(&format!("WbioSrvc is {}", state_str));
+            } else if status.dwCurrentState == SERVICE_STOPPED
+                && start_on_demand
+                && config.is_none_or(|c| c.dwStartType != SERVICE_DISABLED)
+            {
+                // Trigger/demand-started: WinBio starts it when a device or client needs it
+                print_warn(&format!("WbioSrvc is {}", state_str));
+                print_info(
+                    "  Note",
+                    "Stopped is normal for a Manual/trigger-start service while no biometric \
+                     session is active — it starts when a sensor arrives or an app opens a session",
+                );
             } else {
+                crate::error::set_exit_code(crate::error::ExitCode::ServiceNotRunning);
                 print_fail(&format!("WbioSrvc is {}", state_str));
                 if status.dwCurrentState == SERVICE_STOPPED {
                     print_step("Try: net start WbioSrvc (as Administrator)");
                 }
+                findings.push(Finding::critical(
+                    format!("The Windows Biometric Service is {}", state_str),
+                    "Start it with start-service or diagnose --fix (as Administrator)",
+                ));
             }
         }
 
-        // Query service configuration (two-call buffer pattern)
-        let mut bytes_needed = 0u32;
-        let _ = QueryServiceConfigW(service, None, 0, &mut bytes_needed);
+        if let Some(config) = config {
+            let start_type = match config.dwStartType {
+                SERVICE_AUTO_START => "Automatic",
+                SERVICE_BOOT_START => "Boot",
+                SERVICE_DEMAND_START => "Manual (Demand)",
+                SERVICE_DISABLED => "Disabled",
+                SERVICE_SYSTEM_START => "System",
+                _ => "Unknown",
+            };
 
-        if bytes_needed > 0 {
-            let mut buf = vec![0u8; bytes_needed as usize];
-            let config_ptr = buf.as_mut_ptr() as *mut QUERY_SERVICE_CONFIGW;
-
-            let config_ok =
-                QueryServiceConfigW(service, Some(config_ptr), bytes_needed, &mut bytes_needed);
-
-            if config_ok.is_ok() {
-                let config = &*config_ptr;
-                let start_type = match config.dwStartType {
-                    SERVICE_AUTO_START => "Automatic",
-                    SERVICE_BOOT_START => "Boot",
-                    SERVICE_DEMAND_START => "Manual (Demand)",
-                    SERVICE_DISABLED => "Disabled",
-                    SERVICE_SYSTEM_START => "System",
-                    _ => "Unknown",
-                };
-
-                print_info("Start type", start_type);
-
-                if config.dwStartType == SERVICE_DISABLED {
-                    print_warn("Service is disabled — fingerprint operations will not work");
-                    print_step("Enable via: sc config WbioSrvc start=auto (as Administrator)");
-                }
+            print_info(
+                "Start type",
+                &if triggers.is_empty() {
+                    start_type.to_string()
+                } else {
+                    format!("{} (Trigger Start)", start_type)
+                },
+            );
+
+            if config.dwStartType == SERVICE_DISABLED {
+                print_warn("Service is disabled — fingerprint operations will not work");
+                print_step("Enable via: sc config WbioSrvc start=auto (as Administrator)");
+                findings.push(Finding::critical(
+                    "The Windows Biometric Service is disabled",
+                    "Run sc config WbioSrvc start=auto as Administrator, then start-service",
+                ));
+            }
 
-                if !config.lpBinaryPathName.is_null() {
-                    let path = config.lpBinaryPathName.to_string().unwrap_or_default();
-                    print_info("Binary path", &path);
-                }
+            if !config.lpBinaryPathName.is_null() {
+                let path = config.lpBinaryPathName.to_string().unwrap_or_default();
+                print_info("Binary path", &path);
             }
+
+            let dependencies = multi_string(config.lpDependencies);
+            print_info(
+                "Dependencies",
+                &if dependencies.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    dependencies.join(", ")
+                },
+            );
+        }
+
+        if triggers.is_empty() {
+            print_info("Triggers", "(none — not trigger-started)");
+        }
+        for trigger in &triggers {
+            print_info("Trigger", trigger);
         }
 
         let _ = CloseServiceHandle(service);
         let _ = CloseServiceHandle(scm);
     }
 
-    Ok(())
+    Ok(findings)
 }
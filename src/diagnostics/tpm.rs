@@ -0,0 +1,179 @@
+use serde_json::json;
+use std::process::Command;
+use windows::Win32::System::TpmBaseServices::*;
+
+use super::findings::{Finding, Severity};
+use crate::output::*;
+
+/// TBS_E_TPM_NOT_FOUND
+const TBS_E_TPM_NOT_FOUND: u32 = 0x8028_400F;
+
+/// TPM state as reported by PowerShell's `Get-Tpm` (requires elevation).
+struct TpmState {
+    ready: bool,
+    enabled: bool,
+    activated: bool,
+    owned: bool,
+    locked_out: bool,
+    manufacturer: String,
+}
+
+fn tpm_version_name(version: u32) -> &'static str {
+    match version {
+        TPM_VERSION_12 => "1.2",
+        TPM_VERSION_20 => "2.0",
+        _ => "unknown",
+    }
+}
+
+/// Ask the TPM Base Services for the TPM's spec version. `Ok(None)` means
+/// no TPM; `Err` carries any other TBS result code.
+fn tbs_device_version() -> Result<Option<u32>, u32> {
+    let mut info = TPM_DEVICE_INFO::default();
+    let result = unsafe {
+        Tbsi_GetDeviceInfo(
+            std::mem::size_of::<TPM_DEVICE_INFO>() as u32,
+            &mut info as *mut _ as *mut _,
+        )
+    };
+    match result {
+        TBS_SUCCESS => Ok(Some(info.tpmVersion)),
+        TBS_E_TPM_NOT_FOUND => Ok(None),
+        other => Err(other),
+    }
+}
+
+/// Readiness, enablement, and ownership via `Get-Tpm`. None when the cmdlet
+/// fails, which it does without Administrator rights.
+fn query_tpm_state() -> Option<TpmState> {
+    let _timer = time_step("Get-Tpm (PowerShell)");
+    let ps_script = r#"
+        try {
+            Get-Tpm -ErrorAction Stop |
+                Select-Object TpmReady, TpmEnabled, TpmActivated, TpmOwned, LockedOut, ManufacturerIdTxt |
+                ConvertTo-Json -Compress
+        } catch {
+            # Access denied without elevation — return nothing
+        }
+    "#;
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", ps_script])
+        .output()
+        .ok()?;
+    let value: serde_json::Value =
+        serde_json::from_str(String::from_utf8_lossy(&output.stdout).trim()).ok()?;
+    let flag = |name: &str| value[name].as_bool().unwrap_or(false);
+    Some(TpmState {
+        ready: flag("TpmReady"),
+        enabled: flag("TpmEnabled"),
+        activated: flag("TpmActivated"),
+        owned: flag("TpmOwned"),
+        locked_out: flag("LockedOut"),
+        manufacturer: value["ManufacturerIdTxt"]
+            .as_str()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    })
+}
+
+/// Check that a TPM is present and usable. Windows Hello for Business keeps
+/// its sign-in keys in the TPM, so a disabled, unowned, or locked-out TPM
+/// fails fingerprint sign-in even when the sensor matches perfectly. With
+/// `detailed` false (as run by `diagnose`), the version and manufacturer lines
+/// are left out.
+pub fn check_tpm(detailed: bool) -> Vec<Finding> {
+    print_header("TPM (Windows Hello Key Storage)");
+    let _timer = time_step("TPM check");
+
+    let version = match tbs_device_version() {
+        Ok(Some(version)) => version,
+        Ok(None) => {
+            print_warn("No TPM found");
+            record("tpm", json!({ "present": false }));
+            return vec![Finding::warning(
+                "No TPM found — Windows Hello for Business cannot protect its keys",
+                "Enable the TPM (PTT / fTPM / Security Chip) in the firmware setup",
+            )];
+        }
+        Err(code) => {
+            print_warn(&format!(
+                "Could not query the TPM: TBS error 0x{:08X}",
+                code
+            ));
+            return Vec::new();
+        }
+    };
+
+    print_pass(&format!("TPM {} present", tpm_version_name(version)));
+
+    let Some(state) = query_tpm_state() else {
+        record(
+            "tpm",
+            json!({ "present": true, "version": tpm_version_name(version) }),
+        );
+        print_step("Run as Administrator to check TPM readiness and ownership");
+        return Vec::new();
+    };
+    if detailed && !state.manufacturer.is_empty() {
+        print_info("Manufacturer", &state.manufacturer);
+    }
+    record(
+        "tpm",
+        json!({
+            "present": true,
+            "version": tpm_version_name(version),
+            "ready": state.ready,
+            "enabled": state.enabled,
+            "activated": state.activated,
+            "owned": state.owned,
+            "locked_out": state.locked_out,
+        }),
+    );
+
+    let problems = [
+        (
+            !state.enabled || !state.activated,
+            Severity::Critical,
+            "TPM is disabled or not activated",
+            "Enable and activate the TPM in the firmware setup, then set up Windows Hello again",
+        ),
+        (
+            state.locked_out,
+            Severity::Critical,
+            "TPM is in dictionary-attack lockout",
+            "Wait for the lockout to expire, or reset it in tpm.msc (Administrator)",
+        ),
+        (
+            !state.owned,
+            Severity::Warning,
+            "TPM is not owned — Windows has not taken ownership yet",
+            "Open tpm.msc and choose Prepare the TPM, then restart",
+        ),
+        (
+            !state.ready,
+            Severity::Warning,
+            "TPM is not ready for use",
+            "Open tpm.msc to see what it needs; a pending restart or firmware update is common",
+        ),
+    ];
+
+    let mut findings = Vec::new();
+    for (failed, severity, problem, action) in problems {
+        if !failed {
+            continue;
+        }
+        if severity == Severity::Critical {
+            print_fail(problem);
+        } else {
+            print_warn(problem);
+        }
+        findings.push(Finding::new(severity, problem, action));
+    }
+    if findings.is_empty() {
+        print_pass("TPM is enabled, owned, and ready");
+    } else if detailed {
+        print_step("A broken TPM fails Windows Hello sign-in even when the fingerprint matches");
+    }
+    findings
+}
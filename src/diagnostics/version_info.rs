@@ -0,0 +1,150 @@
+use anyhow::Result;
+use serde_json::json;
+use std::path::PathBuf;
+use windows::core::{w, HSTRING};
+use windows::Win32::Storage::FileSystem::*;
+use windows::Win32::System::Registry::*;
+
+use crate::operations::enum_databases::{adapter_binaries, read_registry_string};
+use crate::output::*;
+
+/// Windows product name and build (`CurrentBuild.UBR`) from the registry.
+fn os_version() -> (String, String, String) {
+    unsafe {
+        let mut hkey = HKEY::default();
+        let status = RegOpenKeyExW(
+            HKEY_LOCAL_MACHINE,
+            w!("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion"),
+            None,
+            KEY_READ,
+            &mut hkey,
+        );
+        if status.is_err() {
+            return Default::default();
+        }
+
+        let product = read_registry_string(hkey, "ProductName").unwrap_or_default();
+        let display = read_registry_string(hkey, "DisplayVersion").unwrap_or_default();
+        let build = read_registry_string(hkey, "CurrentBuild").unwrap_or_default();
+        let build = match read_registry_string(hkey, "UBR") {
+            Some(ubr) => format!("{}.{}", build, ubr),
+            None => build,
+        };
+        let _ = RegCloseKey(hkey);
+        (product, display, build)
+    }
+}
+
+/// Resolve an adapter binary from the WinBio configuration to a full path.
+/// Bare names live in System32 or System32\WinBioPlugins; full paths may use
+/// `%SystemRoot%`.
+pub fn resolve_system_binary(binary: &str) -> Option<PathBuf> {
+    let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_string());
+    if binary.contains('\\') {
+        let expanded = binary
+            .replace("%SystemRoot%", &system_root)
+            .replace("%SYSTEMROOT%", &system_root)
+            .replace("%windir%", &system_root);
+        return Some(PathBuf::from(expanded)).filter(|p| p.exists());
+    }
+
+    let system32 = PathBuf::from(&system_root).join("System32");
+    [
+        system32.join(binary),
+        system32.join("WinBioPlugins").join(binary),
+    ]
+    .into_iter()
+    .find(|p| p.exists())
+}
+
+/// `FileVersion` from a binary's fixed version resource, e.g. `10.0.22621.1`.
+fn file_version(path: &std::path::Path) -> Option<String> {
+    unsafe {
+        let name = HSTRING::from(path.as_os_str());
+        let size = GetFileVersionInfoSizeW(&name, None);
+        if size == 0 {
+            return None;
+        }
+        let mut data = vec![0u8; size as usize];
+        GetFileVersionInfoW(&name, None, size, data.as_mut_ptr() as *mut _).ok()?;
+
+        let mut info: *mut std::ffi::c_void = std::ptr::null_mut();
+        let mut len = 0u32;
+        if !VerQueryValueW(data.as_ptr() as *const _, w!("\\"), &mut info, &mut len).as_bool()
+            || info.is_null()
+            || (len as usize) < std::mem::size_of::<VS_FIXEDFILEINFO>()
+        {
+            return None;
+        }
+        let info = &*(info as *const VS_FIXEDFILEINFO);
+        Some(format!(
+            "{}.{}.{}.{}",
+            info.dwFileVersionMS >> 16,
+            info.dwFileVersionMS & 0xFFFF,
+            info.dwFileVersionLS >> 16,
+            info.dwFileVersionLS & 0xFFFF
+        ))
+    }
+}
+
+fn print_binary(label: &str, binary: &str) {
+    let path = resolve_system_binary(binary);
+    let version = path.as_deref().and_then(file_version);
+    match &path {
+        Some(p) => print_info(
+            label,
+            &format!(
+                "{} ({})",
+                p.display(),
+                version.as_deref().unwrap_or("no version resource")
+            ),
+        ),
+        None => print_warn(&format!("{}: {} not found", label.trim(), binary)),
+    }
+    record(
+        "binaries",
+        json!({
+            "name": binary,
+            "path": path.as_ref().map(|p| p.to_string_lossy()),
+            "file_version": version,
+        }),
+    );
+}
+
+/// Print the environment fingerprint for bug reports: tool version, Windows
+/// build, WinBio version, and the adapter DLLs the sensors are configured with.
+pub fn run_version_info() -> Result<()> {
+    print_header("Version Info");
+
+    print_info("win-fp-debug", env!("CARGO_PKG_VERSION"));
+
+    let (product, display, build) = os_version();
+    print_info("Windows", format!("{} {}", product, display).trim());
+    print_info(
+        "OS build",
+        if build.is_empty() {
+            "(unknown)"
+        } else {
+            &build
+        },
+    );
+    record(
+        "os",
+        json!({ "product": product, "display_version": display, "build": build }),
+    );
+
+    // WinBio has no version API; winbio.dll's file version tracks the OS servicing level
+    print_binary("WinBio (winbio.dll)", "winbio.dll");
+
+    print_blank();
+    print_step("Adapter binaries from the WinBio sensor configurations...");
+    let binaries = adapter_binaries();
+    if binaries.is_empty() {
+        print_warn("No WinBio sensor configurations found in the registry");
+    }
+    for binary in &binaries {
+        print_binary("  Adapter", binary);
+    }
+
+    Ok(())
+}
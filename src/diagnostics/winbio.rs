@@ -1,20 +1,72 @@
 use anyhow::Result;
+use serde_json::json;
 use std::process::Command;
+use std::time::Duration;
 use windows::Win32::Devices::BiometricFramework::*;
 
+use super::findings::Finding;
 use crate::output::*;
 use crate::winbio_helpers::*;
 
-pub fn check_sensor() -> Result<()> {
+/// Event IDs `check_winbio_events` reports by default: WbioSrvc logs these to
+/// Microsoft-Windows-Biometrics/Operational when a biometric unit, one of its
+/// adapters, or its database fails to load or initialize.
+const KNOWN_FAILURE_EVENT_IDS: &[u32] = &[1105, 1106, 1107, 1108, 1109, 1110];
+
+/// For each unit, the ID of an earlier unit reporting the same serial number
+/// or device instance ID (the same physical reader enumerated twice, as seen
+/// behind some docks).
+fn find_duplicate_units(units: &[WINBIO_UNIT_SCHEMA]) -> Vec<Option<u32>> {
+    let key = |u: &WINBIO_UNIT_SCHEMA| {
+        (
+            wchar_to_string(&u.SerialNumber),
+            wchar_to_string(&u.DeviceInstanceId).to_uppercase(),
+        )
+    };
+    units
+        .iter()
+        .enumerate()
+        .map(|(i, unit)| {
+            let (serial, instance) = key(unit);
+            units[..i]
+                .iter()
+                .find(|earlier| {
+                    let (s, d) = key(earlier);
+                    (!serial.is_empty() && s == serial) || (!instance.is_empty() && d == instance)
+                })
+                .map(|earlier| earlier.UnitId)
+        })
+        .collect()
+}
+
+/// `unique` hides units that duplicate an earlier one; `instance_id` limits
+/// the report to units of matching devices (`--instance-id`). `all_pools`
+/// tallies units per pool, hides units that duplicate one in the other pool,
+/// and tests a session on every pool that has units instead of just `pool`.
+pub fn check_sensor(
+    pool: SensorPool,
+    since: Option<Duration>,
+    event_ids: &[u32],
+    unique: bool,
+    instance_id: Option<&str>,
+    all_pools: bool,
+) -> Result<Vec<Finding>> {
     print_header("Level 3: WinBio Sensor Enumeration");
+    let _timer = time_step("Level 3: sensor");
+    let mut findings = Vec::new();
 
     unsafe {
         // Enumerate biometric units
         let mut unit_array: *mut WINBIO_UNIT_SCHEMA = std::ptr::null_mut();
         let mut unit_count: usize = 0;
 
-        let result =
-            WinBioEnumBiometricUnits(WINBIO_TYPE_FINGERPRINT, &mut unit_array, &mut unit_count);
+        let enum_timer = time_step("WinBioEnumBiometricUnits");
+        let result = trace_call(
+            "WinBioEnumBiometricUnits",
+            &format!("factor={}", biometric_type().name()),
+            WinBioEnumBiometricUnits(biometric_type().factor(), &mut unit_array, &mut unit_count),
+        );
+        drop(enum_timer);
 
         if let Err(e) = result {
             print_fail(&format!(
@@ -22,27 +74,67 @@ pub fn check_sensor() -> Result<()> {
                 crate::error::hresult_message(e.code()),
                 e.code().0 as u32
             ));
-            return Ok(());
+            findings.push(Finding::critical(
+                "WinBio cannot enumerate fingerprint sensors",
+                "Make sure WbioSrvc is running (start-service), then re-run diagnose",
+            ));
+            return Ok(findings);
         }
 
-        if unit_count == 0 {
-            print_fail("No fingerprint biometric units found");
+        if unit_count == 0 || unit_array.is_null() {
+            crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
+            print_fail(&format!(
+                "No {} biometric units found",
+                biometric_type().name()
+            ));
             winbio_free(unit_array as *const _);
 
             // Run follow-up diagnostics to surface the root cause
-            println!();
-            check_winbio_events();
-            check_database_config();
+            print_blank();
+            findings.extend(check_winbio_events(since, event_ids));
+            let database_findings = check_database_config();
+            if database_findings.is_empty() {
+                findings.push(Finding::critical(
+                    "WinBio sees no fingerprint sensor",
+                    "Reinstall the sensor driver with reinstall-driver; check the event log \
+                     errors above for the failing adapter",
+                ));
+            }
+            findings.extend(database_findings);
 
-            return Ok(());
+            return Ok(findings);
         }
 
         print_pass(&format!("Found {} biometric unit(s)", unit_count));
 
         let units = std::slice::from_raw_parts(unit_array, unit_count);
-
+        let duplicates = find_duplicate_units(units);
+        // Duplicates whose original was enumerated in the other pool
+        let cross_pool: Vec<bool> = units
+            .iter()
+            .zip(&duplicates)
+            .map(|(unit, original)| {
+                original.is_some_and(|id| {
+                    units
+                        .iter()
+                        .any(|u| u.UnitId == id && u.PoolType != unit.PoolType)
+                })
+            })
+            .collect();
+
+        let mut shown = 0;
         for (i, unit) in units.iter().enumerate() {
-            println!();
+            if duplicates[i].is_some() && (unique || (all_pools && cross_pool[i])) {
+                continue;
+            }
+            if !crate::pnp::instance_id_matches(
+                instance_id,
+                &wchar_to_string(&unit.DeviceInstanceId),
+            ) {
+                continue;
+            }
+            shown += 1;
+            print_blank();
             print_info(&format!("  Unit {}", i + 1), "");
             print_info("    Unit ID", &unit.UnitId.to_string());
             print_info(
@@ -80,54 +172,243 @@ pub fn check_sensor() -> Result<()> {
                 if serial.is_empty() { "(none)" } else { &serial },
             );
             print_info("    Firmware version", &firmware);
+            let instance_id = wchar_to_string(&unit.DeviceInstanceId);
+            print_info("    Device instance", &instance_id);
+            if let Some(original) = duplicates[i] {
+                print_warn(&format!(
+                    "Unit {} is the same physical device as unit {} (same serial/device instance)",
+                    unit.UnitId, original
+                ));
+            }
+
+            record(
+                "units",
+                json!({
+                    "unit_id": unit.UnitId,
+                    "pool_type": unit.PoolType,
+                    "biometric_factor": unit.BiometricFactor,
+                    "sensor_subtype": sensor_subtype_name(unit.SensorSubType),
+                    "capabilities": unit.Capabilities,
+                    "description": description,
+                    "manufacturer": manufacturer,
+                    "model": model,
+                    "serial_number": serial,
+                    "firmware_version": firmware,
+                    "device_instance_id": instance_id,
+                    "duplicate_of": duplicates[i],
+                }),
+            );
         }
 
+        if let (Some(filter), 0) = (instance_id, shown) {
+            print_blank();
+            print_warn(&format!(
+                "None of the {} unit(s) belongs to a device whose instance ID starts with '{}'",
+                unit_count, filter
+            ));
+        }
+
+        let overlap_count = if all_pools {
+            cross_pool.iter().filter(|&&c| c).count()
+        } else {
+            0
+        };
+        if all_pools {
+            print_blank();
+            for (name, pool_type) in [
+                ("System", WINBIO_POOL_SYSTEM.0),
+                ("Private", WINBIO_POOL_PRIVATE.0),
+            ] {
+                let count = units.iter().filter(|u| u.PoolType == pool_type).count();
+                print_info(&format!("{} pool units", name), &count.to_string());
+                record("pools", json!({ "pool": name, "units": count }));
+            }
+            if overlap_count > 0 {
+                print_warn(&format!(
+                    "{} unit(s) appear in both pools (same serial/device instance); shown once",
+                    overlap_count
+                ));
+            }
+        }
+
+        let duplicate_count = duplicates.iter().flatten().count() - overlap_count;
+        if duplicate_count > 0 {
+            print_blank();
+            if unique {
+                print_info("Duplicate units hidden", &duplicate_count.to_string());
+            } else {
+                print_warn(&format!(
+                    "{} duplicate unit(s): commands without --unit may pick either copy",
+                    duplicate_count
+                ));
+                findings.push(Finding::info(
+                    format!("{} sensor(s) are enumerated twice", duplicate_count),
+                    "Pass --unit <id> to target one reader",
+                ));
+                print_step(
+                    "Pass --unit <id> to target one reader; --unique hides the duplicates here",
+                );
+            }
+        }
+
+        let mut pools = vec![pool];
+        if all_pools {
+            pools = vec![SensorPool::System];
+            if units.iter().any(|u| u.PoolType == WINBIO_POOL_PRIVATE.0) {
+                pools.push(SensorPool::Private);
+            }
+        }
         winbio_free(unit_array as *const _);
 
-        // Test session open/close
-        println!();
-        print_step("Testing WinBio session open/close...");
-        match open_session(WINBIO_FLAG_DEFAULT) {
-            Ok(session) => {
-                print_pass("WinBioOpenSession succeeded");
-                close_session(session);
-                print_pass("WinBioCloseSession succeeded");
+        for pool in pools {
+            // Test session open/close
+            print_blank();
+            if all_pools {
+                print_step(&format!(
+                    "Testing WinBio session open/close ({:?} pool)...",
+                    pool
+                ));
+            } else {
+                print_step("Testing WinBio session open/close...");
             }
-            Err(e) => {
-                print_fail(&format!("WinBioOpenSession failed: {}", e));
+            match open_session(WINBIO_FLAG_DEFAULT, pool) {
+                Ok(session) => {
+                    print_pass("WinBioOpenSession succeeded");
+                    close_session(session);
+                    print_pass("WinBioCloseSession succeeded");
+                    findings.extend(check_sensor_lock(pool));
+                }
+                Err(e) => {
+                    print_fail(&format!("WinBioOpenSession failed: {}", e));
+                    findings.push(Finding::critical(
+                        format!("Cannot open a WinBio session: {}", e),
+                        "Restart the service with restart-service; if it persists, reinstall \
+                         the driver",
+                    ));
+                }
             }
         }
     }
 
-    Ok(())
+    Ok(findings)
+}
+
+/// How long `check_sensor_lock` waits in `WinBioLocateSensor` before cancelling.
+const LOCK_PROBE_SECS: u64 = 2;
+
+/// Probe whether another process (usually Windows Hello on the lock screen or
+/// sign-in UI) holds the sensor. A held sensor fails `WinBioLocateSensor` with
+/// DEVICE_BUSY / SESSION_BUSY; a free one blocks waiting for a touch until the
+/// probe cancels it.
+fn check_sensor_lock(pool: SensorPool) -> Option<Finding> {
+    let _timer = time_step("Sensor lock probe");
+    print_step("Checking whether the sensor is held by another process...");
+
+    let guard = match crate::operations::SessionGuard::new(WINBIO_FLAG_DEFAULT, pool, true) {
+        Ok(guard) => guard,
+        Err(e) => {
+            print_warn(&format!("Could not open a session for the probe: {}", e));
+            return None;
+        }
+    };
+
+    let result = guard.run_with_timeout(Some(LOCK_PROBE_SECS), |session| {
+        let mut unit_id = 0u32;
+        unsafe {
+            trace_call(
+                "WinBioLocateSensor",
+                &format!("session={}", session),
+                WinBioLocateSensor(session, Some(&mut unit_id)),
+            )
+        }
+        .map(|()| unit_id)
+    });
+
+    match result {
+        // Deadline passed without a busy error: the sensor is waiting for a touch
+        Err(_) => print_pass("Sensor is free (waiting for a touch, not held by another process)"),
+        Ok(Ok(unit_id)) => print_pass(&format!("Sensor responded (touch on unit {})", unit_id)),
+        Ok(Err(e)) => {
+            let code = crate::error::error_code(&e);
+            if matches!(code, 0x8009_8010 | 0x8009_802D) {
+                // WINBIO_E_DEVICE_BUSY, WINBIO_E_SESSION_BUSY
+                print_fail("Sensor appears locked by another process (likely Windows Hello)");
+                print_step(
+                    "Close the lock screen or any Windows Hello prompt, or sign out and back in",
+                );
+                print_step(
+                    "Then re-run this check; use --retries to wait longer in other commands",
+                );
+                return Some(Finding::warning(
+                    "The sensor is held by another process (likely Windows Hello)",
+                    "Close the lock screen or any Windows Hello prompt, then re-run diagnose",
+                ));
+            } else {
+                print_warn(&format!(
+                    "WinBioLocateSensor failed: {} (0x{:08X})",
+                    crate::error::hresult_message(e.code()),
+                    code
+                ));
+            }
+        }
+    }
+    None
 }
 
 /// Check the WinBio operational event log for recent configuration errors.
-fn check_winbio_events() {
+/// `since` limits the lookback; `event_ids` overrides `KNOWN_FAILURE_EVENT_IDS`.
+pub(crate) fn check_winbio_events(since: Option<Duration>, event_ids: &[u32]) -> Vec<Finding> {
     print_step("Checking WinBio event log...");
 
-    let ps_script = r#"
-        try {
-            $events = Get-WinEvent -LogName 'Microsoft-Windows-Biometrics/Operational' -MaxEvents 20 -ErrorAction Stop
-            $errors = $events | Where-Object { $_.Id -in @(1106, 1109) }
-            $errors | ForEach-Object {
-                [PSCustomObject]@{
-                    Id      = [int]$_.Id
-                    Level   = [int]$_.Level
-                    Message = [string]$_.Message
-                }
-            } | ConvertTo-Json -Compress
-        } catch {
-            # Log may not exist or be inaccessible — silently return nothing
-        }
-    "#;
+    let ids = if event_ids.is_empty() {
+        KNOWN_FAILURE_EVENT_IDS
+    } else {
+        event_ids
+    };
+    let id_list = ids
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    // Without a lookback only the most recent matches are interesting
+    let (start_time, max_events) = match since {
+        Some(d) => (
+            format!("; StartTime = (Get-Date).AddSeconds(-{})", d.as_secs()),
+            "",
+        ),
+        None => (String::new(), "-MaxEvents 20"),
+    };
 
+    let ps_script = format!(
+        r#"
+        try {{
+            $filter = @{{ LogName = 'Microsoft-Windows-Biometrics/Operational'; Id = @({ids}){start_time} }}
+            $events = Get-WinEvent -FilterHashtable $filter {max_events} -ErrorAction Stop
+            $events | ForEach-Object {{
+                [PSCustomObject]@{{
+                    Id          = [int]$_.Id
+                    Level       = [int]$_.Level
+                    TimeCreated = $_.TimeCreated.ToUniversalTime().ToString('yyyy-MM-dd HH:mm:ss') + ' UTC'
+                    Message     = [string]$_.Message
+                }}
+            }} | ConvertTo-Json -Compress
+        }} catch {{
+            # Log may not exist, be inaccessible, or have no matching events — silently return nothing
+        }}
+    "#,
+        ids = id_list,
+        start_time = start_time,
+        max_events = max_events,
+    );
+
+    let _timer = time_step("Event log query (PowerShell)");
     let output = match Command::new("powershell")
-        .args(["-NoProfile", "-NonInteractive", "-Command", ps_script])
+        .args(["-NoProfile", "-NonInteractive", "-Command", &ps_script])
         .output()
     {
         Ok(o) => o,
-        Err(_) => return,
+        Err(_) => return Vec::new(),
     };
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -135,18 +416,18 @@ fn check_winbio_events() {
 
     if stdout.is_empty() {
         print_pass("No WinBio configuration errors in event log");
-        return;
+        return Vec::new();
     }
 
     let events: Vec<serde_json::Value> = if stdout.starts_with('[') {
         match serde_json::from_str(stdout) {
             Ok(v) => v,
-            Err(_) => return,
+            Err(_) => return Vec::new(),
         }
     } else {
         match serde_json::from_str(stdout) {
             Ok(v) => vec![v],
-            Err(_) => return,
+            Err(_) => return Vec::new(),
         }
     };
 
@@ -160,131 +441,121 @@ fn check_winbio_events() {
             .next()
             .unwrap_or("(no message)");
 
-        let line = format!("Event {}: {}", id, message);
+        let time = event["TimeCreated"].as_str().unwrap_or("(unknown time)");
+
+        let line = format!("{}  Event {}: {}", time, id, message);
         if level <= 2 {
             print_fail(&line);
         } else {
             print_warn(&line);
         }
     }
+
+    vec![Finding::warning(
+        format!("{} WinBio error event(s) in the event log", events.len()),
+        "Read the events above: they name the adapter or database that failed to load",
+    )]
 }
 
 /// Check each biometric device's WinBio DatabaseId references against registered databases.
-fn check_database_config() {
-    println!();
-    print_step("Checking device database configuration...");
+pub fn check_database_config() -> Vec<Finding> {
+    let _timer = time_step("Database configuration (registry)");
+    use crate::operations::enum_databases::{device_database_ids, registered_database_ids};
 
-    // This script:
-    // 1. Gets all biometric PnP devices
-    // 2. For each, reads WinBio\Configurations\*\DatabaseId values
-    // 3. Lists registered databases under WbioSrvc\Databases
-    // 4. Outputs a JSON structure with the comparison results
-    let ps_script = r#"
-        $dbBasePath = 'HKLM:\SYSTEM\CurrentControlSet\Services\WbioSrvc\Databases'
-        $registeredDbs = @()
-        if (Test-Path $dbBasePath) {
-            $registeredDbs = Get-ChildItem $dbBasePath -ErrorAction SilentlyContinue |
-                ForEach-Object { $_.PSChildName.ToLower() }
-        }
-
-        $devs = Get-PnpDevice -Class Biometric -ErrorAction SilentlyContinue
-        if ($null -eq $devs) { exit 0 }
-
-        $results = @()
-        foreach ($dev in $devs) {
-            $id = [string]$dev.InstanceId
-            $name = [string]$dev.FriendlyName
-            $configBase = "HKLM:\SYSTEM\CurrentControlSet\Enum\$id\Device Parameters\WinBio\Configurations"
-            if (-not (Test-Path $configBase)) { continue }
-
-            $configs = @()
-            Get-ChildItem $configBase -ErrorAction SilentlyContinue | ForEach-Object {
-                $configName = $_.PSChildName
-                $dbId = (Get-ItemProperty -Path $_.PSPath -Name 'DatabaseId' -ErrorAction SilentlyContinue).DatabaseId
-                if ($dbId) {
-                    $dbIdLower = $dbId.ToLower()
-                    $registered = $registeredDbs -contains $dbIdLower
-                    $configs += [PSCustomObject]@{
-                        ConfigName = [string]$configName
-                        DatabaseId = [string]$dbId
-                        Registered = [bool]$registered
-                    }
-                }
-            }
-
-            if ($configs.Count -gt 0) {
-                $results += [PSCustomObject]@{
-                    FriendlyName = $name
-                    InstanceId   = $id
-                    Configurations = $configs
-                }
-            }
-        }
-
-        if ($results.Count -gt 0) {
-            $results | ConvertTo-Json -Depth 4 -Compress
-        }
-    "#;
+    print_blank();
+    print_step("Checking device database configuration...");
 
-    let output = match Command::new("powershell")
-        .args(["-NoProfile", "-NonInteractive", "-Command", ps_script])
-        .output()
-    {
-        Ok(o) => o,
+    let devices = match crate::pnp::enum_biometric_devices() {
+        Ok(d) => d,
         Err(e) => {
-            print_fail(&format!("Failed to run PowerShell: {}", e));
-            return;
-        }
-    };
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stdout = stdout.trim();
-
-    if stdout.is_empty() {
-        print_step("No biometric devices with WinBio configuration found");
-        return;
-    }
-
-    let devices: Vec<serde_json::Value> = if stdout.starts_with('[') {
-        match serde_json::from_str(stdout) {
-            Ok(v) => v,
-            Err(_) => return,
-        }
-    } else {
-        match serde_json::from_str(stdout) {
-            Ok(v) => vec![v],
-            Err(_) => return,
+            print_fail(&format!("Failed to enumerate biometric devices: {}", e));
+            return Vec::new();
         }
     };
+    let registered = registered_database_ids();
 
+    let mut any_device = false;
     let mut any_mismatch = false;
+    let mut findings = Vec::new();
 
     for dev in &devices {
-        let name = dev["FriendlyName"].as_str().unwrap_or("(unknown)");
-        let instance_id = dev["InstanceId"].as_str().unwrap_or("(unknown)");
-        println!("  Device: {} ({})", name, instance_id);
-
-        let configs = match dev["Configurations"].as_array() {
-            Some(c) => c,
-            None => continue,
-        };
-
-        for config in configs {
-            let config_name = config["ConfigName"].as_str().unwrap_or("?");
-            let db_id = config["DatabaseId"].as_str().unwrap_or("?");
-            let registered = config["Registered"].as_bool().unwrap_or(false);
+        let configs = device_database_ids(&dev.instance_id);
+        if configs.is_empty() {
+            continue;
+        }
+        any_device = true;
+        print_info(
+            "  Device",
+            &format!("{} ({})", dev.friendly_name, dev.instance_id),
+        );
 
-            println!("    Configuration {} DatabaseId: {}", config_name, db_id);
-            if registered {
+        for (config_name, db_id) in &configs {
+            print_info(
+                &format!("    Configuration {} DatabaseId", config_name),
+                db_id,
+            );
+            if registered.contains(db_id) {
                 print_pass("    Registered in WbioSrvc\\Databases");
             } else {
                 print_fail("    Not registered in WbioSrvc\\Databases");
                 any_mismatch = true;
+                findings.push(Finding::critical(
+                    format!(
+                        "{} uses database {} which is not registered with WbioSrvc",
+                        dev.friendly_name, db_id
+                    ),
+                    "Reinstall the driver (reinstall-driver, or diagnose --fix) so its INF \
+                     recreates the database entry",
+                ));
             }
         }
+
+        let distinct: std::collections::BTreeSet<&str> =
+            configs.iter().map(|(_, db_id)| db_id.as_str()).collect();
+        if distinct.len() > 1 {
+            let ids: Vec<&str> = distinct.into_iter().collect();
+            print_warn(&format!(
+                "    Configurations reference {} different databases: {}",
+                ids.len(),
+                ids.join(", ")
+            ));
+            findings.push(Finding::warning(
+                format!(
+                    "{} has WinBio configurations for {} different databases ({}); only the \
+                     active configuration's database is used, the others are orphaned",
+                    dev.friendly_name,
+                    ids.len(),
+                    ids.join(", ")
+                ),
+                "Reinstall the driver (reinstall-driver) to rewrite its configurations, then \
+                 enroll again",
+            ));
+        }
     }
 
+    if !any_device {
+        print_step("No biometric devices with WinBio configuration found");
+    }
     if any_mismatch {
         print_step("Reinstall the fingerprint sensor driver to recreate missing database entries");
     }
+    findings
+}
+
+/// Check that a Windows Hello password credential is linked to the logged-in
+/// user. Without one, an enrolled finger is recognized but cannot sign in.
+pub fn check_credential() -> Result<Vec<Finding>> {
+    print_blank();
+    print_step("Checking credential state for the current user...");
+    let sid = crate::elevation::current_user_sid()?;
+    let identity = sid_identity(&sid)
+        .ok_or_else(|| anyhow::anyhow!("Current user SID is too large for WinBio"))?;
+    if crate::operations::credential_state::check_credential_state(identity)? {
+        return Ok(Vec::new());
+    }
+    Ok(vec![Finding::warning(
+        "No Windows Hello password credential is linked to your account, so fingerprint \
+         sign-in will fail even when the finger is recognized",
+        "Settings > Accounts > Sign-in options: remove the fingerprint and set it up again",
+    )])
 }
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use windows::Win32::Foundation::{CloseHandle, HANDLE};
-use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+use windows::Win32::Security::{
+    GetLengthSid, GetTokenInformation, TokenElevation, TokenUser, TOKEN_ELEVATION, TOKEN_QUERY,
+    TOKEN_USER,
+};
 use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
 
 /// Returns true if the current process is running elevated (as Administrator).
@@ -28,6 +31,45 @@ pub fn is_elevated() -> Result<bool> {
     }
 }
 
+/// Returns the binary SID of the user this process runs as (from `TokenUser`).
+pub fn current_user_sid() -> Result<Vec<u8>> {
+    unsafe {
+        let mut token_handle = HANDLE::default();
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token_handle)?;
+
+        // First call only reports the required size (TOKEN_USER plus the SID it points to)
+        let mut return_length = 0u32;
+        let _ = GetTokenInformation(token_handle, TokenUser, None, 0, &mut return_length);
+
+        // u64 storage keeps the buffer aligned for TOKEN_USER
+        let mut buf = vec![0u64; (return_length as usize).div_ceil(8)];
+        let result = GetTokenInformation(
+            token_handle,
+            TokenUser,
+            Some(buf.as_mut_ptr() as *mut _),
+            return_length,
+            &mut return_length,
+        );
+
+        let _ = CloseHandle(token_handle);
+        result?;
+
+        let token_user = &*(buf.as_ptr() as *const TOKEN_USER);
+        let sid = token_user.User.Sid;
+        let len = GetLengthSid(sid) as usize;
+        Ok(std::slice::from_raw_parts(sid.0 as *const u8, len).to_vec())
+    }
+}
+
+/// Fail with `ExitCode::NotElevated` unless running as Administrator.
+pub fn require_elevated() -> Result<()> {
+    if is_elevated()? {
+        return Ok(());
+    }
+    crate::error::set_exit_code(crate::error::ExitCode::NotElevated);
+    anyhow::bail!("This command requires Administrator privileges. Re-run as Administrator.");
+}
+
 /// Print a warning if not running as admin.
 pub fn warn_if_not_elevated() {
     match is_elevated() {
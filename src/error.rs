@@ -1,55 +1,259 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicI32, Ordering};
+
 use windows::core::HRESULT;
 
-/// Translate common WinBio / HRESULT codes into human-readable strings.
-pub fn hresult_message(hr: HRESULT) -> &'static str {
+/// Translate WinBio / HRESULT codes into human-readable strings.
+/// Covers the WINBIO_E_* / WINBIO_I_* codes from winbio_err.h; anything else
+/// falls back to the system message table (FormatMessageW via `HRESULT::message`).
+pub fn hresult_message(hr: HRESULT) -> Cow<'static, str> {
     match hr.0 as u32 {
         // Generic success / failure
-        0x0000_0000 => "Success (S_OK)",
-        0x8007_0005 => "Access denied (E_ACCESSDENIED)",
-        0x8000_4005 => "Unspecified error (E_FAIL)",
-        0x8000_4002 => "No such interface (E_NOINTERFACE)",
-        0x8007_0057 => "Invalid argument (E_INVALIDARG)",
-        0x8000_FFFF => "Catastrophic failure (E_UNEXPECTED)",
+        0x0000_0000 => "Success (S_OK)".into(),
+        0x8007_0005 => "Access denied (E_ACCESSDENIED)".into(),
+        0x8000_4005 => "Unspecified error (E_FAIL)".into(),
+        0x8000_4002 => "No such interface (E_NOINTERFACE)".into(),
+        0x8007_0057 => "Invalid argument (E_INVALIDARG)".into(),
+        0x8000_FFFF => "Catastrophic failure (E_UNEXPECTED)".into(),
 
         // WinBio-specific (WINBIO_E_*)
-        0x8009_8001 => "Sensor not calibrated (WINBIO_E_UNSUPPORTED_FACTOR)",
-        0x8009_8002 => "Invalid unit (WINBIO_E_INVALID_UNIT)",
-        0x8009_8003 => "Unknown ID (WINBIO_E_UNKNOWN_ID)",
-        0x8009_8004 => "Operation canceled (WINBIO_E_CANCELED)",
-        0x8009_8005 => "No match (WINBIO_E_NO_MATCH)",
-        0x8009_8006 => "Capture sample failed (WINBIO_E_CAPTURE_ABORTED)",
-        0x8009_8007 => "Enrollment in progress (WINBIO_E_ENROLLMENT_IN_PROGRESS)",
-        0x8009_8008 => "Bad capture (WINBIO_E_BAD_CAPTURE)",
-        0x8009_800B => "Session busy (WINBIO_E_SESSION_BUSY)",
-        0x8009_800E => "No session (WINBIO_E_SESSION_HANDLE_CLOSED)",
-        0x8009_8010 => "Database full (WINBIO_E_DATABASE_FULL)",
-        0x8009_8011 => "Database locked (WINBIO_E_DATABASE_LOCKED)",
-        0x8009_8014 => "Not enrolled (WINBIO_E_UNKNOWN_ID)",
-        0x8009_8016 => "Database no such record (WINBIO_E_DATABASE_NO_SUCH_RECORD)",
-        0x8009_8019 => "Sensor unavailable (WINBIO_E_DEVICE_BUSY)",
-        0x8009_802E => "Data collection in progress",
-        0x8009_8015 => "Duplicate enrollment (WINBIO_E_DUPLICATE_ENROLLMENT)",
-        0x8009_8029 => "No preboot identity (WINBIO_E_NO_PREBOOT_IDENTITY)",
-
-        // WinBio informational
-        0x0009_0001 => "Sample needed for enrollment (WINBIO_I_MORE_DATA)",
-
-        _ => "Unknown HRESULT",
+        0x8009_8001 => "Biometric factor not supported (WINBIO_E_UNSUPPORTED_FACTOR)".into(),
+        0x8009_8002 => "Invalid unit (WINBIO_E_INVALID_UNIT)".into(),
+        0x8009_8003 => "Unknown ID (WINBIO_E_UNKNOWN_ID)".into(),
+        0x8009_8004 => "Operation canceled (WINBIO_E_CANCELED)".into(),
+        0x8009_8005 => "No match (WINBIO_E_NO_MATCH)".into(),
+        0x8009_8006 => "Capture aborted (WINBIO_E_CAPTURE_ABORTED)".into(),
+        0x8009_8007 => "Enrollment in progress (WINBIO_E_ENROLLMENT_IN_PROGRESS)".into(),
+        0x8009_8008 => "Bad capture (WINBIO_E_BAD_CAPTURE)".into(),
+        0x8009_8009 => "Invalid control code (WINBIO_E_INVALID_CONTROL_CODE)".into(),
+        0x8009_800B => "Data collection in progress (WINBIO_E_DATA_COLLECTION_IN_PROGRESS)".into(),
+        0x8009_800C => "Unsupported data format (WINBIO_E_UNSUPPORTED_DATA_FORMAT)".into(),
+        0x8009_800D => "Unsupported data type (WINBIO_E_UNSUPPORTED_DATA_TYPE)".into(),
+        0x8009_800E => "Unsupported purpose (WINBIO_E_UNSUPPORTED_PURPOSE)".into(),
+        0x8009_800F => "Invalid device state (WINBIO_E_INVALID_DEVICE_STATE)".into(),
+        0x8009_8010 => "Device busy (WINBIO_E_DEVICE_BUSY)".into(),
+        0x8009_8011 => "Cannot create database (WINBIO_E_DATABASE_CANT_CREATE)".into(),
+        0x8009_8012 => "Cannot open database (WINBIO_E_DATABASE_CANT_OPEN)".into(),
+        0x8009_8013 => "Cannot close database (WINBIO_E_DATABASE_CANT_CLOSE)".into(),
+        0x8009_8014 => "Cannot erase database (WINBIO_E_DATABASE_CANT_ERASE)".into(),
+        0x8009_8015 => "Cannot find database (WINBIO_E_DATABASE_CANT_FIND)".into(),
+        0x8009_8016 => "Database already exists (WINBIO_E_DATABASE_ALREADY_EXISTS)".into(),
+        0x8009_8018 => "Database full (WINBIO_E_DATABASE_FULL)".into(),
+        0x8009_8019 => "Database locked (WINBIO_E_DATABASE_LOCKED)".into(),
+        0x8009_801A => "Database corrupted (WINBIO_E_DATABASE_CORRUPTED)".into(),
+        0x8009_801B => "Database no such record (WINBIO_E_DATABASE_NO_SUCH_RECORD)".into(),
+        0x8009_801C => "Duplicate enrollment (WINBIO_E_DUPLICATE_ENROLLMENT)".into(),
+        0x8009_801D => "Database read error (WINBIO_E_DATABASE_READ_ERROR)".into(),
+        0x8009_801E => "Database write error (WINBIO_E_DATABASE_WRITE_ERROR)".into(),
+        0x8009_801F => "Database query returned no results (WINBIO_E_DATABASE_NO_RESULTS)".into(),
+        0x8009_8020 => "No more database records (WINBIO_E_DATABASE_NO_MORE_RECORDS)".into(),
+        0x8009_8021 => "Database end of file (WINBIO_E_DATABASE_EOF)".into(),
+        0x8009_8022 => "Database bad index vector (WINBIO_E_DATABASE_BAD_INDEX_VECTOR)".into(),
+        0x8009_8024 => "Incorrect biometric service provider (WINBIO_E_INCORRECT_BSP)".into(),
+        0x8009_8025 => "Incorrect sensor pool (WINBIO_E_INCORRECT_SENSOR_POOL)".into(),
+        0x8009_8026 => "No capture data (WINBIO_E_NO_CAPTURE_DATA)".into(),
+        0x8009_8027 => "Invalid sensor mode (WINBIO_E_INVALID_SENSOR_MODE)".into(),
+        0x8009_802A => "Lock violation (WINBIO_E_LOCK_VIOLATION)".into(),
+        0x8009_802B => "Duplicate template (WINBIO_E_DUPLICATE_TEMPLATE)".into(),
+        0x8009_802C => "Invalid operation (WINBIO_E_INVALID_OPERATION)".into(),
+        0x8009_802D => "Session busy (WINBIO_E_SESSION_BUSY)".into(),
+        0x8009_8030 => "Credential provider disabled (WINBIO_E_CRED_PROV_DISABLED)".into(),
+        0x8009_8031 => {
+            "No credential for credential provider (WINBIO_E_CRED_PROV_NO_CREDENTIAL)".into()
+        }
+        0x8009_8032 => "Biometrics disabled by policy (WINBIO_E_DISABLED)".into(),
+        0x8009_8033 => "Configuration failure (WINBIO_E_CONFIGURATION_FAILURE)".into(),
+        0x8009_8034 => "Sensor unavailable (WINBIO_E_SENSOR_UNAVAILABLE)".into(),
+        0x8009_8035 => "Secure attention sequence enabled (WINBIO_E_SAS_ENABLED)".into(),
+        0x8009_8036 => "Device failure (WINBIO_E_DEVICE_FAILURE)".into(),
+        0x8009_8037 => "Fast user switching disabled (WINBIO_E_FAST_USER_SWITCH_DISABLED)".into(),
+        0x8009_8038 => "Not the active console session (WINBIO_E_NOT_ACTIVE_CONSOLE)".into(),
+        0x8009_8039 => "Event monitor already active (WINBIO_E_EVENT_MONITOR_ACTIVE)".into(),
+        0x8009_803A => "Invalid property type (WINBIO_E_INVALID_PROPERTY_TYPE)".into(),
+        0x8009_803B => "Invalid property ID (WINBIO_E_INVALID_PROPERTY_ID)".into(),
+        0x8009_803C => "Unsupported property (WINBIO_E_UNSUPPORTED_PROPERTY)".into(),
+        0x8009_803D => "Adapter integrity check failed (WINBIO_E_ADAPTER_INTEGRITY_FAILURE)".into(),
+        0x8009_803E => "Incorrect session type (WINBIO_E_INCORRECT_SESSION_TYPE)".into(),
+        0x8009_803F => "Session handle closed (WINBIO_E_SESSION_HANDLE_CLOSED)".into(),
+        0x8009_8040 => "Deadlock detected (WINBIO_E_DEADLOCK_DETECTED)".into(),
+        0x8009_8041 => "No preboot identity (WINBIO_E_NO_PREBOOT_IDENTITY)".into(),
+        0x8009_8042 => "Too many failed attempts (WINBIO_E_MAX_ERROR_COUNT_EXCEEDED)".into(),
+        0x8009_8043 => "Auto logon disabled (WINBIO_E_AUTO_LOGON_DISABLED)".into(),
+        0x8009_8044 => "Invalid ticket (WINBIO_E_INVALID_TICKET)".into(),
+        0x8009_8045 => "Ticket quota exceeded (WINBIO_E_TICKET_QUOTA_EXCEEDED)".into(),
+        0x8009_8046 => "Data protection failure (WINBIO_E_DATA_PROTECTION_FAILURE)".into(),
+        0x8009_8047 => {
+            "Credential provider security lockout (WINBIO_E_CRED_PROV_SECURITY_LOCKOUT)".into()
+        }
+        0x8009_8048 => "Unsupported pool type (WINBIO_E_UNSUPPORTED_POOL_TYPE)".into(),
+        0x8009_8049 => "Enrollment selection required (WINBIO_E_SELECTION_REQUIRED)".into(),
+        0x8009_804A => "Presence monitor already active (WINBIO_E_PRESENCE_MONITOR_ACTIVE)".into(),
+        0x8009_804B => "Invalid subfactor (WINBIO_E_INVALID_SUBFACTOR)".into(),
+        0x8009_804C => {
+            "Invalid calibration format array (WINBIO_E_INVALID_CALIBRATION_FORMAT_ARRAY)".into()
+        }
+        0x8009_804D => {
+            "No supported calibration format (WINBIO_E_NO_SUPPORTED_CALIBRATION_FORMAT)".into()
+        }
+        0x8009_804E => {
+            "Unsupported sensor calibration format (WINBIO_E_UNSUPPORTED_SENSOR_CALIBRATION_FORMAT)"
+                .into()
+        }
+        0x8009_804F => {
+            "Calibration buffer too small (WINBIO_E_CALIBRATION_BUFFER_TOO_SMALL)".into()
+        }
+        0x8009_8050 => {
+            "Calibration buffer too large (WINBIO_E_CALIBRATION_BUFFER_TOO_LARGE)".into()
+        }
+        0x8009_8051 => "Calibration buffer invalid (WINBIO_E_CALIBRATION_BUFFER_INVALID)".into(),
+        0x8009_8052 => "Invalid key identifier (WINBIO_E_INVALID_KEY_IDENTIFIER)".into(),
+        0x8009_8053 => "Key creation failed (WINBIO_E_KEY_CREATION_FAILED)".into(),
+        0x8009_8054 => {
+            "Key identifier buffer too small (WINBIO_E_KEY_IDENTIFIER_BUFFER_TOO_SMALL)".into()
+        }
+        0x8009_8055 => "Property unavailable (WINBIO_E_PROPERTY_UNAVAILABLE)".into(),
+        0x8009_8056 => {
+            "Policy protection unavailable (WINBIO_E_POLICY_PROTECTION_UNAVAILABLE)".into()
+        }
+        0x8009_8057 => "Insecure sensor (WINBIO_E_INSECURE_SENSOR)".into(),
+        0x8009_8058 => "Invalid buffer ID (WINBIO_E_INVALID_BUFFER_ID)".into(),
+        0x8009_8059 => "Invalid buffer (WINBIO_E_INVALID_BUFFER)".into(),
+        0x8009_805A => "Trustlet integrity check failed (WINBIO_E_TRUSTLET_INTEGRITY_FAIL)".into(),
+        0x8009_805B => {
+            "Enrollment canceled by suspend (WINBIO_E_ENROLLMENT_CANCELED_BY_SUSPEND)".into()
+        }
+
+        // WinBio informational (WINBIO_I_*)
+        0x0009_0001 => "Sample needed for enrollment (WINBIO_I_MORE_DATA)".into(),
+        0x0009_0002 => {
+            "Extended status information available (WINBIO_I_EXTENDED_STATUS_INFORMATION)".into()
+        }
+
+        code => {
+            let message = hr.message();
+            let message = message.trim().trim_end_matches('.');
+            if message.is_empty() {
+                format!(
+                    "Unknown HRESULT (facility {}, code {})",
+                    (code >> 16) & 0x1FFF,
+                    code & 0xFFFF
+                )
+                .into()
+            } else {
+                message.to_string().into()
+            }
+        }
     }
 }
 
+/// A failed WinBio call. Kept as a typed error (rather than only a string) so
+/// the `--json` report can carry the HRESULT numerically; see `error_json`.
+#[derive(Debug)]
+pub struct WinBioError {
+    /// The API or operation that failed, e.g. `WinBioIdentify`.
+    pub context: String,
+    pub hresult: u32,
+    /// Human-readable meaning of `hresult`.
+    pub message: String,
+}
+
+impl std::fmt::Display for WinBioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} (0x{:08X})",
+            self.context, self.message, self.hresult
+        )
+    }
+}
+
+impl std::error::Error for WinBioError {}
+
 /// Format a windows::core::Error into an anyhow error with human-readable context.
 pub fn wrap_winbio_error(context: &str, err: &windows::core::Error) -> anyhow::Error {
     let hr = err.code();
-    anyhow::anyhow!(
-        "{}: {} (0x{:08X})",
-        context,
-        hresult_message(hr),
-        hr.0 as u32
-    )
+    anyhow::Error::new(WinBioError {
+        context: context.to_string(),
+        hresult: hr.0 as u32,
+        message: hresult_message(hr).into_owned(),
+    })
+}
+
+/// The `error` object of the `--json` report: `context` (what failed),
+/// `hresult` (a number, or null when no Windows error is involved), and
+/// `message`. The first WinBio or Windows error in the chain supplies the
+/// HRESULT.
+pub fn error_json(e: &anyhow::Error) -> serde_json::Value {
+    for cause in e.chain() {
+        if let Some(winbio) = cause.downcast_ref::<WinBioError>() {
+            return serde_json::json!({
+                "context": winbio.context,
+                "hresult": winbio.hresult,
+                "message": winbio.message,
+            });
+        }
+        if let Some(windows) = cause.downcast_ref::<windows::core::Error>() {
+            return serde_json::json!({
+                "context": e.to_string(),
+                "hresult": windows.code().0 as u32,
+                "message": hresult_message(windows.code()),
+            });
+        }
+    }
+    let root = e.root_cause().to_string();
+    let outer = e.to_string();
+    serde_json::json!({
+        "context": if outer == root { None } else { Some(outer) },
+        "hresult": null,
+        "message": root,
+    })
 }
 
 /// Extract the HRESULT code from a windows::core::Error as u32.
 pub fn error_code(err: &windows::core::Error) -> u32 {
     err.code().0 as u32
 }
+
+/// Process exit codes, so scripts can tell failure classes apart.
+/// Documented in the `--help` output (see `cli.rs`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Any other error or `[FAIL]` result
+    General = 1,
+    /// No biometric device or sensor unit found
+    NoHardware = 2,
+    /// WbioSrvc is not running
+    ServiceNotRunning = 3,
+    /// The finger did not match an enrolled template
+    NoMatch = 4,
+    /// The command requires Administrator privileges
+    NotElevated = 5,
+    /// The Windows Biometric Framework (WbioSrvc) is not installed
+    FrameworkUnavailable = 6,
+}
+
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
+
+/// Record why the command failed. The first specific code recorded wins;
+/// diagnostics keep running and printing after this.
+pub fn set_exit_code(code: ExitCode) {
+    let _ = EXIT_CODE.compare_exchange(0, code as i32, Ordering::Relaxed, Ordering::Relaxed);
+}
+
+/// Run `f` without keeping any exit code it records; for checks that a
+/// command runs only to collect their output.
+pub fn without_exit_code<T>(f: impl FnOnce() -> T) -> T {
+    let saved = EXIT_CODE.swap(0, Ordering::Relaxed);
+    let result = f();
+    EXIT_CODE.store(saved, Ordering::Relaxed);
+    result
+}
+
+/// The exit code for a finished command: a recorded specific code, else
+/// `General` if it returned an error or printed any `[FAIL]`, else 0.
+pub fn exit_code(failed: bool) -> i32 {
+    match EXIT_CODE.load(Ordering::Relaxed) {
+        0 if failed => ExitCode::General as i32,
+        code => code,
+    }
+}
@@ -0,0 +1,95 @@
+use colored::Colorize;
+
+use crate::output::*;
+
+/// Finger positions as drawn, viewed from above with both palms down:
+/// (subfactor, height in rows). Thumbs sit on the inner side of each hand.
+const LEFT_HAND: [(u8, usize); 5] = [(10, 2), (9, 3), (8, 4), (7, 3), (6, 1)];
+const RIGHT_HAND: [(u8, usize); 5] = [(1, 1), (2, 3), (3, 4), (4, 3), (5, 2)];
+
+/// Height of the tallest (middle) finger.
+const MAX_HEIGHT: usize = 4;
+/// Each finger is three columns wide, so a hand is 15.
+const HAND_WIDTH: usize = 15;
+const GAP: &str = "      ";
+
+/// One line of the diagram as (text, highlighted) segments.
+type Line = Vec<(String, bool)>;
+
+/// Row `row` of one hand: a finger's tip, its outline (filled when
+/// selected), or blank space above shorter fingers.
+fn finger_row(hand: &[(u8, usize)], selected: u8, row: usize, line: &mut Line) {
+    for &(subfactor, height) in hand {
+        let top = MAX_HEIGHT - height;
+        let cell = if row < top {
+            "   "
+        } else if row == top {
+            " _ "
+        } else if subfactor == selected {
+            "|#|"
+        } else {
+            "| |"
+        };
+        line.push((cell.to_string(), subfactor == selected));
+    }
+}
+
+fn label_row(hand: &[(u8, usize)], selected: u8, line: &mut Line) {
+    for &(subfactor, _) in hand {
+        line.push((format!("{:^3}", subfactor), subfactor == selected));
+    }
+}
+
+/// Both hands with `selected` filled in and its number highlighted.
+fn render(selected: u8) -> Vec<Line> {
+    let mut lines = vec![vec![(
+        format!(
+            "{:^width$}{}{:^width$}",
+            "Left hand",
+            GAP,
+            "Right hand",
+            width = HAND_WIDTH
+        ),
+        false,
+    )]];
+    for row in 0..=MAX_HEIGHT {
+        let mut line = Vec::new();
+        finger_row(&LEFT_HAND, selected, row, &mut line);
+        line.push((GAP.to_string(), false));
+        finger_row(&RIGHT_HAND, selected, row, &mut line);
+        lines.push(line);
+    }
+    let palm = format!("\\{}/", "_".repeat(HAND_WIDTH - 2));
+    lines.push(vec![(format!("{}{}{}", palm, GAP, palm), false)]);
+    let mut labels = Vec::new();
+    label_row(&LEFT_HAND, selected, &mut labels);
+    labels.push((GAP.to_string(), false));
+    label_row(&RIGHT_HAND, selected, &mut labels);
+    lines.push(labels);
+    lines
+}
+
+/// Draw both hands with the finger to place on the sensor highlighted, so
+/// there is no doubt which finger "2" is. Nothing is drawn for "any finger"
+/// or vendor-specific positions, or in JSON mode; `--quiet` hides it.
+pub fn print_finger_guide(finger: u8) {
+    if !(1..=10).contains(&finger) || json_mode() {
+        return;
+    }
+    print_blank();
+    for line in render(finger) {
+        let plain: String = line.iter().map(|(text, _)| text.as_str()).collect();
+        let colored: String = line
+            .iter()
+            .map(|(text, highlighted)| {
+                if *highlighted {
+                    text.bold().green().to_string()
+                } else {
+                    text.clone()
+                }
+            })
+            .collect();
+        print_art(&colored, &plain);
+    }
+    print_blank();
+}
@@ -2,65 +2,443 @@ mod cli;
 mod diagnostics;
 mod elevation;
 mod error;
+mod finger_guide;
 mod operations;
 mod output;
+mod pnp;
 mod winbio_helpers;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 
 use cli::{Cli, Command};
+use winbio_helpers::SensorPool;
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
 
-    match cli.command {
-        Command::Diagnose => {
-            output::print_header("Windows Fingerprint Reader Diagnostics");
-            elevation::warn_if_not_elevated();
-            diagnostics::hardware::check_hardware()?;
-            diagnostics::service::check_service()?;
-            diagnostics::winbio::check_sensor()?;
-            println!();
-            output::print_step("Diagnostics complete.");
+    output::init_color(cli.no_color);
+    output::set_verbose(cli.verbose);
+    output::set_quiet(cli.quiet);
+    winbio_helpers::set_busy_retries(cli.retries);
+    winbio_helpers::set_biometric_type(cli.biometric_type);
+    winbio_helpers::set_session_flags_override(cli.session_flags);
+    operations::set_force_remote(cli.force);
+    if let Some(path) = &cli.log_file {
+        output::open_log_file(path)?;
+    }
+    if cli.json {
+        output::enable_json(matches.subcommand_name().unwrap_or_default(), cli.compact);
+    }
+    if cli.timings {
+        output::enable_timings();
+    }
+
+    let result = if uses_winbio(&cli.command) {
+        winbio_helpers::require_framework()
+    } else {
+        Ok(())
+    }
+    .and_then(|()| match cli.wait_for_sensor {
+        Some(secs) => winbio_helpers::wait_for_sensor(std::time::Duration::from_secs(secs)),
+        None => Ok(()),
+    })
+    .and_then(|()| run(cli.command, cli.pool));
+    output::print_timings();
+
+    let failed = if output::json_mode() {
+        output::finish_json(result.as_ref().err())
+    } else {
+        if let Err(e) = &result {
+            output::print_error(e);
+        }
+        result.is_err() || output::fail_count() > 0
+    };
+
+    match error::exit_code(failed) {
+        0 => Ok(()),
+        code => std::process::exit(code),
+    }
+}
+
+/// Whether `command` calls the WinBio API and so needs the framework present.
+/// Diagnostics that report a missing WbioSrvc themselves are excluded.
+fn uses_winbio(command: &Command) -> bool {
+    !matches!(
+        command,
+        Command::Diagnose { .. }
+            | Command::SelfTest
+            | Command::CheckHardware { .. }
+            | Command::CheckConflicts
+            | Command::CheckPolicy
+            | Command::CheckTpm
+            | Command::CheckDriver
+            | Command::StopService
+            | Command::StartService
+            | Command::RestartService
+            | Command::MonitorService
+            | Command::ReinstallDriver { .. }
+            | Command::RemoveDevice { .. }
+            | Command::VersionInfo
+            | Command::Collect { .. }
+    )
+}
+
+/// The three diagnostic levels plus the conflicting-software and credential
+/// checks, collecting what each finds into `findings` for the ranked summary.
+fn run_diagnose_checks(
+    pool: SensorPool,
+    events: &cli::EventLogArgs,
+    findings: &mut Vec<diagnostics::findings::Finding>,
+) -> Result<()> {
+    findings.extend(diagnostics::hardware::check_hardware(None)?);
+    findings.extend(diagnostics::service::check_service()?);
+    let sensor_findings = diagnostics::winbio::check_sensor(
+        pool,
+        events.since,
+        &events.event_ids,
+        false,
+        None,
+        false,
+    )?;
+    let sensor_ok = !sensor_findings
+        .iter()
+        .any(|f| f.severity == diagnostics::findings::Severity::Critical);
+    findings.extend(sensor_findings);
+    match diagnostics::conflicts::check_conflicting_software() {
+        Ok(conflicts) => findings.extend(conflicts),
+        Err(e) => output::print_warn(&format!("Could not scan for conflicting software: {:#}", e)),
+    }
+    findings.extend(diagnostics::database_acl::check_database_acls()?);
+    findings.extend(diagnostics::policy::check_biometric_policy());
+    findings.extend(diagnostics::tpm::check_tpm(false));
+    if sensor_ok {
+        // Only meaningful once WinBio can talk to a sensor
+        match diagnostics::winbio::check_credential() {
+            Ok(credential) => findings.extend(credential),
+            Err(e) => output::print_warn(&format!("Could not check credential state: {:#}", e)),
+        }
+    }
+    Ok(())
+}
+
+/// Full `diagnose`: every level, the optional `--fix` pass, the sensor
+/// summary, and the ranked problem list. Returns the problems found.
+fn run_diagnose(
+    pool: SensorPool,
+    events: &cli::EventLogArgs,
+    fix: bool,
+) -> Result<Vec<diagnostics::findings::Finding>> {
+    output::print_header("Windows Fingerprint Reader Diagnostics");
+    elevation::warn_if_not_elevated();
+    let mut findings = Vec::new();
+    let checked = run_diagnose_checks(pool, events, &mut findings);
+    if let (Err(e), true) = (&checked, fix) {
+        // The repairs may be exactly what the failed check needs
+        output::print_fail(&format!("{:#}", e));
+    }
+    if fix {
+        diagnostics::fix::offer_fixes()?;
+    } else {
+        checked?;
+    }
+    diagnostics::known_issues::print_sensor_summary();
+    if fix {
+        output::print_blank();
+        output::print_step("Problems below were found before --fix ran");
+    }
+    diagnostics::findings::print_findings(&mut findings);
+    output::print_blank();
+    output::print_step("Diagnostics complete.");
+    Ok(findings)
+}
+
+/// `diagnose --repeat`: run the full diagnosis every `interval` until Ctrl+C,
+/// following each run with the problems that cleared or appeared since the last.
+fn run_diagnose_repeat(
+    pool: SensorPool,
+    events: &cli::EventLogArgs,
+    interval: std::time::Duration,
+) -> Result<()> {
+    if output::json_mode() {
+        anyhow::bail!("--repeat cannot be combined with --json (the run never finishes)");
+    }
+
+    let mut previous: Option<Vec<diagnostics::findings::Finding>> = None;
+    for run in 1u64.. {
+        output::print_blank();
+        output::print_raw(&"=".repeat(72));
+        output::print_step(&format!(
+            "Run {} at {} (Ctrl+C to stop)",
+            run,
+            output::timestamp()
+        ));
+
+        // A check that errors out is itself a change worth tracking
+        let findings = run_diagnose(pool, events, false).unwrap_or_else(|e| {
+            output::print_fail(&format!("{:#}", e));
+            vec![diagnostics::findings::Finding::critical(
+                format!("{:#}", e),
+                "See the error above",
+            )]
+        });
+        if let Some(previous) = &previous {
+            diagnostics::findings::print_findings_delta(previous, &findings);
+        }
+        previous = Some(findings);
+
+        std::thread::sleep(interval);
+    }
+    Ok(())
+}
+
+fn run(command: Command, pool: SensorPool) -> Result<()> {
+    match command {
+        Command::Diagnose {
+            events,
+            fix,
+            repeat,
+        } => match repeat {
+            Some(interval) => run_diagnose_repeat(pool, &events, interval)?,
+            None => {
+                run_diagnose(pool, &events, fix)?;
+            }
+        },
+        Command::SensorProperties { unit, serial } => {
+            let unit = winbio_helpers::unit_for_serial(unit, serial.as_deref())?;
+            operations::sensor_properties::run_sensor_properties(unit, pool)?;
+        }
+        Command::SetIndicator {
+            unit,
+            serial,
+            on,
+            off: _,
+            control_code,
+        } => {
+            let unit = winbio_helpers::unit_for_serial(unit, serial.as_deref())?;
+            operations::set_indicator::run_set_indicator(unit, on, control_code, pool)?;
         }
-        Command::CheckHardware => {
-            diagnostics::hardware::check_hardware()?;
+        Command::EnumAdapters => {
+            operations::enum_adapters::run_enum_adapters()?;
+        }
+        Command::CheckDatabases => {
+            diagnostics::databases::check_databases()?;
+        }
+        Command::CheckDatabaseAcl => {
+            diagnostics::database_acl::check_database_acls()?;
+        }
+        Command::CheckFirmware => {
+            diagnostics::firmware::check_firmware()?;
+        }
+        Command::VersionInfo => {
+            diagnostics::version_info::run_version_info()?;
+        }
+        Command::Collect {
+            output_dir,
+            redact,
+            events,
+        } => {
+            operations::collect::run_collect(
+                &output_dir,
+                redact,
+                events.since,
+                &events.event_ids,
+                pool,
+            )?;
+        }
+        Command::SelfTest => {
+            diagnostics::self_test::run_self_test(pool)?;
+        }
+        Command::CheckConflicts => {
+            diagnostics::conflicts::check_conflicting_software()?;
+        }
+        Command::CheckPolicy => {
+            diagnostics::policy::check_biometric_policy();
+        }
+        Command::CheckTpm => {
+            diagnostics::tpm::check_tpm(true);
+        }
+        Command::CheckHardware {
+            instance_id,
+            format,
+        } => {
+            diagnostics::hardware::run_check_hardware(format, instance_id.as_deref())?;
         }
         Command::CheckDriver => {
             diagnostics::service::check_service()?;
         }
-        Command::CheckSensor => {
-            diagnostics::winbio::check_sensor()?;
+        Command::CheckSensor {
+            events,
+            unique,
+            instance_id,
+            all_pools,
+        } => {
+            diagnostics::winbio::check_sensor(
+                pool,
+                events.since,
+                &events.event_ids,
+                unique,
+                instance_id.as_deref(),
+                all_pools,
+            )?;
+        }
+        Command::ListUnits => {
+            operations::list_units::run_list_units()?;
+        }
+        Command::ListFingerprints {
+            timeout,
+            all_users,
+            format,
+            output,
+        } => {
+            operations::list::run_list(timeout, all_users, format, output.as_deref(), pool)?;
+        }
+        Command::Whoami { timeout } => {
+            operations::whoami::run_whoami(timeout, pool)?;
+        }
+        Command::Identify {
+            unit,
+            serial,
+            timeout,
+            count,
+            use_async,
+            restart_on_lock,
+        } => {
+            let unit = winbio_helpers::unit_for_serial(unit, serial.as_deref())?;
+            operations::with_database_lock_retry(restart_on_lock, || {
+                if use_async {
+                    operations::identify::run_identify_async(unit, pool)
+                } else {
+                    operations::identify::run_identify(unit, timeout, count, pool)
+                }
+            })?;
         }
-        Command::ListFingerprints => {
-            operations::list::run_list()?;
+        Command::LocateSensor { timeout } => {
+            operations::locate_sensor::run_locate_sensor(timeout, pool)?;
+        }
+        Command::Verify {
+            finger,
+            unit,
+            serial,
+            timeout,
+            no_identify,
+        } => {
+            let unit = winbio_helpers::unit_for_serial(unit, serial.as_deref())?;
+            operations::verify::run_verify(finger, unit, timeout, no_identify, pool)?;
+        }
+        Command::Capture {
+            unit,
+            serial,
+            output,
+            count,
+            purpose,
+            output_bir_info,
+            bench,
+        } => {
+            let unit = winbio_helpers::unit_for_serial(unit, serial.as_deref())?;
+            operations::capture::run_capture(
+                unit,
+                output,
+                count,
+                purpose,
+                output_bir_info,
+                bench,
+                pool,
+            )?;
+        }
+        Command::CompareTemplates {
+            unit,
+            serial,
+            files,
+        } => {
+            let unit = winbio_helpers::unit_for_serial(unit, serial.as_deref())?;
+            operations::compare_templates::run_compare_templates(unit, files, pool)?;
         }
-        Command::Identify => {
-            operations::identify::run_identify()?;
+        Command::ExportTemplate {
+            finger,
+            unit,
+            serial,
+            output,
+        } => {
+            let unit = winbio_helpers::unit_for_serial(unit, serial.as_deref())?;
+            operations::export_template::run_export_template(finger, unit, &output, pool)?;
         }
-        Command::Verify { finger } => {
-            operations::verify::run_verify(finger)?;
+        Command::Watch => {
+            operations::watch::run_watch(pool)?;
         }
-        Command::Capture => {
-            operations::capture::run_capture()?;
+        Command::MonitorEvents => {
+            operations::monitor_events::run_monitor_events(pool)?;
         }
-        Command::Delete { finger } => {
-            operations::delete::run_delete(finger)?;
+        Command::Delete {
+            finger,
+            all: _,
+            unit,
+            serial,
+            timeout,
+            dry_run,
+            yes,
+            restart_on_lock,
+        } => {
+            let unit = winbio_helpers::unit_for_serial(unit, serial.as_deref())?;
+            operations::with_database_lock_retry(restart_on_lock, || {
+                operations::delete::run_delete(finger, unit, timeout, dry_run, yes, pool)
+            })?;
         }
-        Command::Enroll { finger } => {
-            operations::enroll::run_enroll(finger)?;
+        Command::Enroll {
+            finger,
+            fingers,
+            unit,
+            serial,
+            resume,
+            discard,
+            min_samples,
+            max_samples,
+            sample_timeout,
+            overwrite,
+            restart_on_lock,
+        } => {
+            let unit = winbio_helpers::unit_for_serial(unit, serial.as_deref())?;
+            let fingers: Vec<u8> = finger.into_iter().chain(fingers).collect();
+            let options = operations::enroll::EnrollOptions {
+                resume,
+                discard,
+                min_samples,
+                max_samples,
+                sample_timeout,
+                overwrite,
+            };
+            operations::with_database_lock_retry(restart_on_lock, || {
+                operations::enroll::run_enroll(&fingers, unit, options, pool)
+            })?;
         }
-        Command::EnumDatabases => {
-            operations::enum_databases::run_enum_databases()?;
+        Command::EnumDatabases {
+            format,
+            instance_id,
+        } => {
+            operations::enum_databases::run_enum_databases(format, instance_id.as_deref())?;
         }
         Command::DeleteDatabase {
             db,
             all,
             file,
             registry,
+            dry_run,
+            yes,
+            stop_service,
         } => {
-            operations::delete_database::run_delete_database(db, all, file, registry)?;
+            operations::delete_database::run_delete_database(
+                db,
+                all,
+                file,
+                registry,
+                dry_run,
+                yes,
+                stop_service,
+            )?;
+        }
+        Command::BackupDatabases { output } => {
+            operations::backup_databases::run_backup_databases(&output)?;
         }
         Command::StopService => {
             operations::service::run_stop_service()?;
@@ -68,17 +446,33 @@ fn main() -> Result<()> {
         Command::StartService => {
             operations::service::run_start_service()?;
         }
-        Command::CredentialState => {
-            operations::credential_state::run_credential_state()?;
+        Command::RestartService => {
+            operations::service::run_restart_service()?;
+        }
+        Command::MonitorService => {
+            operations::service::run_monitor_service()?;
         }
-        Command::ReinstallDriver => {
-            operations::reinstall_driver::run_reinstall_driver()?;
+        Command::CredentialState { no_identify } => {
+            operations::credential_state::run_credential_state(no_identify, pool)?;
+        }
+        Command::ReinstallDriver {
+            device,
+            dry_run,
+            keep_backup,
+        } => {
+            operations::reinstall_driver::run_reinstall_driver(
+                device.as_deref(),
+                dry_run,
+                keep_backup,
+            )?;
         }
         Command::RemoveDevice {
             instance_id,
             phantom,
+            dry_run,
+            yes,
         } => {
-            operations::remove_device::run_remove_device(instance_id, phantom)?;
+            operations::remove_device::run_remove_device(instance_id, phantom, dry_run, yes)?;
         }
     }
 
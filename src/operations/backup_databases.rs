@@ -0,0 +1,139 @@
+use anyhow::{bail, Result};
+use serde_json::json;
+use std::path::Path;
+use std::process::Command;
+
+use super::delete_database::{enumerate_targets, DatabaseTarget};
+use crate::output::*;
+
+/// Export the `Databases\{id}` registry subkey to a .reg file.
+fn export_registry_key(db_id: &str, dest: &Path) -> Result<()> {
+    reg_export(
+        &format!(
+            "HKLM\\SYSTEM\\CurrentControlSet\\Services\\WbioSrvc\\Databases\\{}",
+            db_id
+        ),
+        dest,
+    )
+}
+
+/// Export registry `key` and its subkeys to a .reg file with `reg export`.
+pub(crate) fn reg_export(key: &str, dest: &Path) -> Result<()> {
+    let output = Command::new("reg")
+        .args(["export", key, &dest.to_string_lossy(), "/y"])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run reg export: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        bail!("reg export failed: {} {}", stdout.trim(), stderr.trim());
+    }
+    Ok(())
+}
+
+/// Back up one database. Returns its manifest entry and whether every part succeeded.
+fn backup_target(target: &DatabaseTarget, output: &Path) -> (serde_json::Value, bool) {
+    let mut ok = true;
+
+    print_blank();
+    print_step(&format!("Database {} — {}", target.index, target.db_id));
+
+    let mut file_backup = None;
+    let mut file_size = None;
+    if target.file_path.is_empty() {
+        print_info("  File", "none (on-chip storage)");
+    } else if !Path::new(&target.file_path).exists() {
+        print_info("  File", "does not exist — nothing to copy");
+    } else {
+        let name = Path::new(&target.file_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| format!("{}.DAT", target.db_id));
+        let dest = output.join(&name);
+        match std::fs::copy(&target.file_path, &dest) {
+            Ok(bytes) => {
+                print_pass(&format!("  Copied {} ({} bytes)", name, bytes));
+                file_backup = Some(name);
+                file_size = Some(bytes);
+            }
+            Err(e) => {
+                print_fail(&format!("  Failed to copy {}: {}", target.file_path, e));
+                print_step(
+                    "  If the file is in use, run stop-service first and start-service after",
+                );
+                ok = false;
+            }
+        }
+    }
+
+    let reg_name = format!("{}.reg", target.db_id);
+    let registry_backup = match export_registry_key(&target.db_id, &output.join(&reg_name)) {
+        Ok(()) => {
+            print_pass(&format!("  Exported registry key to {}", reg_name));
+            Some(reg_name)
+        }
+        Err(e) => {
+            print_fail(&format!("  {}", e));
+            ok = false;
+            None
+        }
+    };
+
+    let entry = json!({
+        "index": target.index,
+        "database_id": target.db_id,
+        "file_path": target.file_path,
+        "file_backup": file_backup,
+        "file_size": file_size,
+        "registry_backup": registry_backup,
+    });
+    (entry, ok)
+}
+
+/// Copy every registered database's .DAT file and export its registry entry
+/// into `output`, alongside a `manifest.json` describing what was saved.
+pub fn run_backup_databases(output: &Path) -> Result<()> {
+    crate::elevation::require_elevated()?;
+
+    print_header("Back Up Biometric Databases");
+
+    let targets = enumerate_targets(None, true)?;
+    if targets.is_empty() {
+        print_info("Nothing to do", "no registered databases found");
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(output)
+        .map_err(|e| anyhow::anyhow!("Cannot create {}: {}", output.display(), e))?;
+    print_info("Output", &output.display().to_string());
+    print_info("Databases", &targets.len().to_string());
+
+    let mut entries = Vec::new();
+    let mut any_error = false;
+    for target in &targets {
+        let (entry, ok) = backup_target(target, output);
+        entries.push(entry);
+        any_error |= !ok;
+    }
+
+    let manifest = json!({
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "created": timestamp(),
+        "databases": entries,
+    });
+    let manifest_path = output.join("manifest.json");
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .map_err(|e| anyhow::anyhow!("Cannot write {}: {}", manifest_path.display(), e))?;
+    record("backup", manifest);
+
+    print_blank();
+    print_pass(&format!("Manifest written to {}", manifest_path.display()));
+
+    if any_error {
+        bail!("Some databases were not fully backed up (see above)");
+    }
+
+    print_step("To restore: stop-service, copy the .DAT files back, import the .reg files with `reg import`, start-service");
+    Ok(())
+}
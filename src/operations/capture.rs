@@ -1,88 +1,493 @@
 use anyhow::Result;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use windows::Win32::Devices::BiometricFramework::*;
 
 use crate::output::*;
-use crate::winbio_helpers;
+use crate::winbio_helpers::{self, SensorPool};
 
 use super::SessionGuard;
 
-pub fn run_capture() -> Result<()> {
+/// The `WINBIO_PURPOSE_*` value passed to `WinBioCaptureSample`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum CapturePurpose {
+    /// WINBIO_NO_PURPOSE_AVAILABLE
+    None,
+    /// WINBIO_PURPOSE_VERIFY
+    Verify,
+    /// WINBIO_PURPOSE_IDENTIFY
+    Identify,
+    /// WINBIO_PURPOSE_ENROLL
+    Enroll,
+    /// WINBIO_PURPOSE_ENROLL_FOR_VERIFICATION
+    EnrollForVerification,
+    /// WINBIO_PURPOSE_ENROLL_FOR_IDENTIFICATION
+    EnrollForIdentification,
+}
+
+impl CapturePurpose {
+    fn code(self) -> u8 {
+        match self {
+            CapturePurpose::None => winbio_helpers::WINBIO_PURPOSE_NO_PURPOSE_AVAILABLE,
+            CapturePurpose::Verify => 0x01,
+            CapturePurpose::Identify => 0x02,
+            CapturePurpose::Enroll => 0x04,
+            CapturePurpose::EnrollForVerification => 0x08,
+            CapturePurpose::EnrollForIdentification => 0x10,
+        }
+    }
+}
+
+/// Print min/median/max/mean of the successful captures' latencies (`--bench`).
+fn print_latency_stats(latencies: &mut [Duration]) {
+    print_header("Capture Latency");
+    if latencies.is_empty() {
+        print_warn("No successful captures to time");
+        return;
+    }
+    latencies.sort();
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let n = latencies.len();
+    let median = if n % 2 == 1 {
+        ms(latencies[n / 2])
+    } else {
+        (ms(latencies[n / 2 - 1]) + ms(latencies[n / 2])) / 2.0
+    };
+    let mean = latencies.iter().map(|&d| ms(d)).sum::<f64>() / n as f64;
+    let min = ms(latencies[0]);
+    let max = ms(latencies[n - 1]);
+
+    print_info("Samples timed", &n.to_string());
+    print_info("Min (ms)", &format!("{:.1}", min));
+    print_info("Median (ms)", &format!("{:.1}", median));
+    print_info("Max (ms)", &format!("{:.1}", max));
+    print_info("Mean (ms)", &format!("{:.1}", mean));
+    // Printing and saving between samples is excluded; finger placement isn't
+    print_step("Timed from each WinBioCaptureSample call to its return; this includes the wait for the touch");
+    record(
+        "capture_latency",
+        json!({
+            "samples": n,
+            "min_ms": min,
+            "median_ms": median,
+            "max_ms": max,
+            "mean_ms": mean,
+            "latencies_ms": latencies.iter().map(|&d| ms(d)).collect::<Vec<_>>(),
+        }),
+    );
+}
+
+/// With `bench`, time each successful capture and finish with latency stats.
+pub fn run_capture(
+    unit: Option<u32>,
+    output: Option<PathBuf>,
+    count: u32,
+    purpose: CapturePurpose,
+    bir_info_output: Option<PathBuf>,
+    bench: bool,
+    pool: SensorPool,
+) -> Result<()> {
     print_header("Raw Fingerprint Capture");
 
     crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(unit, winbio_helpers::SensorOperation::RawCapture)?;
+
+    if unit.is_some() {
+        winbio_helpers::resolve_unit_id(unit)?;
+    }
 
     // Raw capture requires WINBIO_FLAG_RAW
-    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_RAW, false)?;
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_RAW, pool, false)?;
+    print_info(
+        "Purpose",
+        &format!("{:?} (0x{:02X})", purpose, purpose.code()),
+    );
     print_step("Session opened in RAW mode. Touch the sensor now...");
 
-    unsafe {
-        let mut sample: *mut WINBIO_BIR = std::ptr::null_mut();
-        let mut sample_size: usize = 0;
-        let mut unit_id = 0u32;
-        let mut reject_detail = 0u32;
-
-        let result = WinBioCaptureSample(
-            guard.session,
-            winbio_helpers::WINBIO_PURPOSE_NO_PURPOSE_AVAILABLE,
-            WINBIO_DATA_FLAG_RAW as u8,
-            Some(&mut unit_id),
-            &mut sample,
-            Some(&mut sample_size),
-            Some(&mut reject_detail),
-        );
-
-        if let Err(e) = result {
-            let code = crate::error::error_code(&e);
-            if code == 0x8009_8008 {
-                print_fail("Bad capture");
-                print_info(
-                    "Reject reason",
-                    winbio_helpers::reject_reason(reject_detail),
-                );
+    let mut sizes = Vec::new();
+    let mut latencies = Vec::new();
+    for n in 1..=count {
+        if count > 1 {
+            print_blank();
+            print_step(&format!("Sample {} of {} — touch the sensor...", n, count));
+        }
+
+        // With several samples, each gets its own file: <path>.1, <path>.2, ...
+        let numbered = |path: &PathBuf| {
+            if count > 1 {
+                PathBuf::from(format!("{}.{}", path.display(), n))
             } else {
-                print_fail(&format!(
-                    "WinBioCaptureSample failed: {} (0x{:08X})",
-                    crate::error::hresult_message(e.code()),
-                    code
-                ));
+                path.clone()
             }
-            if !sample.is_null() {
-                winbio_helpers::winbio_free(sample as *const _);
+        };
+        let path = output.as_ref().map(numbered);
+        let info_path = bir_info_output.as_ref().map(numbered);
+
+        match unsafe {
+            capture_one(
+                guard.session,
+                unit,
+                purpose,
+                path.as_deref(),
+                info_path.as_deref(),
+            )?
+        } {
+            CaptureOutcome::Captured(size, latency) => {
+                if bench {
+                    print_info(
+                        "Latency (ms)",
+                        &format!("{:.1}", latency.as_secs_f64() * 1000.0),
+                    );
+                }
+                sizes.push(size);
+                latencies.push(latency);
             }
-            return Ok(());
+            CaptureOutcome::Rejected | CaptureOutcome::Empty => {}
+            // Not a bad capture: the session is unlikely to recover
+            CaptureOutcome::Fatal => break,
         }
+    }
 
-        print_pass("Sample captured successfully");
-        print_info("Unit ID", &unit_id.to_string());
-        print_info("Sample size (bytes)", &sample_size.to_string());
+    if count > 1 {
+        print_header("Capture Summary");
+        print_info("Good samples", &format!("{}/{}", sizes.len(), count));
+        if let (Some(min), Some(max)) = (sizes.iter().min(), sizes.iter().max()) {
+            let mean = sizes.iter().sum::<usize>() as f64 / sizes.len() as f64;
+            print_info("Min size (bytes)", &min.to_string());
+            print_info("Max size (bytes)", &max.to_string());
+            print_info("Mean size (bytes)", &format!("{:.1}", mean));
+            record(
+                "capture_summary",
+                json!({
+                    "requested": count,
+                    "captured": sizes.len(),
+                    "min_size": min,
+                    "max_size": max,
+                    "mean_size": mean,
+                }),
+            );
+        }
+    }
+
+    if bench {
+        print_latency_stats(&mut latencies);
+    }
+
+    Ok(())
+}
+
+enum CaptureOutcome {
+    /// Sample captured; holds its size in bytes and how long the capture call took.
+    Captured(usize, Duration),
+    /// Bad capture, or the touch landed on a different unit.
+    Rejected,
+    /// The call succeeded but the sensor returned no sample data.
+    Empty,
+    /// Any other WinBio error.
+    Fatal,
+}
+
+/// Capture a single raw sample on an open RAW session and print its metadata.
+/// The BIR is freed before returning.
+unsafe fn capture_one(
+    session: u32,
+    unit: Option<u32>,
+    purpose: CapturePurpose,
+    output: Option<&Path>,
+    bir_info_output: Option<&Path>,
+) -> Result<CaptureOutcome> {
+    let mut sample: *mut WINBIO_BIR = std::ptr::null_mut();
+    let mut sample_size: usize = 0;
+    let mut unit_id = 0u32;
+    let mut reject_detail = 0u32;
 
+    // Stopped at the call's return, so printing and saving below aren't counted
+    let started = Instant::now();
+    let result = winbio_helpers::with_busy_retry(|| {
+        winbio_helpers::trace_call(
+            "WinBioCaptureSample",
+            &format!(
+                "session={}, purpose=0x{:02X}, flags=RAW",
+                session,
+                purpose.code()
+            ),
+            WinBioCaptureSample(
+                session,
+                purpose.code(),
+                WINBIO_DATA_FLAG_RAW as u8,
+                Some(&mut unit_id),
+                &mut sample,
+                Some(&mut sample_size),
+                Some(&mut reject_detail),
+            ),
+        )
+    });
+    let latency = started.elapsed();
+
+    if let Err(e) = result {
         if !sample.is_null() {
-            let bir = &*sample;
-            print_info(
-                "BIR header block",
-                &format!(
-                    "offset={}, size={}",
-                    bir.HeaderBlock.Offset, bir.HeaderBlock.Size
-                ),
-            );
-            print_info(
-                "BIR standard data block",
-                &format!(
-                    "offset={}, size={}",
-                    bir.StandardDataBlock.Offset, bir.StandardDataBlock.Size
-                ),
-            );
+            winbio_helpers::winbio_free(sample as *const _);
+        }
+        let code = crate::error::error_code(&e);
+        if code == 0x8009_8008 {
+            // WINBIO_E_BAD_CAPTURE
+            print_fail("Bad capture");
             print_info(
-                "BIR vendor data block",
-                &format!(
-                    "offset={}, size={}",
-                    bir.VendorDataBlock.Offset, bir.VendorDataBlock.Size
-                ),
+                "Reject reason",
+                &winbio_helpers::reject_reason(reject_detail),
             );
-
-            winbio_helpers::winbio_free(sample as *const _);
+            return Ok(CaptureOutcome::Rejected);
         }
+        print_fail(&format!(
+            "WinBioCaptureSample failed: {} (0x{:08X})",
+            crate::error::hresult_message(e.code()),
+            code
+        ));
+        return Ok(CaptureOutcome::Fatal);
+    }
+
+    if winbio_helpers::is_empty_sample(sample, sample_size) {
+        winbio_helpers::winbio_free(sample as *const _);
+        print_fail("Sensor returned an empty sample");
+        print_warn(&format!(
+            "WinBioCaptureSample succeeded but returned {} byte(s){}",
+            sample_size,
+            if sample.is_null() { " and no BIR" } else { "" }
+        ));
+        return Ok(CaptureOutcome::Empty);
+    }
+
+    if !super::check_responding_unit(unit, unit_id) {
+        winbio_helpers::winbio_free(sample as *const _);
+        return Ok(CaptureOutcome::Rejected);
+    }
+
+    print_pass("Sample captured successfully");
+    print_info("Unit ID", &unit_id.to_string());
+    print_info("Sample size (bytes)", &sample_size.to_string());
+    let detail = match reject_detail {
+        0 => "0 (none)".to_string(),
+        d => format!("{} ({})", d, winbio_helpers::reject_reason(d)),
+    };
+    print_info("Reject detail", &detail);
+
+    let bir = &*sample;
+    print_info(
+        "BIR header block",
+        &format!(
+            "offset={}, size={}",
+            bir.HeaderBlock.Offset, bir.HeaderBlock.Size
+        ),
+    );
+    print_info(
+        "BIR standard data block",
+        &format!(
+            "offset={}, size={}",
+            bir.StandardDataBlock.Offset, bir.StandardDataBlock.Size
+        ),
+    );
+    print_info(
+        "BIR vendor data block",
+        &format!(
+            "offset={}, size={}",
+            bir.VendorDataBlock.Offset, bir.VendorDataBlock.Size
+        ),
+    );
+
+    let bytes = std::slice::from_raw_parts(sample as *const u8, sample_size);
+    print_bir_header(bytes, &bir.HeaderBlock);
+    let info = bir_info(bytes, bir);
+    record("bir", info.clone());
+
+    let saved = match output {
+        Some(path) => save_sample(path, bytes, bir),
+        None => Ok(()),
+    }
+    .and_then(|()| match bir_info_output {
+        Some(path) => write_bir_info(path, &info),
+        None => Ok(()),
+    });
+
+    winbio_helpers::winbio_free(sample as *const _);
+    saved?;
+
+    Ok(CaptureOutcome::Captured(sample_size, latency))
+}
+
+/// The WINBIO_BIR_HEADER at the header block, if the block is in bounds and
+/// large enough to hold one.
+fn read_bir_header(bytes: &[u8], block: &WINBIO_BIR_DATA) -> Option<WINBIO_BIR_HEADER> {
+    let start = block.Offset as usize;
+    let size = std::mem::size_of::<WINBIO_BIR_HEADER>();
+    if (block.Size as usize) < size || bytes.len() < start.checked_add(size)? {
+        return None;
+    }
+    // The offset comes from the driver and need not be aligned
+    Some(unsafe { std::ptr::read_unaligned(bytes[start..].as_ptr() as *const WINBIO_BIR_HEADER) })
+}
+
+/// Names of the `WINBIO_DATA_FLAG_*` bits set in `flags`, e.g. "RAW | SIGNED".
+fn data_flag_names(flags: u8) -> String {
+    const FLAGS: &[(u16, &str)] = &[
+        (WINBIO_DATA_FLAG_PRIVACY, "PRIVACY"),
+        (WINBIO_DATA_FLAG_INTEGRITY, "INTEGRITY"),
+        (WINBIO_DATA_FLAG_SIGNED, "SIGNED"),
+        (WINBIO_DATA_FLAG_OPTION_MASK_PRESENT, "OPTION_MASK_PRESENT"),
+        (WINBIO_DATA_FLAG_RAW, "RAW"),
+        (WINBIO_DATA_FLAG_INTERMEDIATE, "INTERMEDIATE"),
+        (WINBIO_DATA_FLAG_PROCESSED, "PROCESSED"),
+    ];
+    let names: Vec<&str> = FLAGS
+        .iter()
+        .filter(|(bit, _)| flags as u16 & bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(" | ")
     }
+}
+
+/// Names of the `WINBIO_PURPOSE_*` bits set in `purpose`.
+fn purpose_names(purpose: u8) -> String {
+    const PURPOSES: &[(u8, &str)] = &[
+        (0x01, "VERIFY"),
+        (0x02, "IDENTIFY"),
+        (0x04, "ENROLL"),
+        (0x08, "ENROLL_FOR_VERIFICATION"),
+        (0x10, "ENROLL_FOR_IDENTIFICATION"),
+        (0x80, "AUDIT"),
+    ];
+    let names: Vec<&str> = PURPOSES
+        .iter()
+        .filter(|(bit, _)| purpose & bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(" | ")
+    }
+}
+
+/// Print the decoded WINBIO_BIR_HEADER, or say why it couldn't be decoded.
+fn print_bir_header(bytes: &[u8], block: &WINBIO_BIR_DATA) {
+    let Some(header) = read_bir_header(bytes, block) else {
+        print_warn(&format!(
+            "BIR header not decoded: block (offset={}, size={}) can't hold a {}-byte header in a {}-byte sample",
+            block.Offset,
+            block.Size,
+            std::mem::size_of::<WINBIO_BIR_HEADER>(),
+            bytes.len()
+        ));
+        return;
+    };
+
+    let factor = match header.Type {
+        winbio_helpers::WINBIO_TYPE_FINGERPRINT => "fingerprint",
+        winbio_helpers::WINBIO_TYPE_FACIAL_FEATURES => "face",
+        _ => "other",
+    };
+    let quality = match header.DataQuality {
+        q @ 0..=100 => format!("{}/100", q),
+        q => format!("not reported ({})", q),
+    };
+    print_info(
+        "BIR header version",
+        &format!(
+            "{} (patron {})",
+            header.HeaderVersion, header.PatronHeaderVersion
+        ),
+    );
+    print_info(
+        "BIR data flags",
+        &format!(
+            "0x{:02X} ({})",
+            header.DataFlags,
+            data_flag_names(header.DataFlags)
+        ),
+    );
+    print_info("BIR factor", &format!("0x{:08X} ({})", header.Type, factor));
+    print_info(
+        "BIR subtype",
+        &winbio_helpers::subfactor_name(header.Subtype),
+    );
+    print_info(
+        "BIR purpose",
+        &format!(
+            "0x{:02X} ({})",
+            header.Purpose,
+            purpose_names(header.Purpose)
+        ),
+    );
+    print_info("BIR quality", &quality);
+    print_info(
+        "BIR data format",
+        &format!(
+            "owner 0x{:04X}, type 0x{:04X}",
+            header.BiometricDataFormat.Owner, header.BiometricDataFormat.Type
+        ),
+    );
+}
+
+/// Decode the WINBIO_BIR_HEADER at the header block, if the block is in
+/// bounds and large enough to hold one.
+fn bir_header_json(bytes: &[u8], block: &WINBIO_BIR_DATA) -> Option<serde_json::Value> {
+    let header = read_bir_header(bytes, block)?;
+    let format = |f: &WINBIO_REGISTERED_FORMAT| json!({ "owner": f.Owner, "type": f.Type });
+    Some(json!({
+        "valid_fields": header.ValidFields,
+        "header_version": header.HeaderVersion,
+        "patron_header_version": header.PatronHeaderVersion,
+        "data_flags": header.DataFlags,
+        "type": header.Type,
+        "subtype": header.Subtype,
+        "purpose": header.Purpose,
+        "data_quality": header.DataQuality,
+        "creation_date": header.CreationDate,
+        "validity_begin": header.ValidityPeriod.BeginDate,
+        "validity_end": header.ValidityPeriod.EndDate,
+        "biometric_data_format": format(&header.BiometricDataFormat),
+        "product_id": format(&header.ProductId),
+    }))
+}
+
+/// Block offsets/sizes of a BIR plus its decoded header fields (`null` when the
+/// header block can't hold a WINBIO_BIR_HEADER).
+pub fn bir_info(bytes: &[u8], bir: &WINBIO_BIR) -> serde_json::Value {
+    let block = |b: &WINBIO_BIR_DATA| json!({ "offset": b.Offset, "size": b.Size });
+    json!({
+        "sample_size": bytes.len(),
+        "header_block": block(&bir.HeaderBlock),
+        "standard_data_block": block(&bir.StandardDataBlock),
+        "vendor_data_block": block(&bir.VendorDataBlock),
+        "header": bir_header_json(bytes, &bir.HeaderBlock),
+    })
+}
+
+fn write_bir_info(path: &Path, info: &serde_json::Value) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(info)?)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    print_pass(&format!("BIR structure written to {}", path.display()));
+    Ok(())
+}
+
+/// Write the full BIR buffer to `path`, plus a `<path>.json` sidecar describing
+/// where the header, standard data, and vendor data blocks begin.
+pub fn save_sample(path: &Path, bytes: &[u8], bir: &WINBIO_BIR) -> Result<()> {
+    std::fs::write(path, bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    print_pass(&format!("Sample written to {}", path.display()));
+
+    let sidecar = PathBuf::from(format!("{}.json", path.display()));
+    std::fs::write(
+        &sidecar,
+        serde_json::to_string_pretty(&bir_info(bytes, bir))?,
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", sidecar.display(), e))?;
+    print_pass(&format!("Block layout written to {}", sidecar.display()));
 
     Ok(())
 }
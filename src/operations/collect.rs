@@ -0,0 +1,255 @@
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use super::backup_databases::reg_export;
+use crate::diagnostics::findings::{print_findings, Finding};
+use crate::output::*;
+use crate::winbio_helpers::SensorPool;
+
+const EVENT_LOG: &str = "Microsoft-Windows-Biometrics/Operational";
+const WBIOSRVC_KEY: &str = r"HKLM\SYSTEM\CurrentControlSet\Services\WbioSrvc";
+
+/// `win-fp-debug-YYYYMMDD-HHMMSS` (UTC), the bundle's folder and zip name.
+fn bundle_name() -> String {
+    let digits: String = timestamp().chars().filter(char::is_ascii_digit).collect();
+    let (date, time) = digits.split_at(digits.len().min(8));
+    format!("win-fp-debug-{}-{}", date, time)
+}
+
+/// Replace the identifying sub-authorities of user SIDs with `<redacted>`:
+/// local and domain accounts (`S-1-5-21-a-b-c-RID`) and Microsoft Entra ID
+/// accounts (`S-1-12-1-a-b-c-RID`). The RID is kept so different accounts
+/// stay distinguishable.
+fn redact_sids(text: &str) -> String {
+    const PREFIXES: [&str; 2] = ["S-1-5-21-", "S-1-12-1-"];
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some((pos, prefix)) = PREFIXES
+        .iter()
+        .filter_map(|p| rest.find(p).map(|pos| (pos, *p)))
+        .min_by_key(|(pos, _)| *pos)
+    {
+        redacted.push_str(&rest[..pos]);
+        let tail = &rest[pos + prefix.len()..];
+        let len = tail
+            .find(|c: char| !(c.is_ascii_digit() || c == '-'))
+            .unwrap_or(tail.len());
+        let sid_tail = tail[..len].trim_end_matches('-');
+        redacted.push_str(prefix);
+        match sid_tail.rsplit_once('-') {
+            Some((_, rid)) => {
+                redacted.push_str("<redacted>-");
+                redacted.push_str(rid);
+            }
+            None => redacted.push_str(sid_tail),
+        }
+        rest = &tail[sid_tail.len()..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Run one check with its output captured as JSON (findings included) and
+/// write it to `<name>.json` in `dir`. Returns the manifest entry.
+fn collect_section(
+    dir: &Path,
+    name: &str,
+    redact: bool,
+    check: impl FnOnce() -> Result<Vec<Finding>>,
+) -> Value {
+    print_step(&format!("Collecting {}...", name));
+    let doc = capture_json(name, || {
+        let mut findings = check()?;
+        if !findings.is_empty() {
+            print_findings(&mut findings);
+        }
+        Ok(())
+    });
+
+    let mut text = serde_json::to_string_pretty(&doc).unwrap_or_else(|_| doc.to_string());
+    if redact {
+        text = redact_sids(&text);
+    }
+    let file = format!("{}.json", name);
+    let written = std::fs::write(dir.join(&file), text);
+
+    let status = doc["status"].as_str().unwrap_or("fail");
+    match (&written, doc["error"]["message"].as_str()) {
+        (Err(e), _) => print_fail(&format!("  Cannot write {}: {}", file, e)),
+        (Ok(()), Some(error)) => print_warn(&format!("  {}: {} (saved)", file, error)),
+        (Ok(()), None) => print_pass(&format!("  {} ({})", file, status)),
+    }
+    json!({
+        "name": name,
+        "file": written.is_ok().then_some(file),
+        "status": status,
+    })
+}
+
+/// Export the WinBio operational event log as `.evtx` with `wevtutil`.
+fn export_event_log(dest: &Path) -> Result<()> {
+    let output = Command::new("wevtutil")
+        .args(["epl", EVENT_LOG, &dest.to_string_lossy(), "/ow:true"])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run wevtutil: {}", e))?;
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("wevtutil epl failed: {} {}", stdout.trim(), stderr.trim());
+    }
+    Ok(())
+}
+
+/// Export the WbioSrvc service tree and each biometric device's `Enum` key
+/// (its WinBio configuration lives under `Device Parameters`) into `dir`.
+fn export_registry(dir: &Path) -> Vec<Value> {
+    let mut keys = vec![(WBIOSRVC_KEY.to_string(), "WbioSrvc.reg".to_string())];
+    match crate::pnp::enum_biometric_devices() {
+        Ok(devices) => keys.extend(devices.iter().enumerate().map(|(i, d)| {
+            (
+                format!(r"HKLM\SYSTEM\CurrentControlSet\Enum\{}", d.instance_id),
+                format!("device-{}.reg", i + 1),
+            )
+        })),
+        Err(e) => print_warn(&format!("  Cannot enumerate devices: {:#}", e)),
+    }
+
+    let mut exported = Vec::new();
+    for (key, file) in keys {
+        match reg_export(&key, &dir.join(&file)) {
+            Ok(()) => {
+                print_pass(&format!("  {} ({})", file, key));
+                exported.push(json!({ "key": key, "file": file }));
+            }
+            Err(e) => print_warn(&format!("  {}: {:#}", key, e)),
+        }
+    }
+    exported
+}
+
+/// Zip `dir` (the folder itself, so it unpacks to one directory) with
+/// PowerShell's `Compress-Archive`.
+fn compress(dir: &Path, zip: &Path) -> Result<()> {
+    let script = format!(
+        "Compress-Archive -LiteralPath '{}' -DestinationPath '{}' -Force -ErrorAction Stop",
+        dir.display().to_string().replace('\'', "''"),
+        zip.display().to_string().replace('\'', "''")
+    );
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run PowerShell: {}", e))?;
+    if !output.status.success() {
+        bail!(
+            "Compress-Archive failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Gather everything a bug report needs into one timestamped zip in
+/// `output_dir`: the non-interactive checks as JSON, the WinBio event log,
+/// and the service and device registry trees. With `redact`, user SIDs in the
+/// reports are masked and the raw event log (which can't be) is left out.
+pub fn run_collect(
+    output_dir: &Path,
+    redact: bool,
+    since: Option<Duration>,
+    event_ids: &[u32],
+    pool: SensorPool,
+) -> Result<()> {
+    print_header("Collect Support Bundle");
+    crate::elevation::warn_if_not_elevated();
+
+    let name = bundle_name();
+    let dir: PathBuf = output_dir.join(&name);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| anyhow::anyhow!("Cannot create {}: {}", dir.display(), e))?;
+    print_info("Output", &dir.display().to_string());
+
+    print_blank();
+    let sections = vec![
+        collect_section(&dir, "version-info", redact, || {
+            crate::diagnostics::version_info::run_version_info().map(|()| Vec::new())
+        }),
+        collect_section(&dir, "check-hardware", redact, || {
+            crate::diagnostics::hardware::check_hardware(None)
+        }),
+        collect_section(&dir, "check-service", redact, || {
+            crate::diagnostics::service::check_service()
+        }),
+        collect_section(&dir, "check-sensor", redact, || {
+            crate::diagnostics::winbio::check_sensor(pool, since, event_ids, false, None, true)
+        }),
+        collect_section(&dir, "events", redact, || {
+            Ok(crate::diagnostics::winbio::check_winbio_events(
+                since, event_ids,
+            ))
+        }),
+        collect_section(&dir, "enum-databases", redact, || {
+            super::enum_databases::run_enum_databases(
+                super::enum_databases::DatabaseFormat::Text,
+                None,
+            )
+            .map(|()| Vec::new())
+        }),
+        collect_section(&dir, "credential-state", redact, || {
+            super::credential_state::run_credential_state(true, pool).map(|()| Vec::new())
+        }),
+    ];
+
+    print_blank();
+    print_step("Exporting the WinBio event log...");
+    let event_log = if redact {
+        print_info("  Skipped", "--redact: the raw .evtx can't be redacted");
+        None
+    } else {
+        match export_event_log(&dir.join("biometrics-operational.evtx")) {
+            Ok(()) => {
+                print_pass("  biometrics-operational.evtx");
+                Some("biometrics-operational.evtx")
+            }
+            Err(e) => {
+                print_warn(&format!("  {:#}", e));
+                None
+            }
+        }
+    };
+
+    print_step("Exporting registry trees...");
+    let registry = export_registry(&dir);
+
+    let manifest = json!({
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "created": timestamp(),
+        "redacted": redact,
+        "sections": sections,
+        "event_log": event_log,
+        "registry": registry,
+    });
+    let manifest_path = dir.join("manifest.json");
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .map_err(|e| anyhow::anyhow!("Cannot write {}: {}", manifest_path.display(), e))?;
+    record("collect", manifest);
+
+    print_blank();
+    let zip = output_dir.join(format!("{}.zip", name));
+    match compress(&dir, &zip) {
+        Ok(()) => {
+            let _ = std::fs::remove_dir_all(&dir);
+            print_pass(&format!("Support bundle written to {}", zip.display()));
+        }
+        Err(e) => {
+            print_warn(&format!("Could not create the zip: {:#}", e));
+            print_pass(&format!("Support bundle written to {}", dir.display()));
+        }
+    }
+    if !redact {
+        print_step("The bundle contains account names and SIDs; pass --redact to mask the SIDs");
+    }
+    Ok(())
+}
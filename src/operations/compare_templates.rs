@@ -0,0 +1,227 @@
+use anyhow::{bail, Result};
+use serde_json::json;
+use std::path::PathBuf;
+use windows::Win32::Devices::BiometricFramework::*;
+
+use crate::output::*;
+use crate::winbio_helpers::{self, SensorPool};
+
+use super::SessionGuard;
+
+/// Bad captures tolerated per sample before giving up.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// `(offset, size)` of the standard data block in a serialized `WINBIO_BIR`.
+/// The BIR starts with four `WINBIO_BIR_DATA { Size, Offset }` pairs:
+/// header, standard data, vendor data, signature.
+fn standard_data_block(bir: &[u8]) -> Result<&[u8]> {
+    let field = |at: usize| -> Result<usize> {
+        let bytes = bir
+            .get(at..at + 4)
+            .ok_or_else(|| anyhow::anyhow!("sample is too short to hold a WINBIO_BIR header"))?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+    let size = field(8)?;
+    let offset = field(12)?;
+    bir.get(offset..offset + size).ok_or_else(|| {
+        anyhow::anyhow!(
+            "standard data block (offset {}, size {}) lies outside the {}-byte sample",
+            offset,
+            size,
+            bir.len()
+        )
+    })
+}
+
+/// Capture one RAW sample and copy the whole BIR out of WinBio's buffer. A RAW
+/// session captures from any reader, so a touch on a unit other than the
+/// requested `unit` is rejected.
+fn capture_sample(session: u32, unit: Option<u32>, n: u32) -> Result<Vec<u8>> {
+    for attempt in 1..=MAX_ATTEMPTS {
+        print_step(&format!("Sample {}: touch the sensor...", n));
+
+        let mut sample: *mut WINBIO_BIR = std::ptr::null_mut();
+        let mut sample_size: usize = 0;
+        let mut unit_id = 0u32;
+        let mut reject_detail = 0u32;
+
+        let result = unsafe {
+            winbio_helpers::with_busy_retry(|| {
+                winbio_helpers::trace_call(
+                    "WinBioCaptureSample",
+                    &format!("session={}, flags=RAW", session),
+                    WinBioCaptureSample(
+                        session,
+                        winbio_helpers::WINBIO_PURPOSE_NO_PURPOSE_AVAILABLE,
+                        WINBIO_DATA_FLAG_RAW as u8,
+                        Some(&mut unit_id),
+                        &mut sample,
+                        Some(&mut sample_size),
+                        Some(&mut reject_detail),
+                    ),
+                )
+            })
+        };
+
+        let bytes = (!winbio_helpers::is_empty_sample(sample, sample_size) && result.is_ok()).then(
+            || unsafe { std::slice::from_raw_parts(sample as *const u8, sample_size).to_vec() },
+        );
+        unsafe { winbio_helpers::winbio_free(sample as *const _) };
+
+        match result {
+            Ok(()) => {
+                let Some(bytes) = bytes else {
+                    bail!(
+                        "Sensor returned an empty sample ({} bytes) despite a successful capture",
+                        sample_size
+                    );
+                };
+                if !super::check_responding_unit(unit, unit_id) {
+                    bail!("Sample {} was captured on the wrong unit", n);
+                }
+                print_pass(&format!("Sample {} captured ({} bytes)", n, bytes.len()));
+                return Ok(bytes);
+            }
+            Err(e) if crate::error::error_code(&e) == 0x8009_8008 => {
+                // WINBIO_E_BAD_CAPTURE
+                print_warn(&format!(
+                    "Bad capture: {} (attempt {}/{})",
+                    winbio_helpers::reject_reason(reject_detail),
+                    attempt,
+                    MAX_ATTEMPTS
+                ));
+            }
+            Err(e) => return Err(crate::error::wrap_winbio_error("WinBioCaptureSample", &e)),
+        }
+    }
+    bail!(
+        "No good capture for sample {} after {} attempts",
+        n,
+        MAX_ATTEMPTS
+    )
+}
+
+/// Pearson correlation of two byte sequences over their common length.
+fn correlation(a: &[u8], b: &[u8]) -> Option<f64> {
+    let n = a.len().min(b.len());
+    if n == 0 {
+        return None;
+    }
+    let mean = |x: &[u8]| x[..n].iter().map(|&v| v as f64).sum::<f64>() / n as f64;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for i in 0..n {
+        let (da, db) = (a[i] as f64 - mean_a, b[i] as f64 - mean_b);
+        cov += da * db;
+        var_a += da * da;
+        var_b += db * db;
+    }
+    (var_a > 0.0 && var_b > 0.0).then(|| cov / (var_a * var_b).sqrt())
+}
+
+/// Compare two raw samples — two fresh captures, or two files written by
+/// `capture --output` — using byte statistics on their standard data blocks.
+/// This is a diagnostic heuristic; it does not run the engine adapter's matcher.
+pub fn run_compare_templates(
+    unit: Option<u32>,
+    files: Vec<PathBuf>,
+    pool: SensorPool,
+) -> Result<()> {
+    print_header("Compare Raw Samples");
+    print_warn("Diagnostic heuristic only — byte statistics, not the Windows Hello matcher");
+
+    let samples: Vec<Vec<u8>> = if files.is_empty() {
+        crate::elevation::warn_if_not_elevated();
+        winbio_helpers::require_capabilities(unit, winbio_helpers::SensorOperation::RawCapture)?;
+        if unit.is_some() {
+            winbio_helpers::resolve_unit_id(unit)?;
+        }
+
+        let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_RAW, pool, false)?;
+        print_step("Session opened in RAW mode. Touch the sensor twice with the same finger.");
+        vec![
+            capture_sample(guard.session, unit, 1)?,
+            capture_sample(guard.session, unit, 2)?,
+        ]
+    } else {
+        files
+            .iter()
+            .map(|path| {
+                std::fs::read(path)
+                    .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let a = standard_data_block(&samples[0])?;
+    let b = standard_data_block(&samples[1])?;
+
+    print_blank();
+    print_info(
+        "Sample sizes (bytes)",
+        &format!("{} / {}", samples[0].len(), samples[1].len()),
+    );
+    print_info(
+        "Standard data blocks (bytes)",
+        &format!("{} / {}", a.len(), b.len()),
+    );
+
+    let common = a.len().min(b.len());
+    let identical = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    let mean_abs_diff = if common == 0 {
+        0.0
+    } else {
+        a.iter()
+            .zip(b)
+            .map(|(&x, &y)| (x as f64 - y as f64).abs())
+            .sum::<f64>()
+            / common as f64
+    };
+    let corr = correlation(a, b);
+
+    print_info(
+        "Identical bytes",
+        &format!(
+            "{} of {} ({:.1}%)",
+            identical,
+            common,
+            identical as f64 * 100.0 / common.max(1) as f64
+        ),
+    );
+    print_info("Mean absolute difference", &format!("{:.2}", mean_abs_diff));
+    print_info(
+        "Correlation",
+        &corr.map_or("n/a (empty or constant data)".to_string(), |c| {
+            format!("{:.3}", c)
+        }),
+    );
+
+    // Rough bands: raw images of the same finger correlate strongly even when
+    // shifted slightly; unrelated touches hover near zero.
+    let verdict = match corr {
+        _ if a == b => "Identical data — the same sample was compared twice",
+        Some(c) if c >= 0.6 => "Likely the same finger (high similarity)",
+        Some(c) if c >= 0.3 => "Inconclusive (moderate similarity)",
+        Some(_) => "Likely different fingers or very different placement",
+        None => "Cannot judge — no usable image data",
+    };
+    if a.len() != b.len() {
+        print_warn("Block sizes differ; statistics cover the common prefix only");
+    }
+    print_info("Similarity heuristic", verdict);
+
+    record(
+        "comparison",
+        json!({
+            "sample_sizes": [samples[0].len(), samples[1].len()],
+            "standard_block_sizes": [a.len(), b.len()],
+            "identical_bytes": identical,
+            "compared_bytes": common,
+            "mean_abs_diff": mean_abs_diff,
+            "correlation": corr,
+            "verdict": verdict,
+        }),
+    );
+
+    Ok(())
+}
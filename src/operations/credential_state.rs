@@ -2,16 +2,36 @@ use anyhow::Result;
 use windows::Win32::Devices::BiometricFramework::*;
 
 use crate::output::*;
-use crate::winbio_helpers;
+use crate::winbio_helpers::{self, SensorPool};
 
 use super::SessionGuard;
 
-pub fn run_credential_state() -> Result<()> {
+pub fn run_credential_state(no_identify: bool, pool: SensorPool) -> Result<()> {
     print_header("Credential State Check");
 
+    if no_identify {
+        // No session or touch: the logged-in user's SID is the identity
+        let sid = crate::elevation::current_user_sid().map_err(|e| {
+            anyhow::anyhow!(
+                "Could not read the current user's SID ({}) — retry without --no-identify",
+                e
+            )
+        })?;
+        let identity = winbio_helpers::sid_identity(&sid).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Current user's SID is too long for WINBIO_IDENTITY — retry without --no-identify"
+            )
+        })?;
+        print_pass("Using the current user's SID as the identity");
+        winbio_helpers::print_identity(&identity);
+        check_credential_state(identity)?;
+        return Ok(());
+    }
+
     crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(None, winbio_helpers::SensorOperation::Identify)?;
 
-    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, true)?;
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
     print_step("Session opened with focus. Touch the sensor to identify yourself...");
 
     unsafe {
@@ -20,16 +40,21 @@ pub fn run_credential_state() -> Result<()> {
         let mut subfactor = 0u8;
         let mut reject_detail = 0u32;
 
-        WinBioIdentify(
-            guard.session,
-            Some(&mut unit_id),
-            Some(&mut identity),
-            Some(&mut subfactor),
-            Some(&mut reject_detail),
+        winbio_helpers::trace_call(
+            "WinBioIdentify",
+            &format!("session={}", guard.session),
+            WinBioIdentify(
+                guard.session,
+                Some(&mut unit_id),
+                Some(&mut identity),
+                Some(&mut subfactor),
+                Some(&mut reject_detail),
+            ),
         )
         .map_err(|e| {
             let code = crate::error::error_code(&e);
             if code == 0x8009_8005 {
+                crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
                 anyhow::anyhow!("No match — finger not enrolled. Cannot check credential state.")
             } else {
                 crate::error::wrap_winbio_error("WinBioIdentify", &e)
@@ -39,29 +64,40 @@ pub fn run_credential_state() -> Result<()> {
         print_pass("User identified on sensor");
         print_info("Unit ID", &unit_id.to_string());
         print_info("Finger", &winbio_helpers::subfactor_name(subfactor));
+        winbio_helpers::print_identity(&identity);
 
-        let credential_state = WinBioGetCredentialState(identity, WINBIO_CREDENTIAL_PASSWORD)
-            .map_err(|e| crate::error::wrap_winbio_error("WinBioGetCredentialState", &e))?;
-
-        println!();
-        if credential_state == WINBIO_CREDENTIAL_SET {
-            print_pass("Password credential is SET — Windows Hello login should work");
-        } else if credential_state == WINBIO_CREDENTIAL_NOT_SET {
-            print_fail("Password credential is NOT SET");
-            print_warn("This means no password hash is linked to the biometric identity.");
-            print_warn(
-                "This is a common cause of \"fingerprint enrolled but login doesn't work.\"",
-            );
-            print_step(
-                "Try: Settings → Accounts → Sign-in options → Remove and re-add fingerprint.",
-            );
-        } else {
-            print_info(
-                "Credential state",
-                &format!("Unknown ({})", credential_state.0),
-            );
-        }
+        check_credential_state(identity)?;
     }
 
     Ok(())
 }
+
+/// Query whether a Windows Hello password credential is linked to `identity`
+/// and print the verdict. Returns true if the credential is set.
+pub fn check_credential_state(identity: WINBIO_IDENTITY) -> Result<bool> {
+    let credential_state = unsafe {
+        winbio_helpers::trace_call(
+            "WinBioGetCredentialState",
+            "type=Password",
+            WinBioGetCredentialState(identity, WINBIO_CREDENTIAL_PASSWORD),
+        )
+    }
+    .map_err(|e| crate::error::wrap_winbio_error("WinBioGetCredentialState", &e))?;
+
+    print_blank();
+    if credential_state == WINBIO_CREDENTIAL_SET {
+        print_pass("Password credential is SET — Windows Hello login should work");
+    } else if credential_state == WINBIO_CREDENTIAL_NOT_SET {
+        print_fail("Password credential is NOT SET");
+        print_warn("This means no password hash is linked to the biometric identity.");
+        print_warn("This is a common cause of \"fingerprint enrolled but login doesn't work.\"");
+        print_step("Try: Settings → Accounts → Sign-in options → Remove and re-add fingerprint.");
+    } else {
+        print_info(
+            "Credential state",
+            &format!("Unknown ({})", credential_state.0),
+        );
+    }
+
+    Ok(credential_state == WINBIO_CREDENTIAL_SET)
+}
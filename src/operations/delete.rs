@@ -2,25 +2,45 @@ use anyhow::Result;
 use windows::Win32::Devices::BiometricFramework::*;
 
 use crate::output::*;
-use crate::winbio_helpers;
+use crate::winbio_helpers::{self, SensorPool};
 
 use super::SessionGuard;
 
-pub fn run_delete(finger: u8) -> Result<()> {
-    print_header(&format!(
-        "Delete Fingerprint — Finger {} ({})",
-        finger,
-        winbio_helpers::subfactor_name(finger)
-    ));
-
-    if !(1..=10).contains(&finger) {
-        print_fail("Finger must be 1–10");
-        return Ok(());
+/// Delete `finger` (or, with `None`, every enrolled finger) for the user who
+/// identifies on the sensor.
+pub fn run_delete(
+    finger: Option<u8>,
+    unit: Option<u32>,
+    timeout: Option<u64>,
+    dry_run: bool,
+    yes: bool,
+    pool: SensorPool,
+) -> Result<()> {
+    match finger {
+        Some(finger) => {
+            print_header(&format!(
+                "Delete Fingerprint — Finger {} ({})",
+                finger,
+                winbio_helpers::subfactor_name(finger)
+            ));
+            if !(1..=10).contains(&finger) {
+                print_fail("Finger must be 1–10");
+                return Ok(());
+            }
+            crate::finger_guide::print_finger_guide(finger);
+        }
+        None => print_header("Delete All Fingerprints"),
     }
 
     crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(unit, winbio_helpers::SensorOperation::Identify)?;
+
+    let target_unit = match unit {
+        Some(_) => Some(winbio_helpers::resolve_unit_id(unit)?),
+        None => None,
+    };
 
-    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, true)?;
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
 
     // Identify user first
     print_step("Touch the sensor to identify yourself...");
@@ -31,42 +51,172 @@ pub fn run_delete(finger: u8) -> Result<()> {
         let mut subfactor = 0u8;
         let mut reject_detail = 0u32;
 
-        WinBioIdentify(
-            guard.session,
-            Some(&mut unit_id),
-            Some(&mut identity),
-            Some(&mut subfactor),
-            Some(&mut reject_detail),
-        )
-        .map_err(|e| crate::error::wrap_winbio_error("WinBioIdentify", &e))?;
+        guard
+            .run_with_timeout(timeout, |session| {
+                winbio_helpers::trace_call(
+                    "WinBioIdentify",
+                    &format!("session={}", session),
+                    WinBioIdentify(
+                        session,
+                        Some(&mut unit_id),
+                        Some(&mut identity),
+                        Some(&mut subfactor),
+                        Some(&mut reject_detail),
+                    ),
+                )
+            })?
+            .map_err(|e| crate::error::wrap_winbio_error("WinBioIdentify", &e))?;
 
         print_pass("User identified");
-        print_step(&format!(
-            "Deleting finger {} ({}) from unit {}...",
-            finger,
-            winbio_helpers::subfactor_name(finger),
-            unit_id
-        ));
-
-        let result = WinBioDeleteTemplate(guard.session, unit_id, &identity, finger);
-
-        if let Err(e) = result {
-            let code = crate::error::error_code(&e);
-            if code == 0x8009_8016 {
-                // WINBIO_E_DATABASE_NO_SUCH_RECORD
-                print_fail("No enrollment found for that finger — nothing to delete");
-            } else {
-                return Err(crate::error::wrap_winbio_error("WinBioDeleteTemplate", &e));
+
+        // Delete from the requested unit, or the unit that identified the user
+        let unit_id = target_unit.unwrap_or(unit_id);
+
+        let fingers = match finger {
+            Some(finger) => {
+                if !require_enrolled(guard.session, unit_id, &identity, finger) {
+                    return Ok(());
+                }
+                vec![finger]
+            }
+            None => {
+                let enrolled = enum_enrollments(guard.session, unit_id, &identity)?;
+                if enrolled.is_empty() {
+                    print_warn("No enrolled fingerprints found for this identity");
+                    return Ok(());
+                }
+                print_info(
+                    "Enrolled",
+                    &enrolled
+                        .iter()
+                        .map(|&sf| winbio_helpers::subfactor_name(sf))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+                enrolled
+            }
+        };
+
+        if dry_run {
+            for &finger in &fingers {
+                print_step(&format!(
+                    "Would delete finger {} ({}) from unit {} (WinBioDeleteTemplate)",
+                    finger,
+                    winbio_helpers::subfactor_name(finger),
+                    unit_id
+                ));
             }
+            print_pass("Dry run — nothing was changed");
             return Ok(());
         }
 
-        print_pass(&format!(
-            "Successfully deleted finger {} ({})",
-            finger,
-            winbio_helpers::subfactor_name(finger)
-        ));
+        let expected = match finger {
+            Some(finger) => finger.to_string(),
+            None => "ALL".to_string(),
+        };
+        if !super::confirm_destructive(&expected, yes)? {
+            return Ok(());
+        }
+
+        for &finger in &fingers {
+            print_step(&format!(
+                "Deleting finger {} ({}) from unit {}...",
+                finger,
+                winbio_helpers::subfactor_name(finger),
+                unit_id
+            ));
+
+            let result = winbio_helpers::trace_call(
+                "WinBioDeleteTemplate",
+                &format!(
+                    "session={}, unit={}, subfactor={}",
+                    guard.session, unit_id, finger
+                ),
+                WinBioDeleteTemplate(guard.session, unit_id, &identity, finger),
+            );
+
+            match result {
+                Ok(()) => print_pass(&format!(
+                    "Successfully deleted finger {} ({})",
+                    finger,
+                    winbio_helpers::subfactor_name(finger)
+                )),
+                // WINBIO_E_DATABASE_NO_SUCH_RECORD
+                Err(e) if crate::error::error_code(&e) == 0x8009_801B => {
+                    if fingers.len() == 1 {
+                        print_fail("No enrollment found for that finger — nothing to delete");
+                    } else {
+                        print_warn("No enrollment found for that finger — skipping");
+                    }
+                }
+                Err(e) => return Err(crate::error::wrap_winbio_error("WinBioDeleteTemplate", &e)),
+            }
+        }
     }
 
     Ok(())
 }
+
+/// Check up front that `finger` is enrolled for `identity` on `unit_id`, and
+/// if not, fail naming the fingers that are. Also true when the enrollments
+/// can't be listed: the operation's own no-match handling then decides.
+pub(crate) unsafe fn require_enrolled(
+    session: u32,
+    unit_id: u32,
+    identity: &WINBIO_IDENTITY,
+    finger: u8,
+) -> bool {
+    let enrolled = match enum_enrollments(session, unit_id, identity) {
+        Ok(enrolled) => enrolled,
+        Err(e) => {
+            print_trace(&format!("Could not check enrollments first: {:#}", e));
+            return true;
+        }
+    };
+    if enrolled.contains(&finger) {
+        return true;
+    }
+    print_fail(&format!(
+        "Finger {} ({}) is not enrolled on unit {}",
+        finger,
+        winbio_helpers::subfactor_name(finger),
+        unit_id
+    ));
+    let names: Vec<String> = enrolled
+        .iter()
+        .map(|&sf| format!("{} ({})", sf, winbio_helpers::subfactor_name(sf)))
+        .collect();
+    if names.is_empty() {
+        print_info("Enrolled", "none");
+    } else {
+        print_info("Enrolled", &names.join(", "));
+    }
+    false
+}
+
+/// Subfactors enrolled for `identity` on `unit_id`.
+pub(crate) unsafe fn enum_enrollments(
+    session: u32,
+    unit_id: u32,
+    identity: &WINBIO_IDENTITY,
+) -> Result<Vec<u8>> {
+    let mut subfactor_array: *mut u8 = std::ptr::null_mut();
+    let mut subfactor_count: usize = 0;
+
+    winbio_helpers::trace_call(
+        "WinBioEnumEnrollments",
+        &format!("session={}, unit={}", session, unit_id),
+        WinBioEnumEnrollments(
+            session,
+            unit_id,
+            identity,
+            &mut subfactor_array,
+            Some(&mut subfactor_count),
+        ),
+    )
+    .map_err(|e| crate::error::wrap_winbio_error("WinBioEnumEnrollments", &e))?;
+
+    let subfactors = winbio_helpers::winbio_slice(subfactor_array, subfactor_count).to_vec();
+    winbio_helpers::winbio_free(subfactor_array as *const _);
+    Ok(subfactors)
+}
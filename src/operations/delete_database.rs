@@ -108,44 +108,34 @@ fn delete_database_registry_key(db_id: &str) -> Result<()> {
     Ok(())
 }
 
-fn format_guid(guid: &windows::core::GUID) -> String {
-    format!(
-        "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
-        guid.data1,
-        guid.data2,
-        guid.data3,
-        guid.data4[0],
-        guid.data4[1],
-        guid.data4[2],
-        guid.data4[3],
-        guid.data4[4],
-        guid.data4[5],
-        guid.data4[6],
-        guid.data4[7]
-    )
-}
-
-struct DatabaseTarget {
-    index: usize,
-    db_id: String,
-    file_path: String,
+pub(crate) struct DatabaseTarget {
+    pub(crate) index: usize,
+    pub(crate) db_id: String,
+    pub(crate) file_path: String,
 }
 
 /// Enumerate registered databases and return the targets to operate on.
 /// When `all` is true and no databases are registered, returns an empty vec instead of an error.
-fn enumerate_targets(db_number: Option<usize>, all: bool) -> Result<Vec<DatabaseTarget>> {
+pub(crate) fn enumerate_targets(
+    db_number: Option<usize>,
+    all: bool,
+) -> Result<Vec<DatabaseTarget>> {
     unsafe {
         let mut schema_array: *mut WINBIO_STORAGE_SCHEMA = std::ptr::null_mut();
         let mut schema_count: usize = 0;
 
-        WinBioEnumDatabases(
-            winbio_helpers::WINBIO_TYPE_FINGERPRINT,
-            &mut schema_array,
-            &mut schema_count,
+        winbio_helpers::trace_call(
+            "WinBioEnumDatabases",
+            &format!("factor={}", winbio_helpers::biometric_type().name()),
+            WinBioEnumDatabases(
+                winbio_helpers::biometric_type().factor(),
+                &mut schema_array,
+                &mut schema_count,
+            ),
         )
         .map_err(|e| crate::error::wrap_winbio_error("WinBioEnumDatabases", &e))?;
 
-        if schema_count == 0 {
+        if schema_count == 0 || schema_array.is_null() {
             if !schema_array.is_null() {
                 winbio_helpers::winbio_free(schema_array as *const _);
             }
@@ -172,7 +162,7 @@ fn enumerate_targets(db_number: Option<usize>, all: bool) -> Result<Vec<Database
                 let schema = &schemas[n - 1];
                 vec![DatabaseTarget {
                     index: n,
-                    db_id: format_guid(&schema.DatabaseId),
+                    db_id: winbio_helpers::format_guid(&schema.DatabaseId),
                     file_path: winbio_helpers::wchar_to_string(&schema.FilePath),
                 }]
             }
@@ -181,7 +171,7 @@ fn enumerate_targets(db_number: Option<usize>, all: bool) -> Result<Vec<Database
                 .enumerate()
                 .map(|(i, schema)| DatabaseTarget {
                     index: i + 1,
-                    db_id: format_guid(&schema.DatabaseId),
+                    db_id: winbio_helpers::format_guid(&schema.DatabaseId),
                     file_path: winbio_helpers::wchar_to_string(&schema.FilePath),
                 })
                 .collect(),
@@ -197,7 +187,7 @@ fn enumerate_targets(db_number: Option<usize>, all: bool) -> Result<Vec<Database
 
 /// Find .DAT files in the WINBIODATABASE directory that aren't in the registered set.
 /// Returns the paths of orphaned files.
-fn find_orphaned_dat_files(registered_paths: &[&str]) -> Vec<std::path::PathBuf> {
+pub(crate) fn find_orphaned_dat_files(registered_paths: &[&str]) -> Vec<std::path::PathBuf> {
     let db_dir = std::path::Path::new(r"C:\WINDOWS\SYSTEM32\WINBIODATABASE");
     let entries = match std::fs::read_dir(db_dir) {
         Ok(e) => e,
@@ -220,15 +210,37 @@ fn find_orphaned_dat_files(registered_paths: &[&str]) -> Vec<std::path::PathBuf>
 }
 
 /// Process a single database target. Returns true if all operations succeeded.
-fn process_target(target: &DatabaseTarget, delete_file: bool, delete_registry: bool) -> bool {
+/// With `dry_run`, only print what would be deleted.
+fn process_target(
+    target: &DatabaseTarget,
+    delete_file: bool,
+    delete_registry: bool,
+    dry_run: bool,
+) -> bool {
     let mut ok = true;
 
-    println!();
+    print_blank();
     print_step(&format!("Database {} — {}", target.index, target.db_id));
 
     let file_exists =
         !target.file_path.is_empty() && std::path::Path::new(&target.file_path).exists();
 
+    if dry_run {
+        if delete_file && file_exists {
+            print_info("  Would delete file", &target.file_path);
+        }
+        if delete_registry {
+            print_info(
+                "  Would delete registry key",
+                &format!(
+                    "HKLM\\SYSTEM\\CurrentControlSet\\Services\\WbioSrvc\\Databases\\{}",
+                    target.db_id
+                ),
+            );
+        }
+        return ok;
+    }
+
     if delete_file {
         if file_exists {
             if let Ok(meta) = std::fs::metadata(&target.file_path) {
@@ -264,13 +276,22 @@ pub fn run_delete_database(
     all: bool,
     delete_file: bool,
     delete_registry: bool,
+    dry_run: bool,
+    yes: bool,
+    stop_service: bool,
 ) -> Result<()> {
     if !delete_file && !delete_registry {
         bail!("Specify --file to delete the .DAT file, --registry to remove the registry entry, or both");
     }
 
-    if !crate::elevation::is_elevated()? {
-        bail!("This command requires Administrator privileges. Re-run as Administrator.");
+    // With --stop-service, a file deletion leaves WbioSrvc stopped so it can't
+    // recreate the .DAT files; a registry-only deletion still restarts it.
+    let keep_stopped = stop_service && delete_file;
+
+    if dry_run {
+        crate::elevation::warn_if_not_elevated();
+    } else {
+        crate::elevation::require_elevated()?;
     }
 
     let targets = enumerate_targets(if all { None } else { db_number }, all)?;
@@ -285,7 +306,7 @@ pub fn run_delete_database(
 
     let total_work = targets.len() + orphans.len();
     if total_work == 0 {
-        println!();
+        print_blank();
         print_info(
             "Nothing to do",
             "no registered databases or .DAT files found",
@@ -350,6 +371,40 @@ pub fn run_delete_database(
         }
     }
 
+    if dry_run {
+        let running = unsafe { super::service::query_service_state() }
+            .map(|state| state == SERVICE_RUNNING.0)
+            .unwrap_or(false);
+        print_step(if running && keep_stopped {
+            "Would stop WbioSrvc and leave it stopped"
+        } else if running {
+            "Would stop WbioSrvc, then restart it afterwards"
+        } else {
+            "WbioSrvc is not running — would leave it stopped"
+        });
+        for target in &targets {
+            process_target(target, delete_file, delete_registry, true);
+        }
+        for path in &orphans {
+            print_blank();
+            print_info("Would delete orphan", &path.to_string_lossy());
+        }
+        print_blank();
+        print_pass("Dry run — nothing was changed");
+        return Ok(());
+    }
+
+    print_step("To keep a copy first, run backup-databases --output <dir>");
+
+    let expected = if all {
+        "ALL".to_string()
+    } else {
+        targets[0].index.to_string()
+    };
+    if !super::confirm_destructive(&expected, yes)? {
+        return Ok(());
+    }
+
     // Stop the service
     print_step("Stopping WbioSrvc service...");
     let was_running = unsafe { stop_wbiosrvc()? };
@@ -362,14 +417,14 @@ pub fn run_delete_database(
     // Process registered targets
     let mut any_error = false;
     for target in &targets {
-        if !process_target(target, delete_file, delete_registry) {
+        if !process_target(target, delete_file, delete_registry, false) {
             any_error = true;
         }
     }
 
     // Delete orphaned files
     for path in &orphans {
-        println!();
+        print_blank();
         print_step(&format!("Orphan: {}", path.display()));
         match std::fs::remove_file(path) {
             Ok(()) => print_pass(&format!("  Deleted {}", path.display())),
@@ -381,13 +436,19 @@ pub fn run_delete_database(
     }
 
     // Restart the service
-    if was_running {
-        println!();
+    if was_running && keep_stopped {
+        print_blank();
+        print_info(
+            "WbioSrvc",
+            "left stopped (--stop-service) — run start-service when ready",
+        );
+    } else if was_running {
+        print_blank();
         print_step("Restarting WbioSrvc service...");
         unsafe { start_wbiosrvc()? };
         print_pass("WbioSrvc restarted");
     } else {
-        println!();
+        print_blank();
         print_info(
             "Note",
             "WbioSrvc was not running — start it manually if needed",
@@ -398,7 +459,7 @@ pub fn run_delete_database(
         bail!("Some operations failed (see above)");
     }
 
-    println!();
+    print_blank();
     let file_count = targets.iter().filter(|t| !t.file_path.is_empty()).count() + orphans.len();
     if delete_registry && targets.is_empty() && !orphans.is_empty() {
         print_pass(&format!("{} orphaned file(s) deleted", orphans.len()));
@@ -414,10 +475,10 @@ pub fn run_delete_database(
             print_step("Service will recreate clean empty databases");
         }
     }
-    if delete_file && was_running {
+    if delete_file && was_running && !keep_stopped {
         print_warn(
             "WbioSrvc was restarted and may recreate .DAT files for active sensors. \
-             To prevent this, use stop-service before deleting and start-service after.",
+             To prevent this, pass --stop-service to leave it stopped.",
         );
     }
     print_step("Re-enroll fingerprints via Windows Settings > Accounts > Sign-in options");
@@ -1,9 +1,11 @@
 use anyhow::Result;
+use serde_json::json;
+use std::collections::BTreeMap;
 use windows::core::HRESULT;
 use windows::Win32::Devices::BiometricFramework::*;
 
 use crate::output::*;
-use crate::winbio_helpers;
+use crate::winbio_helpers::{self, SensorPool};
 
 use super::SessionGuard;
 
@@ -17,29 +19,220 @@ unsafe extern "system" {
 const WINBIO_I_MORE_DATA: HRESULT = HRESULT(0x0009_0001_u32 as i32);
 const WINBIO_E_BAD_CAPTURE: HRESULT = HRESULT(0x8009_8008_u32 as i32);
 
-const MAX_SAMPLES: u32 = 20;
+/// Capture attempts per finger, good and bad, before giving up (`--max-samples`).
+pub const DEFAULT_MAX_SAMPLES: u32 = 20;
 
-pub fn run_enroll(finger: u8) -> Result<()> {
-    print_header(&format!(
-        "Enroll Fingerprint — Finger {} ({})",
-        finger,
-        winbio_helpers::subfactor_name(finger)
-    ));
+/// How each finger is enrolled; the same for every finger of a run, except
+/// that `resume`/`discard` only apply to the first.
+#[derive(Clone, Copy)]
+pub struct EnrollOptions {
+    /// Continue an enrollment left in progress by an interrupted run.
+    pub resume: bool,
+    /// Discard an enrollment left in progress and start over.
+    pub discard: bool,
+    /// Offer to discard a template with fewer good samples than this.
+    pub min_samples: Option<u32>,
+    /// Capture attempts before the enrollment is discarded.
+    pub max_samples: u32,
+    /// Seconds to wait for each touch before cancelling the capture.
+    pub sample_timeout: Option<u64>,
+    /// On a duplicate, delete the own template for the finger and enroll again.
+    pub overwrite: bool,
+}
+
+/// How one finger's begin/capture/commit cycle ended.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EnrollOutcome {
+    Enrolled,
+    /// Duplicate of this finger's template, deleted and enrolled again (`--overwrite`).
+    Replaced,
+    Duplicate,
+    Failed,
+    /// Another enrollment is in progress; later fingers would hit it too.
+    Blocked,
+    /// No touch arrived within `--sample-timeout`; the sensor may be dead.
+    TimedOut,
+}
+
+pub fn run_enroll(
+    fingers: &[u8],
+    unit: Option<u32>,
+    options: EnrollOptions,
+    pool: SensorPool,
+) -> Result<()> {
+    if let [finger] = fingers {
+        print_header(&format!(
+            "Enroll Fingerprint — Finger {} ({})",
+            finger,
+            winbio_helpers::subfactor_name(*finger)
+        ));
+    } else {
+        print_header(&format!("Enroll Fingerprints — {} fingers", fingers.len()));
+    }
 
-    if !(1..=10).contains(&finger) {
-        print_fail("Finger must be 1–10");
+    if let Some(bad) = fingers.iter().find(|f| !(1..=10).contains(*f)) {
+        print_fail(&format!("Finger must be 1–10 (got {})", bad));
         return Ok(());
     }
 
     crate::elevation::warn_if_not_elevated();
 
-    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, true)?;
-
-    // Get the first fingerprint sensor unit ID via enumeration.
+    // Resolve the sensor unit via enumeration, skipping units that can't enroll.
     // This works even when no fingers are enrolled (unlike the identify-first approach).
-    let unit_id = get_first_unit_id()?;
-    print_info("Using sensor unit", &unit_id.to_string());
+    let unit_id = winbio_helpers::resolve_unit_for(unit, winbio_helpers::SensorOperation::Enroll)?;
+
+    // One session (and focus window) for every finger
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
+
+    if let [finger] = fingers {
+        crate::finger_guide::print_finger_guide(*finger);
+        enroll_finger(&guard, *finger, unit_id, &options)?;
+        return Ok(());
+    }
+
+    let mut results = Vec::new();
+    for (i, &finger) in fingers.iter().enumerate() {
+        print_blank();
+        print_step(&format!(
+            "Finger {} of {}: {} ({})",
+            i + 1,
+            fingers.len(),
+            finger,
+            winbio_helpers::subfactor_name(finger)
+        ));
+        crate::finger_guide::print_finger_guide(finger);
+
+        // Only the first finger can run into an enrollment left behind by an earlier run
+        let first = EnrollOptions {
+            resume: options.resume && i == 0,
+            discard: options.discard && i == 0,
+            ..options
+        };
+        let outcome = match enroll_finger(&guard, finger, unit_id, &first) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                print_fail(&format!("{:#}", e));
+                results.push((finger, EnrollOutcome::Failed));
+                break;
+            }
+        };
+        results.push((finger, outcome));
+        if matches!(outcome, EnrollOutcome::Blocked | EnrollOutcome::TimedOut) {
+            break;
+        }
+    }
+
+    print_header("Enrollment Summary");
+    for &finger in fingers {
+        let status = match results.iter().find(|(f, _)| *f == finger).map(|(_, o)| *o) {
+            Some(EnrollOutcome::Enrolled) => "Enrolled",
+            Some(EnrollOutcome::Replaced) => "Re-enrolled — existing template replaced",
+            Some(EnrollOutcome::Duplicate) => "Duplicate — already enrolled",
+            Some(EnrollOutcome::Failed) => "Failed",
+            Some(EnrollOutcome::Blocked) => "Blocked by an enrollment in progress",
+            Some(EnrollOutcome::TimedOut) => "Timed out waiting for a touch",
+            None => "Not attempted",
+        };
+        print_info(
+            &format!("  {} ({})", finger, winbio_helpers::subfactor_name(finger)),
+            status,
+        );
+    }
+    let enrolled = results
+        .iter()
+        .filter(|(_, o)| matches!(o, EnrollOutcome::Enrolled | EnrollOutcome::Replaced))
+        .count();
+    print_blank();
+    if enrolled == fingers.len() {
+        print_pass(&format!("All {} fingers enrolled", enrolled));
+    } else {
+        print_warn(&format!(
+            "{} of {} fingers enrolled",
+            enrolled,
+            fingers.len()
+        ));
+    }
+
+    Ok(())
+}
 
+/// Ask whether to keep a template that completed with fewer good samples than
+/// `--min-samples`. Without a terminal to ask on, the template is discarded.
+fn keep_short_template(good_samples: u32, min_samples: u32) -> Result<bool> {
+    use std::io::IsTerminal;
+
+    print_warn(&format!(
+        "Template completed after only {} good sample(s) (--min-samples {}) — the driver may be reporting completion too early",
+        good_samples, min_samples
+    ));
+    if !std::io::stdin().is_terminal() {
+        print_warn("stdin is not a terminal — discarding the template");
+        return Ok(false);
+    }
+    print_step("Type 'commit' to keep it anyway, or anything else to discard it");
+    super::confirm_destructive("commit", false)
+}
+
+/// Delete the current user's template for `finger` so it can be enrolled again.
+/// Returns false when there is none, i.e. the duplicate belongs to another
+/// finger or account.
+unsafe fn delete_own_template(guard: &SessionGuard, finger: u8, unit_id: u32) -> Result<bool> {
+    let sid = crate::elevation::current_user_sid()?;
+    let identity = winbio_helpers::sid_identity(&sid)
+        .ok_or_else(|| anyhow::anyhow!("Current user's SID is too long for WINBIO_IDENTITY"))?;
+
+    match winbio_helpers::trace_call(
+        "WinBioDeleteTemplate",
+        &format!(
+            "session={}, unit={}, subfactor={}",
+            guard.session, unit_id, finger
+        ),
+        WinBioDeleteTemplate(guard.session, unit_id, &identity, finger),
+    ) {
+        Ok(()) => Ok(true),
+        // WINBIO_E_DATABASE_NO_SUCH_RECORD
+        Err(e) if crate::error::error_code(&e) == 0x8009_801B => Ok(false),
+        Err(e) => Err(crate::error::wrap_winbio_error("WinBioDeleteTemplate", &e)),
+    }
+}
+
+/// "12 bad captures: 7 too fast, 3 poor quality, 2 too left", most frequent
+/// reason first, from reject detail -> count.
+fn reject_summary(rejects: &BTreeMap<u32, u32>) -> String {
+    let total: u32 = rejects.values().sum();
+    let mut counts: Vec<_> = rejects.iter().collect();
+    // Stable sort: equal counts keep reject-detail order
+    counts.sort_by(|a, b| b.1.cmp(a.1));
+    let breakdown: Vec<String> = counts
+        .iter()
+        .map(|(&detail, count)| {
+            format!(
+                "{} {}",
+                count,
+                winbio_helpers::reject_reason(detail).to_lowercase()
+            )
+        })
+        .collect();
+    format!("{} bad captures: {}", total, breakdown.join(", "))
+}
+
+/// Run the begin/capture-loop/commit cycle for one finger on an open session.
+/// With `overwrite`, a duplicate of this finger's own template is deleted and
+/// the cycle runs once more.
+fn enroll_finger(
+    guard: &SessionGuard,
+    finger: u8,
+    unit_id: u32,
+    options: &EnrollOptions,
+) -> Result<EnrollOutcome> {
+    let EnrollOptions {
+        resume,
+        discard,
+        min_samples,
+        max_samples,
+        sample_timeout,
+        overwrite,
+    } = *options;
     unsafe {
         // Begin enrollment
         print_step(&format!(
@@ -48,33 +241,155 @@ pub fn run_enroll(finger: u8) -> Result<()> {
             winbio_helpers::subfactor_name(finger)
         ));
 
-        if let Err(e) = WinBioEnrollBegin(guard.session, finger, unit_id) {
-            return Err(crate::error::wrap_winbio_error("WinBioEnrollBegin", &e));
+        let begin = || {
+            winbio_helpers::with_busy_retry(|| {
+                winbio_helpers::trace_call(
+                    "WinBioEnrollBegin",
+                    &format!(
+                        "session={}, subfactor={}, unit={}",
+                        guard.session, finger, unit_id
+                    ),
+                    WinBioEnrollBegin(guard.session, finger, unit_id),
+                )
+            })
+        };
+
+        match begin() {
+            Ok(()) => {}
+            Err(e) if crate::error::error_code(&e) == 0x8009_8007 => {
+                // WINBIO_E_ENROLLMENT_IN_PROGRESS — left behind by an interrupted run
+                if resume {
+                    print_warn("An enrollment is already in progress — resuming it");
+                } else if discard {
+                    print_warn("An enrollment is already in progress — discarding it");
+                    let _ = winbio_helpers::trace_call(
+                        "WinBioEnrollDiscard",
+                        &format!("session={}", guard.session),
+                        WinBioEnrollDiscard(guard.session),
+                    );
+                    begin()
+                        .map_err(|e| crate::error::wrap_winbio_error("WinBioEnrollBegin", &e))?;
+                } else {
+                    print_fail("Another enrollment is already in progress on this sensor");
+                    print_step("Re-run with --resume to finish the in-progress enrollment");
+                    print_step("Re-run with --discard to throw it away and start over");
+                    return Ok(EnrollOutcome::Blocked);
+                }
+            }
+            Err(e) => return Err(crate::error::wrap_winbio_error("WinBioEnrollBegin", &e)),
+        }
+
+        // How many good samples the adapter wants, when it says
+        let needed = super::sensor_properties::sample_hint(guard.session, unit_id);
+        if let Some(needed) = needed {
+            print_info("Samples needed", &needed.to_string());
         }
+        let progress = |good: u32| match needed {
+            Some(needed) => format!(
+                "{} of {} ({}%)",
+                good,
+                needed,
+                (good * 100 / needed).min(100)
+            ),
+            None => good.to_string(),
+        };
 
         // Capture loop
         let mut sample_num = 0u32;
+        let mut good_samples = 0u32;
+        // Reject detail -> how often it was seen, to coach the user on failure
+        let mut rejects: BTreeMap<u32, u32> = BTreeMap::new();
         loop {
             sample_num += 1;
-            if sample_num > MAX_SAMPLES {
-                print_fail("Too many capture attempts — discarding enrollment");
-                let _ = WinBioEnrollDiscard(guard.session);
-                return Ok(());
+            if sample_num > max_samples {
+                print_fail(&format!(
+                    "Too many capture attempts ({}, --max-samples) — discarding enrollment",
+                    max_samples
+                ));
+                if !rejects.is_empty() {
+                    print_step(&reject_summary(&rejects));
+                    for (detail, count) in &rejects {
+                        record(
+                            "reject_details",
+                            json!({
+                                "finger": finger,
+                                "reason": winbio_helpers::reject_reason(*detail),
+                                "count": count,
+                            }),
+                        );
+                    }
+                }
+                let _ = winbio_helpers::trace_call(
+                    "WinBioEnrollDiscard",
+                    &format!("session={}", guard.session),
+                    WinBioEnrollDiscard(guard.session),
+                );
+                return Ok(EnrollOutcome::Failed);
             }
 
             print_step(&format!("Touch the sensor (sample {})...", sample_num));
 
             let mut reject_detail = 0u32;
-            let hr = WinBioEnrollCapture(guard.session, &mut reject_detail);
+            // Keep the success code so WINBIO_I_MORE_DATA stays distinguishable from S_OK
+            let capture = guard.run_with_timeout(sample_timeout, |session| {
+                winbio_helpers::with_busy_retry(|| {
+                    let hr = WinBioEnrollCapture(session, &mut reject_detail);
+                    winbio_helpers::trace_hresult(
+                        "WinBioEnrollCapture",
+                        &format!("session={}", session),
+                        hr,
+                    );
+                    hr.ok().map(|()| hr)
+                })
+            });
+            let hr = match capture {
+                Ok(result) => result.unwrap_or_else(|e| e.code()),
+                Err(e) => {
+                    // No touch at all, as opposed to a touch the sensor rejected
+                    let _ = winbio_helpers::trace_call(
+                        "WinBioEnrollDiscard",
+                        &format!("session={}", guard.session),
+                        WinBioEnrollDiscard(guard.session),
+                    );
+                    print_fail(&format!(
+                        "Sample {}: {} — enrollment discarded",
+                        sample_num, e
+                    ));
+                    print_step(
+                        "The sensor may have stopped responding; check it with check-sensor",
+                    );
+                    return Ok(EnrollOutcome::TimedOut);
+                }
+            };
+
+            if super::cancel_requested() {
+                // Ctrl+C cancelled the capture; don't leave a half-built template behind
+                let _ = winbio_helpers::trace_call(
+                    "WinBioEnrollDiscard",
+                    &format!("session={}", guard.session),
+                    WinBioEnrollDiscard(guard.session),
+                );
+                print_warn("Cancelled — in-progress enrollment discarded");
+                anyhow::bail!("Enrollment cancelled by Ctrl+C");
+            }
 
             if hr == HRESULT(0) {
                 // S_OK — template complete
-                print_pass("Template complete");
+                good_samples += 1;
+                print_pass(&format!(
+                    "Template complete after {} good sample(s)",
+                    good_samples
+                ));
                 break;
             } else if hr == WINBIO_I_MORE_DATA {
-                print_info("  Status", "Good sample — more needed");
+                good_samples += 1;
+                print_info(
+                    "  Status",
+                    &format!("Good sample — more needed ({})", progress(good_samples)),
+                );
                 continue;
             } else if hr == WINBIO_E_BAD_CAPTURE {
+                *rejects.entry(reject_detail).or_default() += 1;
                 print_warn(&format!(
                     "Bad capture: {} — try again",
                     winbio_helpers::reject_reason(reject_detail)
@@ -88,26 +403,84 @@ pub fn run_enroll(finger: u8) -> Result<()> {
                     crate::error::hresult_message(hr),
                     hr.0 as u32
                 ));
-                let _ = WinBioEnrollDiscard(guard.session);
+                let _ = winbio_helpers::trace_call(
+                    "WinBioEnrollDiscard",
+                    &format!("session={}", guard.session),
+                    WinBioEnrollDiscard(guard.session),
+                );
                 return Err(crate::error::wrap_winbio_error("WinBioEnrollCapture", &err));
             }
         }
 
+        if let Some(min) = min_samples.filter(|&min| good_samples < min) {
+            if !keep_short_template(good_samples, min)? {
+                let _ = winbio_helpers::trace_call(
+                    "WinBioEnrollDiscard",
+                    &format!("session={}", guard.session),
+                    WinBioEnrollDiscard(guard.session),
+                );
+                print_warn("Enrollment discarded — nothing was committed");
+                return Ok(EnrollOutcome::Failed);
+            }
+        }
+
         // Commit the enrollment
         print_step("Committing enrollment...");
         let mut identity = WINBIO_IDENTITY::default();
         let mut is_new_template: u8 = 0;
 
-        if let Err(e) = WinBioEnrollCommit(
-            guard.session,
-            Some(&mut identity),
-            Some(&mut is_new_template),
+        if let Err(e) = winbio_helpers::trace_call(
+            "WinBioEnrollCommit",
+            &format!("session={}", guard.session),
+            WinBioEnrollCommit(
+                guard.session,
+                Some(&mut identity),
+                Some(&mut is_new_template),
+            ),
         ) {
             let code = crate::error::error_code(&e);
-            let _ = WinBioEnrollDiscard(guard.session);
-            if code == 0x8009_8015 {
-                print_fail("Duplicate enrollment — this finger is already enrolled");
-                return Ok(());
+            let _ = winbio_helpers::trace_call(
+                "WinBioEnrollDiscard",
+                &format!("session={}", guard.session),
+                WinBioEnrollDiscard(guard.session),
+            );
+            if code == 0x8009_801C {
+                // WINBIO_E_DUPLICATE_ENROLLMENT
+                if !overwrite {
+                    print_fail("Duplicate enrollment — this finger is already enrolled");
+                    print_step("Re-run with --overwrite to replace the existing template");
+                    return Ok(EnrollOutcome::Duplicate);
+                }
+                print_warn("Duplicate enrollment — deleting the existing template (--overwrite)");
+                if !delete_own_template(guard, finger, unit_id)? {
+                    print_fail(&format!(
+                        "No template for your finger {} ({}) — the duplicate belongs to another finger or account",
+                        finger,
+                        winbio_helpers::subfactor_name(finger)
+                    ));
+                    print_step("Find it with identify, then remove it with delete");
+                    return Ok(EnrollOutcome::Duplicate);
+                }
+                print_pass("Existing template deleted — enrolling again");
+                return Ok(
+                    match enroll_finger(
+                        guard,
+                        finger,
+                        unit_id,
+                        &EnrollOptions {
+                            resume: false,
+                            discard: false,
+                            overwrite: false,
+                            ..*options
+                        },
+                    )? {
+                        EnrollOutcome::Enrolled => {
+                            print_pass("Existing template replaced");
+                            EnrollOutcome::Replaced
+                        }
+                        other => other,
+                    },
+                );
             }
             return Err(crate::error::wrap_winbio_error("WinBioEnrollCommit", &e));
         }
@@ -126,43 +499,8 @@ pub fn run_enroll(finger: u8) -> Result<()> {
             },
         );
 
-        // Print identity info
-        if identity.Type == winbio_helpers::WINBIO_ID_TYPE_SID {
-            let sid_data = &identity.Value.AccountSid;
-            let size = sid_data.Size as usize;
-            let bytes = &sid_data.Data[..size.min(sid_data.Data.len())];
-            let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
-            print_info("Identity (SID)", &hex.join(" "));
-        } else {
-            print_info("Identity type", &format!("{}", identity.Type));
-        }
+        winbio_helpers::print_identity(&identity);
     }
 
-    Ok(())
-}
-
-/// Enumerate biometric units and return the first fingerprint sensor's unit ID.
-fn get_first_unit_id() -> Result<u32> {
-    unsafe {
-        let mut unit_array: *mut WINBIO_UNIT_SCHEMA = std::ptr::null_mut();
-        let mut unit_count: usize = 0;
-
-        WinBioEnumBiometricUnits(
-            winbio_helpers::WINBIO_TYPE_FINGERPRINT,
-            &mut unit_array,
-            &mut unit_count,
-        )
-        .map_err(|e| crate::error::wrap_winbio_error("WinBioEnumBiometricUnits", &e))?;
-
-        if unit_count == 0 {
-            if !unit_array.is_null() {
-                winbio_helpers::winbio_free(unit_array as *const _);
-            }
-            anyhow::bail!("No fingerprint biometric units found");
-        }
-
-        let unit_id = (*unit_array).UnitId;
-        winbio_helpers::winbio_free(unit_array as *const _);
-        Ok(unit_id)
-    }
+    Ok(EnrollOutcome::Enrolled)
 }
@@ -0,0 +1,191 @@
+use anyhow::Result;
+use serde_json::json;
+use windows::core::PCWSTR;
+use windows::Win32::Devices::BiometricFramework::*;
+use windows::Win32::System::Registry::*;
+
+use crate::diagnostics::version_info::resolve_system_binary;
+use crate::output::*;
+use crate::winbio_helpers;
+
+use super::enum_databases::{configured_adapters, enum_registry_subkeys, read_registry_string};
+
+const SERVICE_PROVIDERS_KEY: &str =
+    "SYSTEM\\CurrentControlSet\\Services\\WbioSrvc\\Service Providers";
+
+/// List the biometric service providers (BSPs) WinBio reports for the `--type`.
+fn print_service_providers() {
+    print_header("Biometric Service Providers (WinBioEnumServiceProviders)");
+
+    unsafe {
+        let mut bsp_array: *mut WINBIO_BSP_SCHEMA = std::ptr::null_mut();
+        let mut bsp_count: usize = 0;
+        let result = winbio_helpers::trace_call(
+            "WinBioEnumServiceProviders",
+            &format!("factor={}", winbio_helpers::biometric_type().name()),
+            WinBioEnumServiceProviders(
+                winbio_helpers::biometric_type().factor(),
+                &mut bsp_array,
+                &mut bsp_count,
+            ),
+        );
+        if let Err(e) = result {
+            print_fail(&format!(
+                "WinBioEnumServiceProviders failed: {} (0x{:08X})",
+                crate::error::hresult_message(e.code()),
+                e.code().0 as u32
+            ));
+            return;
+        }
+
+        if bsp_count == 0 {
+            print_warn("No service providers registered");
+        }
+        for bsp in winbio_helpers::winbio_slice(bsp_array, bsp_count) {
+            let id = winbio_helpers::format_guid(&bsp.BspId);
+            let description = winbio_helpers::wchar_to_string(&bsp.Description);
+            let vendor = winbio_helpers::wchar_to_string(&bsp.Vendor);
+            let version = format!("{}.{}", bsp.Version.MajorVersion, bsp.Version.MinorVersion);
+
+            print_blank();
+            print_info("  Provider", &description);
+            print_info("    BSP ID", &id);
+            print_info("    Vendor", &vendor);
+            print_info("    Version", &version);
+
+            record(
+                "service_providers",
+                json!({
+                    "bsp_id": id,
+                    "description": description,
+                    "vendor": vendor,
+                    "version": version,
+                }),
+            );
+        }
+
+        if !bsp_array.is_null() {
+            winbio_helpers::winbio_free(bsp_array as *const _);
+        }
+    }
+}
+
+/// Provider DLLs registered under `WbioSrvc\Service Providers`: one subkey per
+/// biometric factor, whose `BiometricProvider` value names the DLL.
+/// Returns how many are missing from disk.
+fn print_registered_providers() -> usize {
+    print_header("Registered Provider DLLs (WbioSrvc\\Service Providers)");
+
+    let names = enum_registry_subkeys(HKEY_LOCAL_MACHINE, SERVICE_PROVIDERS_KEY);
+    if names.is_empty() {
+        print_warn("No service provider registrations found");
+        return 0;
+    }
+
+    let mut missing = 0;
+    for name in &names {
+        let subkey = format!("{}\\{}", SERVICE_PROVIDERS_KEY, name);
+        let subkey_wide: Vec<u16> = subkey.encode_utf16().chain(std::iter::once(0)).collect();
+        let mut hkey = HKEY::default();
+        let provider = unsafe {
+            let status = RegOpenKeyExW(
+                HKEY_LOCAL_MACHINE,
+                PCWSTR(subkey_wide.as_ptr()),
+                None,
+                KEY_READ,
+                &mut hkey,
+            );
+            if status.is_err() {
+                None
+            } else {
+                let provider = read_registry_string(hkey, "BiometricProvider");
+                let _ = RegCloseKey(hkey);
+                provider
+            }
+        };
+
+        print_blank();
+        print_info("  Provider key", name);
+        let Some(provider) = provider else {
+            print_info("    BiometricProvider", "(not set)");
+            continue;
+        };
+        print_info("    BiometricProvider", &provider);
+        let path = resolve_system_binary(&provider);
+        match &path {
+            Some(path) => print_pass(&format!("    Present: {}", path.display())),
+            None => {
+                missing += 1;
+                print_fail("    DLL not found on disk");
+            }
+        }
+
+        record(
+            "registered_providers",
+            json!({
+                "name": name,
+                "binary": provider,
+                "path": path.map(|p| p.display().to_string()),
+            }),
+        );
+    }
+    missing
+}
+
+/// Sensor, engine, and storage adapters named by any device's WinBio
+/// configuration. Returns how many are missing from disk.
+fn print_configured_adapters() -> usize {
+    print_header("Configured Adapters (device WinBio configurations)");
+
+    let adapters = configured_adapters();
+    if adapters.is_empty() {
+        print_warn("No device WinBio configurations name an adapter");
+        return 0;
+    }
+
+    let mut missing = 0;
+    for adapter in &adapters {
+        print_blank();
+        print_info(&format!("  {} adapter", adapter.kind), &adapter.binary);
+        let path = resolve_system_binary(&adapter.binary);
+        match &path {
+            Some(path) => print_pass(&format!("    Present: {}", path.display())),
+            None => {
+                missing += 1;
+                print_fail("    DLL not found on disk");
+            }
+        }
+        for device in &adapter.devices {
+            print_info("    Used by", device);
+        }
+
+        record(
+            "adapters",
+            json!({
+                "kind": adapter.kind,
+                "binary": adapter.binary,
+                "path": path.map(|p| p.display().to_string()),
+                "devices": adapter.devices,
+            }),
+        );
+    }
+    missing
+}
+
+/// Enumerate the installed WinBio service providers and every adapter DLL the
+/// framework would load, independent of a particular device, and flag any
+/// DLL missing from disk.
+pub fn run_enum_adapters() -> Result<()> {
+    print_service_providers();
+    let missing = print_registered_providers() + print_configured_adapters();
+
+    print_blank();
+    if missing == 0 {
+        print_pass("All registered provider and adapter DLLs are present");
+    } else {
+        print_step(
+            "Reinstall the sensor driver (reinstall-driver) to restore missing adapter DLLs",
+        );
+    }
+    Ok(())
+}
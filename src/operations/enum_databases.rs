@@ -1,29 +1,14 @@
 use anyhow::Result;
+use serde_json::{json, Value};
 use std::collections::HashMap;
 use windows::core::{PCWSTR, PWSTR};
 use windows::Win32::Devices::BiometricFramework::*;
 use windows::Win32::System::Registry::*;
+use windows::Win32::System::Services::SERVICE_RUNNING;
 
 use crate::output::*;
 use crate::winbio_helpers;
 
-fn format_guid(guid: &windows::core::GUID) -> String {
-    format!(
-        "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
-        guid.data1,
-        guid.data2,
-        guid.data3,
-        guid.data4[0],
-        guid.data4[1],
-        guid.data4[2],
-        guid.data4[3],
-        guid.data4[4],
-        guid.data4[5],
-        guid.data4[6],
-        guid.data4[7]
-    )
-}
-
 fn attributes_string(attrs: u32) -> String {
     let mut parts = Vec::new();
     if attrs & 0x01 != 0 {
@@ -53,70 +38,6 @@ fn format_file_size(bytes: u64) -> String {
     }
 }
 
-fn format_system_time(time: std::time::SystemTime) -> String {
-    let since_unix = match time.duration_since(std::time::UNIX_EPOCH) {
-        Ok(d) => d,
-        Err(_) => return format!("{:?}", time),
-    };
-
-    let secs = since_unix.as_secs() as i64;
-
-    let mut days = secs / 86400;
-    let day_secs = secs % 86400;
-    let hour = day_secs / 3600;
-    let minute = (day_secs % 3600) / 60;
-    let second = day_secs % 60;
-
-    let mut year = 1970i32;
-    loop {
-        let days_in_year = if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) {
-            366
-        } else {
-            365
-        };
-        if days < days_in_year {
-            break;
-        }
-        days -= days_in_year;
-        year += 1;
-    }
-
-    let is_leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
-    let month_days = [
-        31,
-        if is_leap { 29 } else { 28 },
-        31,
-        30,
-        31,
-        30,
-        31,
-        31,
-        30,
-        31,
-        30,
-        31,
-    ];
-
-    let mut month = 0usize;
-    for (i, &md) in month_days.iter().enumerate() {
-        if days < md {
-            month = i;
-            break;
-        }
-        days -= md;
-    }
-
-    format!(
-        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
-        year,
-        month + 1,
-        days + 1,
-        hour,
-        minute,
-        second
-    )
-}
-
 fn print_file_metadata(file_path: &str) {
     match std::fs::metadata(file_path) {
         Ok(meta) => {
@@ -134,7 +55,7 @@ fn print_file_metadata(file_path: &str) {
     }
 }
 
-fn read_registry_string(key: HKEY, value_name: &str) -> Option<String> {
+pub fn read_registry_string(key: HKEY, value_name: &str) -> Option<String> {
     unsafe {
         let value_name_wide: Vec<u16> = value_name
             .encode_utf16()
@@ -192,6 +113,46 @@ fn read_registry_string(key: HKEY, value_name: &str) -> Option<String> {
     }
 }
 
+fn read_registry_binary(key: HKEY, value_name: &str) -> Option<Vec<u8>> {
+    unsafe {
+        let value_name_wide: Vec<u16> = value_name
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect();
+        let mut data_type = REG_VALUE_TYPE::default();
+        let mut data_size: u32 = 0;
+
+        let status = RegQueryValueExW(
+            key,
+            PCWSTR(value_name_wide.as_ptr()),
+            None,
+            Some(&mut data_type),
+            None,
+            Some(&mut data_size),
+        );
+
+        if status.is_err() || data_size == 0 || data_type != REG_BINARY {
+            return None;
+        }
+
+        let mut buf = vec![0u8; data_size as usize];
+        let status = RegQueryValueExW(
+            key,
+            PCWSTR(value_name_wide.as_ptr()),
+            None,
+            Some(&mut data_type),
+            Some(buf.as_mut_ptr()),
+            Some(&mut data_size),
+        );
+
+        if status.is_err() {
+            return None;
+        }
+        buf.truncate(data_size as usize);
+        Some(buf)
+    }
+}
+
 fn print_registry_info(database_id: &str) {
     unsafe {
         let subkey = format!(
@@ -262,10 +223,20 @@ struct SensorDatabaseLink {
     device_instance_id: String,
     sensor_subtype: Option<u32>,
     config_index: u32,
+    sensor_adapter: String,
     engine_adapter: String,
     storage_adapter: String,
     sensor_mode: String,
     virtual_secure_mode: bool,
+    /// Distinct "engine / storage" pairs across this device's configurations,
+    /// filled only when they disagree.
+    conflicting_adapters: Vec<String>,
+    /// Distinct DatabaseIds across this device's configurations, filled only
+    /// when there is more than one.
+    conflicting_databases: Vec<String>,
+    /// The active unit reports `WINBIO_CAPABILITY_DATABASE`: templates live on
+    /// the sensor, not in the .DAT file.
+    on_chip_storage: bool,
 }
 
 /// Read WinBio configuration values for a given device instance and config index.
@@ -300,6 +271,7 @@ fn read_device_winbio_config(
         }
 
         let result = read_registry_string(hkey, "DatabaseId").map(|db_id| {
+            let sensor = read_registry_string(hkey, "SensorAdapterBinary").unwrap_or_default();
             let engine = read_registry_string(hkey, "EngineAdapterBinary").unwrap_or_default();
             let storage = read_registry_string(hkey, "StorageAdapterBinary").unwrap_or_default();
             let sensor_mode_val = read_registry_string(hkey, "SensorMode").unwrap_or_default();
@@ -313,12 +285,7 @@ fn read_device_winbio_config(
                 other => format!("Unknown ({})", other),
             };
 
-            let db_id_upper = db_id.to_uppercase();
-            let db_id_key = if db_id_upper.starts_with('{') {
-                db_id_upper
-            } else {
-                format!("{{{}}}", db_id_upper)
-            };
+            let db_id_key = winbio_helpers::normalize_guid(&db_id);
 
             let link = SensorDatabaseLink {
                 unit_id,
@@ -328,10 +295,14 @@ fn read_device_winbio_config(
                 device_instance_id: device_instance_id.to_string(),
                 sensor_subtype,
                 config_index: config_idx,
+                sensor_adapter: sensor,
                 engine_adapter: engine,
                 storage_adapter: storage,
                 sensor_mode: sensor_mode_display,
                 virtual_secure_mode: vsm,
+                conflicting_adapters: Vec::new(),
+                conflicting_databases: Vec::new(),
+                on_chip_storage: false,
             };
 
             (db_id_key, link)
@@ -343,7 +314,7 @@ fn read_device_winbio_config(
 }
 
 /// Enumerate registry subkeys under a given parent key.
-fn enum_registry_subkeys(parent: HKEY, subpath: &str) -> Vec<String> {
+pub fn enum_registry_subkeys(parent: HKEY, subpath: &str) -> Vec<String> {
     let mut result = Vec::new();
     unsafe {
         let subpath_wide: Vec<u16> = subpath.encode_utf16().chain(std::iter::once(0)).collect();
@@ -386,6 +357,37 @@ fn enum_registry_subkeys(parent: HKEY, subpath: &str) -> Vec<String> {
     result
 }
 
+/// (configuration name, `{DATABASE-ID}`) for every WinBio configuration of a
+/// device that names a database.
+pub fn device_database_ids(device_instance_id: &str) -> Vec<(String, String)> {
+    let configs = enum_registry_subkeys(
+        HKEY_LOCAL_MACHINE,
+        &format!(
+            "SYSTEM\\CurrentControlSet\\Enum\\{}\\Device Parameters\\WinBio\\Configurations",
+            device_instance_id
+        ),
+    );
+    configs
+        .into_iter()
+        .filter_map(|name| {
+            let index = name.parse().ok()?;
+            read_device_winbio_config(device_instance_id, index, None, "", "", "", None)
+                .map(|(db_id, _)| (name, db_id))
+        })
+        .collect()
+}
+
+/// Upper-cased `{GUID}` names of the databases registered under WbioSrvc\Databases.
+pub fn registered_database_ids() -> std::collections::HashSet<String> {
+    enum_registry_subkeys(
+        HKEY_LOCAL_MACHINE,
+        "SYSTEM\\CurrentControlSet\\Services\\WbioSrvc\\Databases",
+    )
+    .into_iter()
+    .map(|id| winbio_helpers::normalize_guid(&id))
+    .collect()
+}
+
 /// Read the "FriendlyName" from a device's registry key for display when the device
 /// is not currently active as a biometric unit.
 fn read_device_friendly_name(device_instance_id: &str) -> String {
@@ -420,6 +422,7 @@ fn read_device_friendly_name(device_instance_id: &str) -> String {
 /// Pass 1: active sensors from WinBioEnumBiometricUnits.
 /// Pass 2: registry scan for all USB devices with WinBio configurations (catches disconnected sensors).
 fn build_sensor_database_map() -> HashMap<String, Vec<SensorDatabaseLink>> {
+    let _timer = crate::output::time_step("Sensor/database registry scan");
     let mut map: HashMap<String, Vec<SensorDatabaseLink>> = HashMap::new();
     // Track device instance IDs we've already processed from active sensors
     let mut seen_devices: std::collections::HashSet<String> = std::collections::HashSet::new();
@@ -429,14 +432,18 @@ fn build_sensor_database_map() -> HashMap<String, Vec<SensorDatabaseLink>> {
         let mut unit_array: *mut WINBIO_UNIT_SCHEMA = std::ptr::null_mut();
         let mut unit_count: usize = 0;
 
-        let result = WinBioEnumBiometricUnits(
-            winbio_helpers::WINBIO_TYPE_FINGERPRINT,
-            &mut unit_array,
-            &mut unit_count,
+        let result = winbio_helpers::trace_call(
+            "WinBioEnumBiometricUnits",
+            &format!("factor={}", winbio_helpers::biometric_type().name()),
+            WinBioEnumBiometricUnits(
+                winbio_helpers::biometric_type().factor(),
+                &mut unit_array,
+                &mut unit_count,
+            ),
         );
 
         if result.is_ok() && unit_count > 0 {
-            let units = std::slice::from_raw_parts(unit_array, unit_count);
+            let units = winbio_helpers::winbio_slice(unit_array, unit_count);
             for unit in units {
                 let device_instance_id = winbio_helpers::wchar_to_string(&unit.DeviceInstanceId);
                 let description = winbio_helpers::wchar_to_string(&unit.Description);
@@ -446,7 +453,7 @@ fn build_sensor_database_map() -> HashMap<String, Vec<SensorDatabaseLink>> {
                 seen_devices.insert(device_instance_id.to_uppercase());
 
                 for config_idx in 0..3u32 {
-                    if let Some((key, link)) = read_device_winbio_config(
+                    if let Some((key, mut link)) = read_device_winbio_config(
                         &device_instance_id,
                         config_idx,
                         Some(unit.UnitId),
@@ -455,6 +462,8 @@ fn build_sensor_database_map() -> HashMap<String, Vec<SensorDatabaseLink>> {
                         &model,
                         Some(unit.SensorSubType),
                     ) {
+                        link.on_chip_storage =
+                            unit.Capabilities & winbio_helpers::WINBIO_CAPABILITY_DATABASE != 0;
                         map.entry(key).or_default().push(link);
                     }
                 }
@@ -531,9 +540,114 @@ fn build_sensor_database_map() -> HashMap<String, Vec<SensorDatabaseLink>> {
         }
     }
 
+    flag_conflicting_adapters(&mut map);
+    flag_conflicting_databases(&mut map);
     map
 }
 
+/// Mark every link whose device has configurations naming different adapter
+/// binaries, typically left behind by a partial driver update.
+fn flag_conflicting_adapters(map: &mut HashMap<String, Vec<SensorDatabaseLink>>) {
+    let mut per_device: HashMap<String, std::collections::BTreeSet<String>> = HashMap::new();
+    for link in map.values().flatten() {
+        per_device
+            .entry(link.device_instance_id.to_uppercase())
+            .or_default()
+            .insert(format!(
+                "{} / {}",
+                link.engine_adapter, link.storage_adapter
+            ));
+    }
+    for link in map.values_mut().flatten() {
+        if let Some(pairs) = per_device.get(&link.device_instance_id.to_uppercase()) {
+            if pairs.len() > 1 {
+                link.conflicting_adapters = pairs.iter().cloned().collect();
+            }
+        }
+    }
+}
+
+/// Mark every link whose device's configurations name more than one
+/// DatabaseId. Only one configuration is in use, so the other databases are
+/// orphaned; a known leftover of driver upgrades.
+fn flag_conflicting_databases(map: &mut HashMap<String, Vec<SensorDatabaseLink>>) {
+    let mut per_device: HashMap<String, std::collections::BTreeSet<String>> = HashMap::new();
+    for (db_id, links) in map.iter() {
+        for link in links {
+            per_device
+                .entry(link.device_instance_id.to_uppercase())
+                .or_default()
+                .insert(db_id.clone());
+        }
+    }
+    for links in map.values_mut() {
+        for link in links {
+            if let Some(ids) = per_device.get(&link.device_instance_id.to_uppercase()) {
+                if ids.len() > 1 {
+                    link.conflicting_databases = ids.iter().cloned().collect();
+                }
+            }
+        }
+    }
+}
+
+/// Whether an adapter binary named in a configuration exists on disk.
+fn adapter_present(binary: &str) -> bool {
+    crate::diagnostics::version_info::resolve_system_binary(binary).is_some()
+}
+
+/// Distinct engine and storage adapter binaries named by any sensor's WinBio
+/// configuration (active or not), sorted.
+pub fn adapter_binaries() -> Vec<String> {
+    let mut binaries = std::collections::BTreeSet::new();
+    for link in build_sensor_database_map().values().flatten() {
+        for binary in [&link.engine_adapter, &link.storage_adapter] {
+            if !binary.is_empty() {
+                binaries.insert(binary.clone());
+            }
+        }
+    }
+    binaries.into_iter().collect()
+}
+
+/// An adapter binary named by one or more sensors' WinBio configurations.
+pub struct ConfiguredAdapter {
+    /// "Sensor", "Engine", or "Storage".
+    pub kind: &'static str,
+    pub binary: String,
+    /// Device instance IDs whose configurations name this binary.
+    pub devices: Vec<String>,
+}
+
+/// Every distinct (kind, binary) pair across all sensors' WinBio
+/// configurations (active or not), sorted by kind and binary.
+pub fn configured_adapters() -> Vec<ConfiguredAdapter> {
+    let mut adapters: std::collections::BTreeMap<_, std::collections::BTreeSet<String>> =
+        std::collections::BTreeMap::new();
+    for link in build_sensor_database_map().values().flatten() {
+        for (kind, binary) in [
+            ("Sensor", &link.sensor_adapter),
+            ("Engine", &link.engine_adapter),
+            ("Storage", &link.storage_adapter),
+        ] {
+            if !binary.is_empty() {
+                adapters
+                    .entry((kind, binary.clone()))
+                    .or_default()
+                    .insert(link.device_instance_id.clone());
+            }
+        }
+    }
+    adapters
+        .into_iter()
+        .map(|((kind, binary), devices)| ConfiguredAdapter {
+            kind,
+            binary,
+            devices: devices.into_iter().collect(),
+        })
+        .collect()
+}
+
 fn print_sensor_info(links: &[SensorDatabaseLink]) {
     for link in links {
         let vsm_tag = if link.virtual_secure_mode {
@@ -577,15 +691,413 @@ fn print_sensor_info(links: &[SensorDatabaseLink]) {
                 link.config_index, link.engine_adapter, link.storage_adapter
             ),
         );
+        for (kind, binary) in [
+            ("Engine", &link.engine_adapter),
+            ("Storage", &link.storage_adapter),
+        ] {
+            if !binary.is_empty() && !adapter_present(binary) {
+                print_fail(&format!(
+                    "    {} adapter {} not found under System32 — reinstall the sensor driver",
+                    kind, binary
+                ));
+            }
+        }
+        if !link.conflicting_adapters.is_empty() {
+            print_warn(&format!(
+                "    This device's configurations reference different adapters: {}",
+                link.conflicting_adapters.join("; ")
+            ));
+        }
+        if !link.conflicting_databases.is_empty() {
+            print_warn(&format!(
+                "    This device's configurations reference different databases: {}",
+                link.conflicting_databases.join(", ")
+            ));
+        }
+    }
+}
+
+fn sensor_link_json(link: &SensorDatabaseLink) -> Value {
+    json!({
+        "unit_id": link.unit_id,
+        "active": link.unit_id.is_some(),
+        "description": link.description,
+        "manufacturer": link.manufacturer,
+        "model": link.model,
+        "device_instance_id": link.device_instance_id,
+        "sensor_subtype": link.sensor_subtype.map(winbio_helpers::sensor_subtype_name),
+        "config_index": link.config_index,
+        "engine_adapter": link.engine_adapter,
+        "storage_adapter": link.storage_adapter,
+        "sensor_mode": link.sensor_mode,
+        "virtual_secure_mode": link.virtual_secure_mode,
+        "engine_adapter_found": link.engine_adapter.is_empty() || adapter_present(&link.engine_adapter),
+        "storage_adapter_found": link.storage_adapter.is_empty() || adapter_present(&link.storage_adapter),
+        "conflicting_adapters": link.conflicting_adapters,
+        "conflicting_databases": link.conflicting_databases,
+        "on_chip_storage": link.on_chip_storage,
+    })
+}
+
+/// Read (string SID, binary SID) pairs for every user profile on this machine.
+/// WinBio cannot list the identities stored in a database, so these are the
+/// candidates the enrollment census asks about.
+pub fn read_profile_sids() -> Vec<(String, Vec<u8>)> {
+    let profile_list = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\ProfileList";
+    let mut sids = Vec::new();
+
+    // S-1-5-21-*: local and domain accounts, S-1-12-1-*: Microsoft Entra ID accounts
+    for sid_string in enum_registry_subkeys(HKEY_LOCAL_MACHINE, profile_list) {
+        if !sid_string.starts_with("S-1-5-21-") && !sid_string.starts_with("S-1-12-1-") {
+            continue;
+        }
+        unsafe {
+            let subkey = format!("{}\\{}", profile_list, sid_string);
+            let subkey_wide: Vec<u16> = subkey.encode_utf16().chain(std::iter::once(0)).collect();
+            let mut hkey = HKEY::default();
+            let status = RegOpenKeyExW(
+                HKEY_LOCAL_MACHINE,
+                PCWSTR(subkey_wide.as_ptr()),
+                None,
+                KEY_READ,
+                &mut hkey,
+            );
+            if status.is_err() {
+                continue;
+            }
+            if let Some(sid) = read_registry_binary(hkey, "Sid") {
+                sids.push((sid_string, sid));
+            }
+            let _ = RegCloseKey(hkey);
+        }
+    }
+
+    sids
+}
+
+/// Result of asking one unit about every profile SID.
+pub struct EnrollmentCensus {
+    /// (SID string, enrolled subfactors) for identities with at least one enrollment.
+    pub identities: Vec<(String, Vec<u8>)>,
+    /// SIDs the unit refused to report on (access denied, unsupported by the
+    /// storage adapter, ...). Unknown identities are not counted.
+    pub refused: usize,
+}
+
+/// Ask `unit_id` which subfactors each profile SID has enrolled.
+pub fn enrollment_census(
+    session: u32,
+    unit_id: u32,
+    sids: &[(String, Vec<u8>)],
+) -> EnrollmentCensus {
+    let mut census = EnrollmentCensus {
+        identities: Vec::new(),
+        refused: 0,
+    };
+
+    for (sid_string, sid) in sids {
+        let Some(identity) = winbio_helpers::sid_identity(sid) else {
+            continue;
+        };
+
+        unsafe {
+            let mut subfactor_array: *mut u8 = std::ptr::null_mut();
+            let mut subfactor_count: usize = 0;
+
+            let result = winbio_helpers::trace_call(
+                "WinBioEnumEnrollments",
+                &format!("session={}, unit={}, sid={}", session, unit_id, sid_string),
+                WinBioEnumEnrollments(
+                    session,
+                    unit_id,
+                    &identity,
+                    &mut subfactor_array,
+                    Some(&mut subfactor_count),
+                ),
+            );
+
+            match result {
+                Ok(()) if subfactor_count > 0 => {
+                    let subfactors = winbio_helpers::winbio_slice(subfactor_array, subfactor_count);
+                    census
+                        .identities
+                        .push((sid_string.clone(), subfactors.to_vec()));
+                }
+                Ok(()) => {}
+                // WINBIO_E_UNKNOWN_ID, WINBIO_E_DATABASE_NO_SUCH_RECORD: nothing enrolled
+                Err(e) if matches!(crate::error::error_code(&e), 0x8009_8003 | 0x8009_801B) => {}
+                Err(_) => census.refused += 1,
+            }
+            winbio_helpers::winbio_free(subfactor_array as *const _);
+        }
+    }
+
+    census
+}
+
+/// Enrolled records of one database, as far as they can be determined.
+enum Enrollments {
+    ServiceStopped,
+    NoActiveSensor,
+    Census {
+        unit_id: u32,
+        identities: Vec<(String, Vec<u8>)>,
+    },
+}
+
+/// Run the enrollment census for one database.
+fn database_enrollments(
+    session: Option<u32>,
+    links: &[SensorDatabaseLink],
+    sids: &[(String, Vec<u8>)],
+) -> Enrollments {
+    let Some(session) = session else {
+        return Enrollments::ServiceStopped;
+    };
+    // Every active unit on a database sees the same records; ask the first one.
+    let Some(unit_id) = links.iter().find_map(|link| link.unit_id) else {
+        return Enrollments::NoActiveSensor;
+    };
+    Enrollments::Census {
+        unit_id,
+        identities: enrollment_census(session, unit_id, sids).identities,
+    }
+}
+
+fn print_enrollments(enrollments: &Enrollments) {
+    let identities = match enrollments {
+        Enrollments::ServiceStopped => {
+            print_info(
+                "  Enrolled Records",
+                "(count unavailable — service stopped)",
+            );
+            return;
+        }
+        Enrollments::NoActiveSensor => {
+            print_info(
+                "  Enrolled Records",
+                "(count unavailable — no active sensor)",
+            );
+            return;
+        }
+        Enrollments::Census { identities, .. } => identities,
+    };
+
+    let total: usize = identities
+        .iter()
+        .map(|(_, subfactors)| subfactors.len())
+        .sum();
+    print_info(
+        "  Enrolled Records",
+        &format!("{} across {} identit(ies)", total, identities.len()),
+    );
+    for (sid_string, subfactors) in identities {
+        let fingers: Vec<String> = subfactors
+            .iter()
+            .map(|&sf| winbio_helpers::subfactor_name(sf))
+            .collect();
+        print_info(&format!("    {}", sid_string), &fingers.join(", "));
+    }
+}
+
+/// JSON form of the census; `null` when the count is unavailable.
+fn enrollments_json(enrollments: &Enrollments) -> Value {
+    let Enrollments::Census {
+        unit_id,
+        identities,
+    } = enrollments
+    else {
+        return Value::Null;
+    };
+    let total: usize = identities
+        .iter()
+        .map(|(_, subfactors)| subfactors.len())
+        .sum();
+    let identities: Vec<Value> = identities
+        .iter()
+        .map(|(sid, subfactors)| json!({ "sid": sid, "subfactors": subfactors }))
+        .collect();
+    json!({ "unit_id": unit_id, "total": total, "identities": identities })
+}
+
+/// Everything `enum-databases` reports about one registered database.
+struct DatabaseEntry {
+    index: usize,
+    database_id: String,
+    data_format: String,
+    attributes: u32,
+    file_path: String,
+    connection_string: String,
+    file_size: Option<u64>,
+    sensors: Vec<SensorDatabaseLink>,
+    enrollments: Enrollments,
+}
+
+/// How full a database is, as far as Windows can tell. WinBio exposes no
+/// capacity, so for a file-backed database the best available measure is its
+/// record count against its file size.
+enum Usage {
+    /// Templates live on the sensor, which manages its own capacity.
+    OnChip,
+    /// File-backed with a known record count.
+    Measured { records: usize, file_size: u64 },
+    /// No record count (service stopped or no active sensor) or no file.
+    Unknown,
+}
+
+fn database_usage(db: &DatabaseEntry) -> Usage {
+    if db.sensors.iter().any(|link| link.on_chip_storage) {
+        return Usage::OnChip;
+    }
+    match (&db.enrollments, db.file_size) {
+        (Enrollments::Census { identities, .. }, Some(file_size)) => Usage::Measured {
+            records: identities
+                .iter()
+                .map(|(_, subfactors)| subfactors.len())
+                .sum(),
+            file_size,
+        },
+        _ => Usage::Unknown,
+    }
+}
+
+fn print_usage(usage: &Usage) {
+    match *usage {
+        Usage::OnChip => print_info(
+            "  Capacity",
+            "Managed by the sensor (on-chip templates) — not visible to Windows",
+        ),
+        Usage::Measured {
+            records: 0,
+            file_size,
+        } => print_info(
+            "  Capacity",
+            &format!(
+                "No records; {} of file overhead",
+                format_file_size(file_size)
+            ),
+        ),
+        Usage::Measured { records, file_size } => print_info(
+            "  Capacity",
+            &format!(
+                "{} record(s) in {}, about {} per record",
+                records,
+                format_file_size(file_size),
+                format_file_size(file_size / records as u64)
+            ),
+        ),
+        Usage::Unknown => print_info(
+            "  Capacity",
+            "(unknown — needs a running service and an active sensor to count records)",
+        ),
+    }
+}
+
+fn usage_json(usage: &Usage) -> Value {
+    match *usage {
+        Usage::OnChip => json!({ "on_chip": true }),
+        Usage::Measured { records, file_size } => json!({
+            "on_chip": false,
+            "records": records,
+            "file_size": file_size,
+            "bytes_per_record": (records > 0).then(|| file_size / records as u64),
+        }),
+        Usage::Unknown => Value::Null,
+    }
+}
+
+fn database_json(db: &DatabaseEntry) -> Value {
+    json!({
+        "index": db.index,
+        "database_id": db.database_id,
+        "data_format": db.data_format,
+        "attributes": db.attributes,
+        "file_path": db.file_path,
+        "connection_string": db.connection_string,
+        "file_size": db.file_size,
+        "sensors": db.sensors.iter().map(sensor_link_json).collect::<Vec<_>>(),
+        "enrollments": enrollments_json(&db.enrollments),
+        "usage": usage_json(&database_usage(db)),
+    })
+}
+
+fn print_database(db: &DatabaseEntry) {
+    print_blank();
+    print_step(&format!("Database {}", db.index));
+    print_info("Database ID", &db.database_id);
+    print_info("Data Format", &db.data_format);
+    print_info("Attributes", &attributes_string(db.attributes));
+    print_info(
+        "File Path",
+        if db.file_path.is_empty() {
+            "(empty)"
+        } else {
+            &db.file_path
+        },
+    );
+    print_info(
+        "Connection String",
+        if db.connection_string.is_empty() {
+            "(empty)"
+        } else {
+            &db.connection_string
+        },
+    );
+
+    // File metadata
+    if !db.file_path.is_empty() {
+        print_file_metadata(&db.file_path);
+    }
+
+    // Registry cross-reference
+    print_registry_info(&db.database_id);
+
+    // Sensor cross-reference
+    if db.sensors.is_empty() {
+        print_info("  Sensor", "(no matching sensor found)");
+    } else {
+        print_sensor_info(&db.sensors);
+    }
+
+    print_enrollments(&db.enrollments);
+    print_usage(&database_usage(db));
+}
+
+/// Quote a CSV field if it contains a separator, quote, or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn print_databases_csv(databases: &[DatabaseEntry]) {
+    print_raw("index,database_id,data_format,attributes,file_path,file_size,sensor_count");
+    for db in databases {
+        print_raw(&format!(
+            "{},{},{},0x{:08X},{},{},{}",
+            db.index,
+            db.database_id,
+            db.data_format,
+            db.attributes,
+            csv_field(&db.file_path),
+            db.file_size
+                .map(|size| size.to_string())
+                .unwrap_or_default(),
+            db.sensors.len()
+        ));
     }
 }
 
 /// Scan the WINBIODATABASE directory for .DAT files not in the registered set.
-fn find_orphaned_files(registered_paths: &std::collections::HashSet<String>) {
+fn find_orphaned_files(
+    registered_paths: &std::collections::HashSet<String>,
+) -> Vec<std::path::PathBuf> {
     let db_dir = std::path::Path::new(r"C:\WINDOWS\SYSTEM32\WINBIODATABASE");
     let entries = match std::fs::read_dir(db_dir) {
         Ok(e) => e,
-        Err(_) => return,
+        Err(_) => return Vec::new(),
     };
 
     let mut orphans = Vec::new();
@@ -598,18 +1110,21 @@ fn find_orphaned_files(registered_paths: &std::collections::HashSet<String>) {
             }
         }
     }
+    orphans
+}
 
+fn print_orphaned_files(orphans: &[std::path::PathBuf]) {
     if orphans.is_empty() {
         return;
     }
 
-    println!();
+    print_blank();
     print_warn(&format!(
         "{} orphaned .DAT file(s) (on disk but not registered)",
         orphans.len()
     ));
-    for path in &orphans {
-        println!();
+    for path in orphans {
+        print_blank();
         print_step(&format!("Orphan: {}", path.display()));
         if let Ok(meta) = std::fs::metadata(path) {
             print_info("  File Size", &format_file_size(meta.len()));
@@ -624,79 +1139,92 @@ fn find_orphaned_files(registered_paths: &std::collections::HashSet<String>) {
             "  Status",
             "No registry entry — delete this file manually or re-register the database",
         );
+        record("orphaned_files", json!(path.to_string_lossy()));
     }
 }
 
-pub fn run_enum_databases() -> Result<()> {
-    print_header("Biometric Storage Databases");
+/// Output layout for `enum-databases`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum DatabaseFormat {
+    /// Human-readable report
+    Text,
+    /// One JSON document with the nested sensor and enrollment info
+    Json,
+    /// One CSV row per database
+    Csv,
+}
+
+/// `instance_id` keeps only databases used by a matching device (`--instance-id`).
+pub fn run_enum_databases(format: DatabaseFormat, instance_id: Option<&str>) -> Result<()> {
+    if format != DatabaseFormat::Text && json_mode() {
+        anyhow::bail!("--format {:?} cannot be combined with --json", format);
+    }
 
     // Build sensor-to-database map from registry
-    let sensor_map = build_sensor_database_map();
+    let mut sensor_map = build_sensor_database_map();
 
     // Track registered file paths to detect orphans
     let mut registered_paths = std::collections::HashSet::new();
 
+    // The enrollment census needs a session, which needs the service running
+    let service_running = unsafe { super::service::query_service_state() }
+        .map(|state| state == SERVICE_RUNNING.0)
+        .unwrap_or(false);
+    let census_session = if service_running {
+        super::SessionGuard::new(
+            winbio_helpers::WINBIO_FLAG_DEFAULT,
+            winbio_helpers::SensorPool::System,
+            false,
+        )
+        .ok()
+    } else {
+        None
+    };
+    let profile_sids = read_profile_sids();
+
+    let mut databases = Vec::new();
     unsafe {
         let mut schema_array: *mut WINBIO_STORAGE_SCHEMA = std::ptr::null_mut();
         let mut schema_count: usize = 0;
 
-        WinBioEnumDatabases(
-            winbio_helpers::WINBIO_TYPE_FINGERPRINT,
-            &mut schema_array,
-            &mut schema_count,
+        winbio_helpers::trace_call(
+            "WinBioEnumDatabases",
+            &format!("factor={}", winbio_helpers::biometric_type().name()),
+            WinBioEnumDatabases(
+                winbio_helpers::biometric_type().factor(),
+                &mut schema_array,
+                &mut schema_count,
+            ),
         )
         .map_err(|e| crate::error::wrap_winbio_error("WinBioEnumDatabases", &e))?;
 
-        if schema_count == 0 {
-            print_warn("No biometric databases found");
-        } else {
-            print_pass(&format!("{} database(s) found", schema_count));
-            let schemas = std::slice::from_raw_parts(schema_array, schema_count);
+        if schema_count > 0 {
+            let schemas = winbio_helpers::winbio_slice(schema_array, schema_count);
             for (i, schema) in schemas.iter().enumerate() {
-                println!();
-                print_step(&format!("Database {}", i + 1));
-                let db_id = format_guid(&schema.DatabaseId);
-                print_info("Database ID", &db_id);
-                print_info("Data Format", &format_guid(&schema.DataFormat));
-                print_info("Attributes", &attributes_string(schema.Attributes));
+                let database_id = winbio_helpers::format_guid(&schema.DatabaseId);
                 let file_path = winbio_helpers::wchar_to_string(&schema.FilePath);
-                let conn_string = winbio_helpers::wchar_to_string(&schema.ConnectionString);
-
                 if !file_path.is_empty() {
                     registered_paths.insert(file_path.to_uppercase());
                 }
 
-                print_info(
-                    "File Path",
-                    if file_path.is_empty() {
-                        "(empty)"
-                    } else {
-                        &file_path
-                    },
+                let sensors = sensor_map.remove(&database_id).unwrap_or_default();
+                let enrollments = database_enrollments(
+                    census_session.as_ref().map(|g| g.session),
+                    &sensors,
+                    &profile_sids,
                 );
-                print_info(
-                    "Connection String",
-                    if conn_string.is_empty() {
-                        "(empty)"
-                    } else {
-                        &conn_string
-                    },
-                );
-
-                // File metadata
-                if !file_path.is_empty() {
-                    print_file_metadata(&file_path);
-                }
-
-                // Registry cross-reference
-                print_registry_info(&db_id);
 
-                // Sensor cross-reference
-                if let Some(links) = sensor_map.get(&db_id) {
-                    print_sensor_info(links);
-                } else {
-                    print_info("  Sensor", "(no matching sensor found)");
-                }
+                databases.push(DatabaseEntry {
+                    index: i + 1,
+                    data_format: winbio_helpers::format_guid(&schema.DataFormat),
+                    attributes: schema.Attributes,
+                    connection_string: winbio_helpers::wchar_to_string(&schema.ConnectionString),
+                    file_size: std::fs::metadata(&file_path).ok().map(|m| m.len()),
+                    database_id,
+                    file_path,
+                    sensors,
+                    enrollments,
+                });
             }
         }
 
@@ -705,8 +1233,53 @@ pub fn run_enum_databases() -> Result<()> {
         }
     }
 
-    // Check for orphaned .DAT files
-    find_orphaned_files(&registered_paths);
+    // Orphaned files belong to no device, so a device filter hides them
+    let orphans = if instance_id.is_some() {
+        Vec::new()
+    } else {
+        find_orphaned_files(&registered_paths)
+    };
+
+    let total = databases.len();
+    if instance_id.is_some() {
+        databases.retain(|db| {
+            db.sensors
+                .iter()
+                .any(|link| crate::pnp::instance_id_matches(instance_id, &link.device_instance_id))
+        });
+    }
+
+    match format {
+        DatabaseFormat::Text => {
+            print_header("Biometric Storage Databases");
+            if total == 0 {
+                print_warn("No biometric databases found");
+            } else if let (Some(filter), true) = (instance_id, databases.is_empty()) {
+                print_warn(&format!(
+                    "None of the {} database(s) is used by a device whose instance ID starts with '{}'",
+                    total, filter
+                ));
+            } else {
+                print_pass(&format!("{} database(s) found", databases.len()));
+            }
+            for db in &databases {
+                print_database(db);
+                record("databases", database_json(db));
+            }
+            print_orphaned_files(&orphans);
+        }
+        DatabaseFormat::Json => {
+            let doc = json!({
+                "databases": databases.iter().map(database_json).collect::<Vec<_>>(),
+                "orphaned_files": orphans
+                    .iter()
+                    .map(|path| path.to_string_lossy())
+                    .collect::<Vec<_>>(),
+            });
+            print_raw(&serde_json::to_string_pretty(&doc)?);
+        }
+        DatabaseFormat::Csv => print_databases_csv(&databases),
+    }
 
     Ok(())
 }
@@ -0,0 +1,197 @@
+use anyhow::Result;
+use std::path::Path;
+use windows::Win32::Devices::BiometricFramework::*;
+
+use crate::output::*;
+use crate::winbio_helpers::{self, SensorPool};
+
+use super::SessionGuard;
+
+/// Export data for an enrolled finger.
+///
+/// The WinBio client API has no call that returns a stored template: host-side
+/// storage adapters keep templates encrypted in the `.DAT` database, and sensors
+/// with on-chip storage never release them at all. So this command:
+/// 1. identifies the user to confirm the requested finger is enrolled,
+/// 2. refuses sensors that report `WINBIO_CAPABILITY_DATABASE` (on-chip storage),
+/// 3. otherwise captures a RAW sample of that finger via `WinBioCaptureSample`,
+///    i.e. the input the engine adapter builds the template from.
+pub fn run_export_template(
+    finger: u8,
+    unit: Option<u32>,
+    output: &Path,
+    pool: SensorPool,
+) -> Result<()> {
+    print_header(&format!(
+        "Export Template — Finger {} ({})",
+        finger,
+        winbio_helpers::subfactor_name(finger)
+    ));
+
+    if !(1..=10).contains(&finger) {
+        print_fail("Finger must be 1–10");
+        return Ok(());
+    }
+
+    crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(unit, winbio_helpers::SensorOperation::Identify)?;
+    winbio_helpers::require_capabilities(unit, winbio_helpers::SensorOperation::RawCapture)?;
+
+    if unit.is_some() {
+        winbio_helpers::resolve_unit_id(unit)?;
+    }
+
+    // Step 1: identify, to make sure the finger is enrolled for this user
+    let unit_id = {
+        let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
+        print_step(&format!(
+            "Touch the sensor with finger {} ({}) to identify...",
+            finger,
+            winbio_helpers::subfactor_name(finger)
+        ));
+
+        let mut unit_id = 0u32;
+        let mut identity = WINBIO_IDENTITY::default();
+        let mut subfactor = 0u8;
+        let mut reject_detail = 0u32;
+
+        let result = unsafe {
+            winbio_helpers::trace_call(
+                "WinBioIdentify",
+                &format!("session={}", guard.session),
+                WinBioIdentify(
+                    guard.session,
+                    Some(&mut unit_id),
+                    Some(&mut identity),
+                    Some(&mut subfactor),
+                    Some(&mut reject_detail),
+                ),
+            )
+        };
+
+        if let Err(e) = result {
+            let code = crate::error::error_code(&e);
+            if code == 0x8009_8005 {
+                // WINBIO_E_NO_MATCH
+                crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+                print_fail("No match — finger not enrolled");
+            } else if code == 0x8009_8008 {
+                // WINBIO_E_BAD_CAPTURE
+                print_fail("Bad capture — try again");
+                print_info(
+                    "Reject reason",
+                    &winbio_helpers::reject_reason(reject_detail),
+                );
+            } else {
+                return Err(crate::error::wrap_winbio_error("WinBioIdentify", &e));
+            }
+            return Ok(());
+        }
+
+        if !super::check_responding_unit(unit, unit_id) {
+            return Ok(());
+        }
+
+        if subfactor != finger {
+            print_fail(&format!(
+                "Identified finger {} ({}), but finger {} ({}) was requested",
+                subfactor,
+                winbio_helpers::subfactor_name(subfactor),
+                finger,
+                winbio_helpers::subfactor_name(finger)
+            ));
+            return Ok(());
+        }
+
+        print_pass(&format!("Finger identified on unit {}", unit_id));
+        unit_id
+    };
+
+    // Step 2: figure out where the template lives
+    let units = winbio_helpers::enum_units()?;
+    let Some(schema) = units.iter().find(|u| u.UnitId == unit_id) else {
+        anyhow::bail!("Unit {} disappeared after identify", unit_id);
+    };
+    print_info(
+        "Capabilities",
+        &winbio_helpers::capabilities_string(schema.Capabilities),
+    );
+
+    if schema.Capabilities & winbio_helpers::WINBIO_CAPABILITY_DATABASE != 0 {
+        print_fail("Templates are stored on-chip and cannot be exported");
+        print_step(
+            "The sensor keeps its own template database and never hands templates to the host",
+        );
+        return Ok(());
+    }
+
+    print_warn(
+        "Host-side templates are encrypted by the storage adapter and not exposed by WinBio",
+    );
+    print_step(
+        "Exporting a RAW capture of the enrolled finger instead (the template's input data)",
+    );
+
+    // Step 3: capture a raw sample of the same finger
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_RAW, pool, false)?;
+    print_step("Session opened in RAW mode. Touch the sensor with the same finger again...");
+
+    unsafe {
+        let mut sample: *mut WINBIO_BIR = std::ptr::null_mut();
+        let mut sample_size: usize = 0;
+        let mut capture_unit = 0u32;
+        let mut reject_detail = 0u32;
+
+        let result = winbio_helpers::trace_call(
+            "WinBioCaptureSample",
+            &format!("session={}, flags=RAW", guard.session),
+            WinBioCaptureSample(
+                guard.session,
+                winbio_helpers::WINBIO_PURPOSE_NO_PURPOSE_AVAILABLE,
+                WINBIO_DATA_FLAG_RAW as u8,
+                Some(&mut capture_unit),
+                &mut sample,
+                Some(&mut sample_size),
+                Some(&mut reject_detail),
+            ),
+        );
+
+        if let Err(e) = result {
+            if !sample.is_null() {
+                winbio_helpers::winbio_free(sample as *const _);
+            }
+            if crate::error::error_code(&e) == 0x8009_8008 {
+                // WINBIO_E_BAD_CAPTURE
+                print_fail("Bad capture — try again");
+                print_info(
+                    "Reject reason",
+                    &winbio_helpers::reject_reason(reject_detail),
+                );
+                return Ok(());
+            }
+            return Err(crate::error::wrap_winbio_error("WinBioCaptureSample", &e));
+        }
+
+        if winbio_helpers::is_empty_sample(sample, sample_size) {
+            winbio_helpers::winbio_free(sample as *const _);
+            print_fail("Sensor returned an empty sample");
+            print_warn(&format!(
+                "WinBioCaptureSample succeeded but returned {} byte(s)",
+                sample_size
+            ));
+            return Ok(());
+        }
+
+        if !super::check_responding_unit(Some(unit_id), capture_unit) {
+            winbio_helpers::winbio_free(sample as *const _);
+            return Ok(());
+        }
+
+        let bytes = std::slice::from_raw_parts(sample as *const u8, sample_size);
+        let saved = super::capture::save_sample(output, bytes, &*sample);
+        winbio_helpers::winbio_free(sample as *const _);
+        saved?;
+    }
+
+    Ok(())
+}
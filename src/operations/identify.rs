@@ -1,72 +1,354 @@
 use anyhow::Result;
+use serde_json::json;
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
 use windows::Win32::Devices::BiometricFramework::*;
+use windows::Win32::System::Console::{
+    GetNumberOfConsoleInputEvents, GetStdHandle, ReadConsoleInputW, INPUT_RECORD, KEY_EVENT,
+    STD_INPUT_HANDLE,
+};
 
 use crate::output::*;
-use crate::winbio_helpers;
+use crate::winbio_helpers::{self, SensorPool};
 
 use super::SessionGuard;
 
-pub fn run_identify() -> Result<()> {
+enum IdentifyOutcome {
+    Matched,
+    NoMatch,
+    BadCapture,
+    /// The touch landed on a unit other than `--unit`.
+    WrongUnit,
+}
+
+pub fn run_identify(
+    unit: Option<u32>,
+    timeout: Option<u64>,
+    count: u32,
+    pool: SensorPool,
+) -> Result<()> {
     print_header("Identify (touch sensor)");
 
     crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(unit, winbio_helpers::SensorOperation::Identify)?;
 
-    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, true)?;
-    print_step("Session opened with focus. Touch the sensor now...");
+    if unit.is_some() {
+        winbio_helpers::resolve_unit_id(unit)?;
+    }
 
-    unsafe {
-        let mut unit_id = 0u32;
-        let mut identity = WINBIO_IDENTITY::default();
-        let mut subfactor = 0u8;
-        let mut reject_detail = 0u32;
-
-        let result = WinBioIdentify(
-            guard.session,
-            Some(&mut unit_id),
-            Some(&mut identity),
-            Some(&mut subfactor),
-            Some(&mut reject_detail),
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
+    if count == 1 {
+        print_step("Session opened with focus. Touch the sensor now...");
+        if let IdentifyOutcome::NoMatch = unsafe { identify_once(&guard, unit, timeout, false)? } {
+            crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+        }
+        return Ok(());
+    }
+
+    // Loop mode: no-match and bad captures are tallied, not treated as failures
+    print_step(&format!(
+        "Session opened with focus. {} attempts — Ctrl+C stops early.",
+        count
+    ));
+    let (mut attempts, mut matched, mut no_match, mut bad_capture, mut wrong_unit) =
+        (0u32, 0u32, 0u32, 0u32, 0u32);
+    for n in 1..=count {
+        print_blank();
+        print_step(&format!("Attempt {} of {} — touch the sensor...", n, count));
+        let outcome = match unsafe { identify_once(&guard, unit, timeout, true) } {
+            Ok(outcome) => outcome,
+            Err(_) if super::cancel_requested() => break,
+            Err(e) => return Err(e),
+        };
+        attempts += 1;
+        match outcome {
+            IdentifyOutcome::Matched => matched += 1,
+            IdentifyOutcome::NoMatch => no_match += 1,
+            IdentifyOutcome::BadCapture => bad_capture += 1,
+            IdentifyOutcome::WrongUnit => wrong_unit += 1,
+        }
+    }
+
+    print_header("Identify Summary");
+    print_info("Matched", &format!("{}/{}", matched, attempts));
+    print_info("No match", &no_match.to_string());
+    print_info("Bad captures", &bad_capture.to_string());
+    if wrong_unit > 0 {
+        print_info("Other unit", &wrong_unit.to_string());
+    }
+    if attempts > 0 {
+        print_info(
+            "Match rate",
+            &format!("{:.1}%", matched as f64 * 100.0 / attempts as f64),
         );
+    }
+    record(
+        "identify_summary",
+        json!({
+            "requested": count,
+            "attempts": attempts,
+            "matched": matched,
+            "no_match": no_match,
+            "bad_capture": bad_capture,
+            "wrong_unit": wrong_unit,
+        }),
+    );
+
+    Ok(())
+}
+
+/// Run one WinBioIdentify on an open session and print the result. With
+/// `tally`, unsuccessful touches are warnings rather than failures. Returns an
+/// error only for fatal WinBio errors (device gone, timeout, cancel).
+unsafe fn identify_once(
+    guard: &SessionGuard,
+    unit: Option<u32>,
+    timeout: Option<u64>,
+    tally: bool,
+) -> Result<IdentifyOutcome> {
+    let report = |message: &str| {
+        if tally {
+            print_warn(message)
+        } else {
+            print_fail(message)
+        }
+    };
+
+    let mut unit_id = 0u32;
+    let mut identity = WINBIO_IDENTITY::default();
+    let mut subfactor = 0u8;
+    let mut reject_detail = 0u32;
+
+    let result = guard.run_with_timeout(timeout, |session| {
+        winbio_helpers::with_busy_retry(|| {
+            winbio_helpers::trace_call(
+                "WinBioIdentify",
+                &format!("session={}", session),
+                WinBioIdentify(
+                    session,
+                    Some(&mut unit_id),
+                    Some(&mut identity),
+                    Some(&mut subfactor),
+                    Some(&mut reject_detail),
+                ),
+            )
+        })
+    })?;
 
-        if let Err(e) = result {
-            let code = crate::error::error_code(&e);
-            if code == 0x8009_8005 {
-                // WINBIO_E_NO_MATCH
-                print_fail("No match — finger not enrolled");
-                if reject_detail != 0 {
-                    print_info(
-                        "Reject reason",
-                        winbio_helpers::reject_reason(reject_detail),
-                    );
-                }
-            } else if code == 0x8009_8008 {
-                // WINBIO_E_BAD_CAPTURE
-                print_fail("Bad capture — try again");
+    report_identify(
+        result,
+        unit,
+        unit_id,
+        &identity,
+        subfactor,
+        reject_detail,
+        report,
+    )
+}
+
+/// Print the outcome of one WinBioIdentify, sync or async. `report` prints
+/// unsuccessful touches.
+fn report_identify(
+    result: windows::core::Result<()>,
+    unit: Option<u32>,
+    unit_id: u32,
+    identity: &WINBIO_IDENTITY,
+    subfactor: u8,
+    reject_detail: u32,
+    report: impl Fn(&str),
+) -> Result<IdentifyOutcome> {
+    if let Err(e) = result {
+        let code = crate::error::error_code(&e);
+        if code == 0x8009_8005 {
+            // WINBIO_E_NO_MATCH
+            report("No match — finger not enrolled");
+            if reject_detail != 0 {
                 print_info(
                     "Reject reason",
-                    winbio_helpers::reject_reason(reject_detail),
+                    &winbio_helpers::reject_reason(reject_detail),
                 );
-            } else {
-                return Err(crate::error::wrap_winbio_error("WinBioIdentify", &e));
             }
-            return Ok(());
+            return Ok(IdentifyOutcome::NoMatch);
+        } else if code == 0x8009_8008 {
+            // WINBIO_E_BAD_CAPTURE
+            report("Bad capture — try again");
+            print_info(
+                "Reject reason",
+                &winbio_helpers::reject_reason(reject_detail),
+            );
+            return Ok(IdentifyOutcome::BadCapture);
         }
+        return Err(crate::error::wrap_winbio_error("WinBioIdentify", &e));
+    }
 
-        print_pass("Finger identified successfully");
-        print_info("Unit ID", &unit_id.to_string());
-        print_info("Finger", &winbio_helpers::subfactor_name(subfactor));
-
-        // Print identity info
-        if identity.Type == winbio_helpers::WINBIO_ID_TYPE_SID {
-            let sid_data = &identity.Value.AccountSid;
-            let size = sid_data.Size as usize;
-            let bytes = &sid_data.Data[..size.min(sid_data.Data.len())];
-            let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
-            print_info("Identity (SID)", &hex.join(" "));
-        } else {
-            print_info("Identity type", &format!("{}", identity.Type));
+    if !super::check_responding_unit(unit, unit_id) {
+        return Ok(IdentifyOutcome::WrongUnit);
+    }
+
+    print_pass("Finger identified successfully");
+    print_info("Unit ID", &unit_id.to_string());
+    print_info("Finger", &winbio_helpers::subfactor_name(subfactor));
+
+    winbio_helpers::print_identity(identity);
+    Ok(IdentifyOutcome::Matched)
+}
+
+// WINBIO_OPERATION_* (winbio.h)
+const WINBIO_OPERATION_IDENTIFY: u32 = 4;
+
+/// How long to wait for the cancelled Identify to report back.
+const CANCEL_GRACE: Duration = Duration::from_secs(5);
+
+/// The completed Identify, copied out of the framework-owned result.
+struct AsyncIdentify {
+    status: windows::core::HRESULT,
+    unit_id: u32,
+    identity: WINBIO_IDENTITY,
+    subfactor: u8,
+    reject_detail: u32,
+}
+
+/// Where `async_callback` delivers Identify results; set while `--async` runs.
+static ASYNC_RESULTS: Mutex<Option<mpsc::Sender<AsyncIdentify>>> = Mutex::new(None);
+
+/// Completion callback for the async session, called on a framework thread.
+/// Every result must be released with `WinBioFree`, whatever the operation.
+unsafe extern "system" fn async_callback(result: *const WINBIO_ASYNC_RESULT) {
+    if result.is_null() {
+        return;
+    }
+    let r = &*result;
+    if r.Operation == WINBIO_OPERATION_IDENTIFY {
+        let identify = r.Parameters.Identify;
+        if let Some(tx) = ASYNC_RESULTS.lock().unwrap().as_ref() {
+            let _ = tx.send(AsyncIdentify {
+                status: r.ApiStatus,
+                unit_id: r.UnitId,
+                identity: identify.Identity,
+                subfactor: identify.SubFactor,
+                reject_detail: identify.RejectDetail,
+            });
+        }
+    }
+    winbio_helpers::winbio_free(result as *const _);
+}
+
+/// True if a key was pressed in the console since the last call. Other input
+/// events (mouse, focus, key releases) are consumed and ignored. Always false
+/// when stdin is not a console.
+fn key_pressed() -> bool {
+    unsafe {
+        let Ok(input) = GetStdHandle(STD_INPUT_HANDLE) else {
+            return false;
+        };
+        let mut pressed = false;
+        let mut pending = 0u32;
+        while GetNumberOfConsoleInputEvents(input, &mut pending).is_ok() && pending > 0 {
+            let mut record = [INPUT_RECORD::default()];
+            let mut read = 0u32;
+            if ReadConsoleInputW(input, &mut record, &mut read).is_err() || read == 0 {
+                break;
+            }
+            if record[0].EventType == KEY_EVENT as u16
+                && record[0].Event.KeyEvent.bKeyDown.as_bool()
+            {
+                pressed = true;
+            }
+        }
+        pressed
+    }
+}
+
+/// `identify --async`: open an asynchronous session so WinBioIdentify returns
+/// immediately, and wait for its completion callback while watching the
+/// console, so any key cancels the touch via `WinBioCancel` without a worker
+/// thread.
+pub fn run_identify_async(unit: Option<u32>, pool: SensorPool) -> Result<()> {
+    print_header("Identify (async, touch sensor)");
+
+    crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(unit, winbio_helpers::SensorOperation::Identify)?;
+
+    if unit.is_some() {
+        winbio_helpers::resolve_unit_id(unit)?;
+    }
+
+    let (tx, rx) = mpsc::channel();
+    *ASYNC_RESULTS.lock().unwrap() = Some(tx);
+    let result = identify_async(unit, pool, &rx);
+    *ASYNC_RESULTS.lock().unwrap() = None;
+    result
+}
+
+fn identify_async(
+    unit: Option<u32>,
+    pool: SensorPool,
+    rx: &mpsc::Receiver<AsyncIdentify>,
+) -> Result<()> {
+    let guard = SessionGuard::new_async(
+        winbio_helpers::WINBIO_FLAG_DEFAULT,
+        pool,
+        Some(async_callback),
+    )?;
+
+    unsafe {
+        winbio_helpers::trace_call(
+            "WinBioIdentify",
+            &format!("session={}, async", guard.session),
+            WinBioIdentify(guard.session, None, None, None, None),
+        )
+    }
+    .map_err(|e| crate::error::wrap_winbio_error("WinBioIdentify", &e))?;
+
+    // Drop key presses made before the prompt
+    key_pressed();
+    print_step(
+        "Async session opened with focus. Touch the sensor now — press any key to cancel...",
+    );
+
+    let mut cancelled = false;
+    let done = loop {
+        match rx.recv_timeout(Duration::from_millis(50)) {
+            Ok(done) => break done,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                anyhow::bail!("The async session stopped reporting results")
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+        if !cancelled && (key_pressed() || super::cancel_requested()) {
+            cancelled = true;
+            print_step("Cancelling...");
+            unsafe {
+                let _ = winbio_helpers::trace_call(
+                    "WinBioCancel",
+                    &format!("session={}", guard.session),
+                    WinBioCancel(guard.session),
+                );
+            }
+            // The Identify still completes, with WINBIO_E_CANCELED
+            break rx.recv_timeout(CANCEL_GRACE).map_err(|_| {
+                anyhow::anyhow!("WinBioIdentify did not complete after WinBioCancel")
+            })?;
         }
+    };
+
+    if (cancelled || super::cancel_requested()) && done.status.0 as u32 == 0x8009_8004 {
+        // WINBIO_E_CANCELED
+        print_warn("Cancelled before a finger touched the sensor");
+        return Ok(());
     }
 
+    let outcome = report_identify(
+        done.status.ok(),
+        unit,
+        done.unit_id,
+        &done.identity,
+        done.subfactor,
+        done.reject_detail,
+        print_fail,
+    )?;
+    if let IdentifyOutcome::NoMatch = outcome {
+        crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+    }
     Ok(())
 }
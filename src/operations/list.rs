@@ -1,17 +1,97 @@
 use anyhow::Result;
+use std::path::Path;
 use windows::Win32::Devices::BiometricFramework::*;
 
 use crate::output::*;
-use crate::winbio_helpers;
+use crate::winbio_helpers::{self, SensorPool};
 
+use super::enum_databases::csv_field;
 use super::SessionGuard;
 
-pub fn run_list() -> Result<()> {
+/// Output layout for `list-fingerprints`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListFormat {
+    /// Human-readable report
+    Text,
+    /// The report, then one CSV row per enrolled finger (or only the rows, with --output)
+    Csv,
+}
+
+/// One enrolled finger, as exported with `--format csv`.
+struct EnrollmentRow {
+    unit_id: u32,
+    account: String,
+    sid: String,
+    subfactor: u8,
+}
+
+/// Write `rows` as CSV to `output`, or print them after the report.
+fn write_csv(rows: &[EnrollmentRow], output: Option<&Path>) -> Result<()> {
+    let mut csv = String::from("unit_id,account,sid,subfactor,finger\n");
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            row.unit_id,
+            csv_field(&row.account),
+            csv_field(&row.sid),
+            row.subfactor,
+            csv_field(&winbio_helpers::subfactor_name(row.subfactor))
+        ));
+    }
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, csv)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            print_blank();
+            print_pass(&format!(
+                "{} row(s) written to {}",
+                rows.len(),
+                path.display()
+            ));
+        }
+        None => {
+            print_blank();
+            print_raw(csv.trim_end());
+        }
+    }
+    Ok(())
+}
+
+pub fn run_list(
+    timeout: Option<u64>,
+    all_users: bool,
+    format: ListFormat,
+    output: Option<&Path>,
+    pool: SensorPool,
+) -> Result<()> {
+    if format == ListFormat::Csv && json_mode() {
+        anyhow::bail!("--format csv cannot be combined with --json");
+    }
+    if output.is_some() && format != ListFormat::Csv {
+        anyhow::bail!("--output requires --format csv");
+    }
+
+    let rows = if all_users {
+        run_list_all_users(pool)?
+    } else {
+        run_list_current_user(timeout, pool)?
+    };
+
+    if format == ListFormat::Csv {
+        write_csv(&rows, output)?;
+    }
+    Ok(())
+}
+
+/// Identify the user with a touch, then list their enrollments on that unit.
+fn run_list_current_user(timeout: Option<u64>, pool: SensorPool) -> Result<Vec<EnrollmentRow>> {
     print_header("List Enrolled Fingerprints");
 
     crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(None, winbio_helpers::SensorOperation::Identify)?;
 
-    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, true)?;
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
     print_step("Session opened with focus. Touch the sensor to identify yourself...");
 
     unsafe {
@@ -21,55 +101,178 @@ pub fn run_list() -> Result<()> {
         let mut subfactor = 0u8;
         let mut reject_detail = 0u32;
 
-        WinBioIdentify(
-            guard.session,
-            Some(&mut unit_id),
-            Some(&mut identity),
-            Some(&mut subfactor),
-            Some(&mut reject_detail),
-        )
-        .map_err(|e| {
-            let code = crate::error::error_code(&e);
-            if code == 0x8009_8005 {
-                anyhow::anyhow!("No match — finger not enrolled. Cannot list enrollments.")
-            } else {
-                crate::error::wrap_winbio_error("WinBioIdentify", &e)
-            }
-        })?;
+        guard
+            .run_with_timeout(timeout, |session| {
+                winbio_helpers::trace_call(
+                    "WinBioIdentify",
+                    &format!("session={}", session),
+                    WinBioIdentify(
+                        session,
+                        Some(&mut unit_id),
+                        Some(&mut identity),
+                        Some(&mut subfactor),
+                        Some(&mut reject_detail),
+                    ),
+                )
+            })?
+            .map_err(|e| {
+                let code = crate::error::error_code(&e);
+                if code == 0x8009_8005 {
+                    crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+                    anyhow::anyhow!("No match — finger not enrolled. Cannot list enrollments.")
+                } else {
+                    crate::error::wrap_winbio_error("WinBioIdentify", &e)
+                }
+            })?;
 
         print_pass("User identified on sensor");
         print_info("Unit ID", &unit_id.to_string());
 
+        // Only SID identities have an account; GUID and wildcard identities
+        // are described instead and leave the sid column empty
+        let (account, sid_string) = if identity.Type == winbio_helpers::WINBIO_ID_TYPE_SID {
+            let account_sid = &identity.Value.AccountSid;
+            let sid = &account_sid.Data[..(account_sid.Size as usize).min(account_sid.Data.len())];
+            (
+                winbio_helpers::sid_to_account_name(sid),
+                winbio_helpers::sid_to_string(sid).unwrap_or_default(),
+            )
+        } else {
+            (
+                winbio_helpers::identity_description(&identity),
+                String::new(),
+            )
+        };
+        let mut rows = Vec::new();
+
         // Now enumerate enrollments for this identity
         let mut subfactor_array: *mut u8 = std::ptr::null_mut();
         let mut subfactor_count: usize = 0;
 
-        WinBioEnumEnrollments(
-            guard.session,
-            unit_id,
-            &identity,
-            &mut subfactor_array,
-            Some(&mut subfactor_count),
+        winbio_helpers::trace_call(
+            "WinBioEnumEnrollments",
+            &format!("session={}, unit={}", guard.session, unit_id),
+            WinBioEnumEnrollments(
+                guard.session,
+                unit_id,
+                &identity,
+                &mut subfactor_array,
+                Some(&mut subfactor_count),
+            ),
         )
         .map_err(|e| crate::error::wrap_winbio_error("WinBioEnumEnrollments", &e))?;
 
-        if subfactor_count == 0 {
+        if subfactor_count == 0 || subfactor_array.is_null() {
             print_warn("No enrolled fingerprints found for this identity");
         } else {
             print_pass(&format!("{} fingerprint(s) enrolled", subfactor_count));
-            let subfactors = std::slice::from_raw_parts(subfactor_array, subfactor_count);
+            let subfactors = winbio_helpers::winbio_slice(subfactor_array, subfactor_count);
             for (i, &sf) in subfactors.iter().enumerate() {
                 print_info(
                     &format!("  {}.", i + 1),
                     &format!("Finger {} — {}", sf, winbio_helpers::subfactor_name(sf)),
                 );
+                rows.push(EnrollmentRow {
+                    unit_id,
+                    account: account.clone(),
+                    sid: sid_string.clone(),
+                    subfactor: sf,
+                });
             }
         }
 
         if !subfactor_array.is_null() {
             winbio_helpers::winbio_free(subfactor_array as *const _);
         }
+
+        Ok(rows)
     }
+}
 
-    Ok(())
+/// List enrollments for every user profile on every unit (administrators only).
+/// WinBio has no wildcard identity for `WinBioEnumEnrollments`, so each profile
+/// SID from the registry is asked about individually.
+fn run_list_all_users(pool: SensorPool) -> Result<Vec<EnrollmentRow>> {
+    crate::elevation::require_elevated()?;
+
+    print_header("List Enrolled Fingerprints — All Users");
+
+    let units = winbio_helpers::enum_units()?;
+    if units.is_empty() {
+        crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
+        anyhow::bail!(
+            "No {} biometric units found",
+            winbio_helpers::biometric_type().name()
+        );
+    }
+
+    let sids = super::enum_databases::read_profile_sids();
+    print_info("User profiles", &sids.len().to_string());
+
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, false)?;
+
+    let mut rows = Vec::new();
+    for unit in &units {
+        print_blank();
+        print_step(&format!(
+            "Unit {} — {}",
+            unit.UnitId,
+            winbio_helpers::wchar_to_string(&unit.Description)
+        ));
+
+        let census = super::enum_databases::enrollment_census(guard.session, unit.UnitId, &sids);
+
+        if census.identities.is_empty() && census.refused > 0 {
+            if unit.Capabilities & winbio_helpers::WINBIO_CAPABILITY_DATABASE != 0 {
+                print_warn("Templates are stored on-chip; this sensor does not report other users' enrollments");
+            } else {
+                print_warn(&format!(
+                    "The storage adapter refused to enumerate {} identit(ies)",
+                    census.refused
+                ));
+            }
+            continue;
+        }
+
+        if census.identities.is_empty() {
+            print_warn("No enrolled fingerprints found for any user");
+            continue;
+        }
+
+        for (sid_string, subfactors) in &census.identities {
+            let account = sids
+                .iter()
+                .find(|(s, _)| s == sid_string)
+                .map(|(_, sid)| winbio_helpers::sid_to_account_name(sid))
+                .unwrap_or_else(|| sid_string.clone());
+            let fingers: Vec<String> = subfactors
+                .iter()
+                .map(|&sf| winbio_helpers::subfactor_name(sf))
+                .collect();
+            print_info(&format!("  {}", account), &fingers.join(", "));
+            record(
+                "enrollments",
+                serde_json::json!({
+                    "unit_id": unit.UnitId,
+                    "account": account,
+                    "sid": sid_string,
+                    "subfactors": subfactors,
+                }),
+            );
+            rows.extend(subfactors.iter().map(|&subfactor| EnrollmentRow {
+                unit_id: unit.UnitId,
+                account: account.clone(),
+                sid: sid_string.clone(),
+                subfactor,
+            }));
+        }
+        if census.refused > 0 {
+            print_warn(&format!(
+                "{} identit(ies) could not be queried",
+                census.refused
+            ));
+        }
+    }
+
+    Ok(rows)
 }
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use serde_json::json;
+use windows::Win32::Devices::BiometricFramework::*;
+
+use crate::output::*;
+use crate::winbio_helpers::{self, wchar_to_string};
+
+/// One line per biometric unit of the `--type`, and with `--json` a `units`
+/// array of the schema fields, so scripts can pick the unit ID to pass as
+/// `--unit` to other commands.
+pub fn run_list_units() -> Result<()> {
+    print_header("Biometric Units");
+
+    let units = winbio_helpers::enum_units()?;
+    if units.is_empty() {
+        crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
+        print_warn(&format!(
+            "No {} biometric units found",
+            winbio_helpers::biometric_type().name()
+        ));
+        return Ok(());
+    }
+
+    for unit in &units {
+        let pool = match unit.PoolType {
+            t if t == WINBIO_POOL_SYSTEM.0 => "System",
+            t if t == WINBIO_POOL_PRIVATE.0 => "Private",
+            _ => "Unknown",
+        };
+        let description = wchar_to_string(&unit.Description);
+        let manufacturer = wchar_to_string(&unit.Manufacturer);
+        let model = wchar_to_string(&unit.Model);
+        let serial = wchar_to_string(&unit.SerialNumber);
+        let firmware = format!(
+            "{}.{}",
+            unit.FirmwareVersion.MajorVersion, unit.FirmwareVersion.MinorVersion
+        );
+        let instance_id = wchar_to_string(&unit.DeviceInstanceId);
+
+        print_info(
+            &format!("  Unit {}", unit.UnitId),
+            &format!("{} ({}, {} pool)", description, manufacturer, pool),
+        );
+
+        record(
+            "units",
+            json!({
+                "unit_id": unit.UnitId,
+                "pool": pool,
+                "sensor_subtype": winbio_helpers::sensor_subtype_name(unit.SensorSubType),
+                "description": description,
+                "manufacturer": manufacturer,
+                "model": model,
+                "serial": serial,
+                "firmware": firmware,
+                "device_instance_id": instance_id,
+                "capabilities": unit.Capabilities,
+            }),
+        );
+    }
+    Ok(())
+}
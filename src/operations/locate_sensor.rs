@@ -0,0 +1,75 @@
+use anyhow::Result;
+use serde_json::json;
+use windows::Win32::Devices::BiometricFramework::*;
+
+use crate::output::*;
+use crate::winbio_helpers::{self, SensorPool};
+
+use super::SessionGuard;
+
+/// Wait for a touch with `WinBioLocateSensor` and report which unit it landed
+/// on, to map physical readers to the unit IDs `--unit` expects.
+pub fn run_locate_sensor(timeout: Option<u64>, pool: SensorPool) -> Result<()> {
+    print_header("Locate Sensor (touch the reader to identify)");
+
+    crate::elevation::warn_if_not_elevated();
+
+    let units = winbio_helpers::enum_units()?;
+    if units.is_empty() {
+        crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
+        anyhow::bail!(
+            "No {} biometric units found",
+            winbio_helpers::biometric_type().name()
+        );
+    }
+    print_info("Units present", &units.len().to_string());
+
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
+    print_step("Session opened with focus. Touch the reader you want to identify...");
+
+    let mut unit_id = 0u32;
+    let result = guard.run_with_timeout(timeout, |session| {
+        winbio_helpers::with_busy_retry(|| unsafe {
+            winbio_helpers::trace_call(
+                "WinBioLocateSensor",
+                &format!("session={}", session),
+                WinBioLocateSensor(session, Some(&mut unit_id)),
+            )
+        })
+    })?;
+    result.map_err(|e| crate::error::wrap_winbio_error("WinBioLocateSensor", &e))?;
+
+    print_blank();
+    print_pass(&format!("Touch detected on unit {}", unit_id));
+    match units.iter().find(|u| u.UnitId == unit_id) {
+        Some(unit) => {
+            let description = winbio_helpers::wchar_to_string(&unit.Description);
+            let instance_id = winbio_helpers::wchar_to_string(&unit.DeviceInstanceId);
+            print_info("Description", &description);
+            print_info(
+                "Manufacturer / Model",
+                &format!(
+                    "{} / {}",
+                    winbio_helpers::wchar_to_string(&unit.Manufacturer),
+                    winbio_helpers::wchar_to_string(&unit.Model)
+                ),
+            );
+            print_info("Device instance", &instance_id);
+            record(
+                "located_unit",
+                json!({
+                    "unit_id": unit_id,
+                    "description": description,
+                    "device_instance_id": instance_id,
+                }),
+            );
+        }
+        None => {
+            print_warn("The unit was not in the enumeration taken before the touch");
+            record("located_unit", json!({ "unit_id": unit_id }));
+        }
+    }
+    print_step(&format!("Pass --unit {} to target this reader", unit_id));
+
+    Ok(())
+}
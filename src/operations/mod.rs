@@ -1,18 +1,197 @@
+pub mod backup_databases;
 pub mod capture;
+pub mod collect;
+pub mod compare_templates;
 pub mod credential_state;
 pub mod delete;
 pub mod delete_database;
 pub mod enroll;
+pub mod enum_adapters;
 pub mod enum_databases;
+pub mod export_template;
 pub mod identify;
 pub mod list;
+pub mod list_units;
+pub mod locate_sensor;
+pub mod monitor_events;
 pub mod reinstall_driver;
 pub mod remove_device;
+pub mod sensor_properties;
 pub mod service;
+pub mod set_indicator;
 pub mod verify;
+pub mod watch;
+pub mod whoami;
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Once};
+use std::time::Duration;
+
+use windows::Win32::Devices::BiometricFramework::WinBioCancel;
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::System::Console::{SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_C_EVENT};
 
 use crate::winbio_helpers;
 
+/// Report a failure if `--unit` was given but a different unit responded.
+/// Returns true when the responding unit is acceptable.
+pub fn check_responding_unit(requested: Option<u32>, actual: u32) -> bool {
+    match requested {
+        Some(id) if id != actual => {
+            crate::output::print_fail(&format!(
+                "Sensor unit {} responded, but unit {} was requested",
+                actual, id
+            ));
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Ask the user to type `expected` before a destructive operation.
+/// Returns Ok(true) to proceed; Ok(false) (after printing a warning) on mismatch.
+/// `yes` (from `--yes`) skips the prompt, and is required when stdin is not a terminal.
+pub fn confirm_destructive(expected: &str, yes: bool) -> anyhow::Result<bool> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if yes {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("stdin is not a terminal — pass --yes to confirm this destructive operation");
+    }
+
+    // Prompt on stderr so `--json` output on stdout stays a single document
+    eprint!("  Type '{}' to confirm: ", expected);
+    let _ = std::io::stderr().flush();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    if answer.trim() == expected {
+        Ok(true)
+    } else {
+        crate::output::print_warn("Confirmation did not match — aborted, nothing was changed");
+        Ok(false)
+    }
+}
+
+/// True if `e` (or anything in its cause chain) is WINBIO_E_DATABASE_LOCKED.
+fn is_database_locked(e: &anyhow::Error) -> bool {
+    const WINBIO_E_DATABASE_LOCKED: u32 = 0x8009_8019;
+    e.chain().any(|cause| {
+        cause
+            .downcast_ref::<crate::error::WinBioError>()
+            .is_some_and(|w| w.hresult == WINBIO_E_DATABASE_LOCKED)
+            || cause
+                .downcast_ref::<windows::core::Error>()
+                .is_some_and(|w| crate::error::error_code(w) == WINBIO_E_DATABASE_LOCKED)
+    })
+}
+
+/// Run `op`; if it fails with WINBIO_E_DATABASE_LOCKED, offer to restart
+/// WbioSrvc (which releases the lock) and run it once more. Restarting
+/// interrupts every biometric client, so it only happens after the user types
+/// `restart`, or unprompted with `restart_on_lock` (`--restart-on-lock`).
+pub fn with_database_lock_retry(
+    restart_on_lock: bool,
+    mut op: impl FnMut() -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    use std::io::IsTerminal;
+
+    let err = match op() {
+        Err(e) if is_database_locked(&e) => e,
+        result => return result,
+    };
+
+    // Only a warning for now: a successful retry must not leave a failure behind
+    crate::output::print_warn(&format!("{:#}", err));
+    crate::output::print_step(
+        "The biometric database is locked, usually by a WbioSrvc stuck mid-operation; \
+         restarting the service clears it",
+    );
+    if !restart_on_lock {
+        if !std::io::stdin().is_terminal() {
+            crate::output::print_fail("The database is still locked; WbioSrvc was not restarted");
+            crate::output::print_step("Re-run with --restart-on-lock, or run restart-service");
+            return Err(err);
+        }
+        if !confirm_destructive("restart", false)? {
+            crate::output::print_fail("The database is still locked; WbioSrvc was not restarted");
+            return Err(err);
+        }
+    }
+
+    service::run_restart_service()?;
+    crate::output::print_step("Retrying once after the restart...");
+    match op() {
+        Ok(()) => {
+            crate::output::print_pass("Retry succeeded after restarting WbioSrvc");
+            Ok(())
+        }
+        Err(e) => {
+            crate::output::print_fail("Retry after restarting WbioSrvc failed too");
+            Err(e)
+        }
+    }
+}
+
+static FORCE_REMOTE: AtomicBool = AtomicBool::new(false);
+
+/// Allow interactive operations inside a Remote Desktop session (`--force`).
+/// Called once at startup.
+pub fn set_force_remote(force: bool) {
+    FORCE_REMOTE.store(force, Ordering::Relaxed);
+}
+
+/// Refuse interactive operations in a Remote Desktop session, where the local
+/// sensor never delivers touches and WinBio calls block forever. With `--force`
+/// this only warns.
+fn check_remote_session() -> anyhow::Result<()> {
+    use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_REMOTESESSION};
+
+    if unsafe { GetSystemMetrics(SM_REMOTESESSION) } == 0 {
+        return Ok(());
+    }
+    if !FORCE_REMOTE.load(Ordering::Relaxed) {
+        anyhow::bail!(
+            "Running in a Remote Desktop session — the fingerprint sensor is not available \
+             to remote sessions, so this command would hang waiting for a touch. \
+             Run it locally, or pass --force to try anyway."
+        );
+    }
+    crate::output::print_warn(
+        "REMOTE DESKTOP SESSION — sensor touches are usually not delivered; expect this to hang",
+    );
+    Ok(())
+}
+
+/// Session the Ctrl+C handler cancels; 0 when no `SessionGuard` is alive.
+static ACTIVE_SESSION: AtomicU32 = AtomicU32::new(0);
+static CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+static INSTALL_CTRL_HANDLER: Once = Once::new();
+
+/// True once Ctrl+C has cancelled the active session.
+pub fn cancel_requested() -> bool {
+    CANCEL_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Console control handler: on the first Ctrl+C/Ctrl+Break while a session is
+/// open, cancel the pending WinBio call so it returns WINBIO_E_CANCELED and the
+/// command unwinds through `SessionGuard::drop`. The handler never closes the
+/// session itself. Otherwise (no session, or a second Ctrl+C) fall through to
+/// the default handler, which terminates the process.
+unsafe extern "system" fn ctrl_handler(ctrl_type: u32) -> BOOL {
+    if ctrl_type != CTRL_C_EVENT && ctrl_type != CTRL_BREAK_EVENT {
+        return BOOL(0);
+    }
+    let session = ACTIVE_SESSION.load(Ordering::SeqCst);
+    if session == 0 || CANCEL_REQUESTED.swap(true, Ordering::SeqCst) {
+        return BOOL(0);
+    }
+    let _ = WinBioCancel(session);
+    BOOL(1)
+}
+
 /// RAII guard that opens a WinBio session and automatically closes it on drop.
 /// When `foreground` is true, creates a hidden focus window to satisfy WinBio's
 /// window focus requirement for interactive operations (Identify/Verify).
@@ -22,30 +201,121 @@ pub struct SessionGuard {
 }
 
 impl SessionGuard {
-    /// Open a new session with the given flags. If `foreground` is true,
+    /// Open a new session with the given flags against `pool`. If `foreground`
+    /// is true (interactive operations), refuse to run over Remote Desktop and
     /// create a hidden focus window with a message pump.
-    pub fn new(flags: u32, foreground: bool) -> anyhow::Result<Self> {
+    pub fn new(
+        flags: u32,
+        pool: winbio_helpers::SensorPool,
+        foreground: bool,
+    ) -> anyhow::Result<Self> {
+        Self::open_with(foreground, || winbio_helpers::open_session(flags, pool))
+    }
+
+    /// Like `new` with `foreground`, but the session is asynchronous: its
+    /// operations return at once and report to `callback` (see
+    /// `winbio_helpers::open_async_session`).
+    pub fn new_async(
+        flags: u32,
+        pool: winbio_helpers::SensorPool,
+        callback: windows::Win32::Devices::BiometricFramework::PWINBIO_ASYNC_COMPLETION_CALLBACK,
+    ) -> anyhow::Result<Self> {
+        Self::open_with(true, || {
+            winbio_helpers::open_async_session(flags, pool, callback)
+        })
+    }
+
+    fn open_with(
+        foreground: bool,
+        open: impl FnOnce() -> anyhow::Result<u32>,
+    ) -> anyhow::Result<Self> {
+        if foreground {
+            check_remote_session()?;
+        }
+
         let focus = if foreground {
             match winbio_helpers::FocusWindow::new() {
-                Some(fw) => Some(fw),
-                None => {
-                    crate::output::print_warn("Could not create focus window");
+                Ok(fw) => Some(fw),
+                Err(e) => {
+                    crate::output::print_warn(&format!("Could not create focus window: {}", e));
                     None
                 }
             }
         } else {
             None
         };
-        let session = winbio_helpers::open_session(flags)?;
+        let session = open()?;
+
+        INSTALL_CTRL_HANDLER.call_once(|| unsafe {
+            let _ = SetConsoleCtrlHandler(Some(Some(ctrl_handler)), true);
+        });
+        ACTIVE_SESSION.store(session, Ordering::SeqCst);
+
         Ok(Self {
             session,
             _focus: focus,
         })
     }
+
+    /// Run a blocking WinBio call (Identify, Verify, ...) against this session.
+    /// With `timeout_secs` set, the call runs on a worker thread and the session
+    /// is cancelled via `WinBioCancel` once the deadline passes. Returns an error
+    /// if the call was cancelled by the deadline; otherwise the call's own result.
+    pub fn run_with_timeout<T: Send>(
+        &self,
+        timeout_secs: Option<u64>,
+        call: impl FnOnce(u32) -> windows::core::Result<T> + Send,
+    ) -> anyhow::Result<windows::core::Result<T>> {
+        let session = self.session;
+        let Some(secs) = timeout_secs else {
+            return Ok(call(session));
+        };
+
+        std::thread::scope(|s| {
+            let (tx, rx) = mpsc::channel::<()>();
+            let worker = s.spawn(move || {
+                let result = call(session);
+                let _ = tx.send(());
+                result
+            });
+
+            let timed_out = matches!(
+                rx.recv_timeout(Duration::from_secs(secs)),
+                Err(mpsc::RecvTimeoutError::Timeout)
+            );
+            if timed_out {
+                unsafe {
+                    let _ = winbio_helpers::trace_call(
+                        "WinBioCancel",
+                        &format!("session={}", session),
+                        WinBioCancel(session),
+                    );
+                }
+            }
+
+            // The session is only closed by Drop, after the worker has returned.
+            let result = worker
+                .join()
+                .unwrap_or_else(|p| std::panic::resume_unwind(p));
+
+            // A finger may touch the sensor just as the deadline fires, in which
+            // case the call completes normally despite the cancel — keep that result.
+            match result {
+                Err(e) if timed_out && crate::error::error_code(&e) == 0x8009_8004 => {
+                    // WINBIO_E_CANCELED
+                    anyhow::bail!("Timed out after {} seconds waiting for the sensor", secs)
+                }
+                other => Ok(other),
+            }
+        })
+    }
 }
 
 impl Drop for SessionGuard {
     fn drop(&mut self) {
+        // Unregister first so the Ctrl+C handler can't cancel a closed handle
+        let _ =
+            ACTIVE_SESSION.compare_exchange(self.session, 0, Ordering::SeqCst, Ordering::SeqCst);
         winbio_helpers::close_session(self.session);
         // _focus drops automatically, releasing WinBio focus and stopping the message pump
     }
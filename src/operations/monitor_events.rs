@@ -0,0 +1,184 @@
+use anyhow::Result;
+use serde_json::json;
+use std::collections::BTreeMap;
+use windows::core::HRESULT;
+use windows::Win32::Devices::BiometricFramework::*;
+
+use crate::output::*;
+use crate::winbio_helpers::{self, SensorPool};
+
+use super::SessionGuard;
+
+// WINBIO_EVENT types (winbio_types.h)
+const WINBIO_EVENT_FP_UNCLAIMED: u32 = 0x0000_0001;
+const WINBIO_EVENT_FP_UNCLAIMED_IDENTIFY: u32 = 0x0000_0002;
+const WINBIO_EVENT_ERROR: u32 = 0xFFFF_FFFF;
+
+/// How often the unit list is polled for arrivals and removals; WinBio's
+/// event monitor only reports touches and errors.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Event monitor registration on a session; unregistered on drop, before the
+/// session itself is closed.
+struct EventMonitor {
+    session: u32,
+}
+
+impl EventMonitor {
+    fn register(session: u32) -> Result<Self> {
+        unsafe {
+            winbio_helpers::trace_call(
+                "WinBioRegisterEventMonitor",
+                &format!("session={}, mask=UNCLAIMED|UNCLAIMED_IDENTIFY", session),
+                WinBioRegisterEventMonitor(
+                    session,
+                    WINBIO_EVENT_FP_UNCLAIMED | WINBIO_EVENT_FP_UNCLAIMED_IDENTIFY,
+                    Some(event_callback),
+                    None,
+                ),
+            )
+        }
+        .map_err(|e| crate::error::wrap_winbio_error("WinBioRegisterEventMonitor", &e))?;
+        Ok(Self { session })
+    }
+}
+
+impl Drop for EventMonitor {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = winbio_helpers::trace_call(
+                "WinBioUnregisterEventMonitor",
+                &format!("session={}", self.session),
+                WinBioUnregisterEventMonitor(self.session),
+            );
+        }
+    }
+}
+
+/// Called by WinBio on its own thread for every monitored event. The event
+/// buffer belongs to us and must be released with `WinBioFree`.
+unsafe extern "system" fn event_callback(
+    _context: *const core::ffi::c_void,
+    status: HRESULT,
+    event: *const WINBIO_EVENT,
+) {
+    if event.is_null() {
+        print_fail(&format!(
+            "{}  event callback failed: {} (0x{:08X})",
+            timestamp(),
+            crate::error::hresult_message(status),
+            status.0 as u32
+        ));
+        return;
+    }
+
+    let event_ref = &*event;
+    match event_ref.Type {
+        WINBIO_EVENT_FP_UNCLAIMED => {
+            let p = event_ref.Parameters.Unclaimed;
+            print_step(&format!(
+                "{}  unit {}  unclaimed touch (reject detail: {})",
+                timestamp(),
+                p.UnitId,
+                winbio_helpers::reject_reason(p.RejectDetail)
+            ));
+            record_event("unclaimed", Some(p.UnitId), None);
+        }
+        WINBIO_EVENT_FP_UNCLAIMED_IDENTIFY => {
+            let p = event_ref.Parameters.UnclaimedIdentify;
+            print_pass(&format!(
+                "{}  unit {}  unclaimed identify: {}",
+                timestamp(),
+                p.UnitId,
+                winbio_helpers::subfactor_name(p.SubFactor)
+            ));
+            winbio_helpers::print_identity(&p.Identity);
+            record_event("unclaimed_identify", Some(p.UnitId), None);
+        }
+        WINBIO_EVENT_ERROR => {
+            let code = event_ref.Parameters.Error.ErrorCode;
+            print_fail(&format!(
+                "{}  framework error: {} (0x{:08X})",
+                timestamp(),
+                crate::error::hresult_message(code),
+                code.0 as u32
+            ));
+            record_event("error", None, Some(code.0 as u32));
+        }
+        other => {
+            print_info(
+                &format!("{}  event", timestamp()),
+                &format!("type 0x{:08X}", other),
+            );
+            record_event("other", None, None);
+        }
+    }
+
+    winbio_helpers::winbio_free(event as *const _);
+}
+
+fn record_event(kind: &str, unit_id: Option<u32>, hresult: Option<u32>) {
+    record(
+        "events",
+        json!({ "time": timestamp(), "event": kind, "unit_id": unit_id, "hresult": hresult }),
+    );
+}
+
+/// Unit ID -> description of the units currently enumerated.
+fn current_units() -> BTreeMap<u32, String> {
+    winbio_helpers::enum_units()
+        .unwrap_or_default()
+        .iter()
+        .map(|u| (u.UnitId, winbio_helpers::wchar_to_string(&u.Description)))
+        .collect()
+}
+
+/// Stream WinBio framework events live until Ctrl+C: touches no application
+/// claimed, framework errors, and (by polling the unit list) sensors arriving
+/// and disappearing.
+pub fn run_monitor_events(pool: SensorPool) -> Result<()> {
+    print_header("Monitor WinBio Events (Ctrl+C to stop)");
+
+    // Unclaimed-touch events are only delivered to the foreground application
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
+    let _monitor = EventMonitor::register(guard.session)?;
+
+    let mut units = current_units();
+    print_pass(&format!(
+        "Event monitor registered; {} unit(s) present",
+        units.len()
+    ));
+    for (id, description) in &units {
+        print_info(&format!("  Unit {}", id), description);
+    }
+    print_blank();
+
+    while !super::cancel_requested() {
+        std::thread::sleep(POLL_INTERVAL);
+
+        let now = current_units();
+        for (id, description) in now.iter().filter(|(id, _)| !units.contains_key(id)) {
+            print_pass(&format!(
+                "{}  unit {} arrived: {}",
+                timestamp(),
+                id,
+                description
+            ));
+            record_event("arrival", Some(*id), None);
+        }
+        for (id, description) in units.iter().filter(|(id, _)| !now.contains_key(id)) {
+            print_warn(&format!(
+                "{}  unit {} removed: {}",
+                timestamp(),
+                id,
+                description
+            ));
+            record_event("removal", Some(*id), None);
+        }
+        units = now;
+    }
+
+    print_blank();
+    print_step("Stopped; unregistering the event monitor");
+    Ok(())
+}
@@ -1,65 +1,10 @@
 use anyhow::{bail, Result};
+use serde_json::json;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use crate::output::*;
 
-/// Device info returned from PowerShell Get-PnpDevice.
-struct BiometricDevice {
-    friendly_name: String,
-    instance_id: String,
-}
-
-/// Find all biometric PnP devices using PowerShell Get-PnpDevice.
-fn find_biometric_devices() -> Result<Vec<BiometricDevice>> {
-    let ps_script = r#"
-        $devs = Get-PnpDevice -Class Biometric -ErrorAction SilentlyContinue |
-            Where-Object { $_.Status -eq 'OK' -or $_.Status -eq 'Error' -or $_.Status -eq 'Degraded' -or $_.Status -eq 'Unknown' }
-        if ($null -eq $devs) { exit 0 }
-        $devs | ForEach-Object {
-            [PSCustomObject]@{
-                FriendlyName = [string]$_.FriendlyName
-                InstanceId   = [string]$_.InstanceId
-                Status       = [string]$_.Status
-            }
-        } | ConvertTo-Json -Compress
-    "#;
-
-    let output = Command::new("powershell")
-        .args(["-NoProfile", "-NonInteractive", "-Command", ps_script])
-        .output()
-        .map_err(|e| anyhow::anyhow!("Failed to run PowerShell: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stdout = stdout.trim();
-
-    if stdout.is_empty() {
-        return Ok(vec![]);
-    }
-
-    // PowerShell returns a single object (not array) for one result
-    let raw: Vec<serde_json::Value> = if stdout.starts_with('[') {
-        serde_json::from_str(stdout)?
-    } else {
-        let single: serde_json::Value = serde_json::from_str(stdout)?;
-        vec![single]
-    };
-
-    let devices = raw
-        .into_iter()
-        .filter_map(|v| {
-            let friendly_name = v.get("FriendlyName")?.as_str()?.to_string();
-            let instance_id = v.get("InstanceId")?.as_str()?.to_string();
-            Some(BiometricDevice {
-                friendly_name,
-                instance_id,
-            })
-        })
-        .collect();
-
-    Ok(devices)
-}
-
 /// Get the OEM INF name (e.g. "oem50.inf") for a device by its instance ID.
 fn get_driver_inf_name(instance_id: &str) -> Result<String> {
     let escaped_id = instance_id.replace('\'', "''");
@@ -68,6 +13,7 @@ fn get_driver_inf_name(instance_id: &str) -> Result<String> {
         escaped_id
     );
 
+    let _timer = time_step("Driver INF lookup (PowerShell)");
     let output = Command::new("powershell")
         .args(["-NoProfile", "-NonInteractive", "-Command", &ps_script])
         .output()
@@ -84,6 +30,107 @@ fn get_driver_inf_name(instance_id: &str) -> Result<String> {
     Ok(stdout)
 }
 
+/// What pnputil reported for `/add-driver` or `/delete-driver`, parsed from
+/// its (English) text output.
+#[derive(Default)]
+struct PnputilResult {
+    /// Driver store name the package was published as, e.g. "oem52.inf".
+    published_name: Option<String>,
+    /// Devices pnputil names as having the package installed.
+    installed_devices: Vec<String>,
+    /// "installed on matching devices", which newer pnputil prints without
+    /// naming the devices.
+    installed: bool,
+    /// The package was deleted from the driver store.
+    deleted: bool,
+    /// "Failed to ..." lines.
+    failures: Vec<String>,
+}
+
+impl PnputilResult {
+    /// "Published as oem52.inf, installed on 1 device".
+    fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(name) = &self.published_name {
+            parts.push(format!("Published as {}", name));
+        }
+        match self.installed_devices.len() {
+            0 if self.installed => parts.push("installed on matching devices".to_string()),
+            0 => {}
+            1 => parts.push("installed on 1 device".to_string()),
+            n => parts.push(format!("installed on {} devices", n)),
+        }
+        if self.deleted {
+            parts.push("deleted from the driver store".to_string());
+        }
+        let summary = parts.join(", ");
+        let mut chars = summary.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => summary,
+        }
+    }
+}
+
+/// Parse pnputil's output. None when no line was recognized (another
+/// language, or a format change), so the caller can show the raw text.
+fn parse_pnputil_output(stdout: &str) -> Option<PnputilResult> {
+    let mut result = PnputilResult::default();
+    let mut recognized = false;
+    for line in stdout.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let lower = line.to_lowercase();
+        if lower.starts_with("failed") {
+            result.failures.push(line.to_string());
+            recognized = true;
+            continue;
+        }
+        match line.split_once(':') {
+            Some((key, value)) => {
+                let key = key.trim().to_lowercase();
+                let value = value.trim();
+                if key == "published name" && !value.is_empty() {
+                    result.published_name = Some(value.to_string());
+                } else if key.starts_with("driver package installed on device") {
+                    result.installed_devices.push(value.to_string());
+                } else {
+                    continue;
+                }
+            }
+            None if lower.starts_with("driver package installed on matching devices") => {
+                result.installed = true;
+            }
+            None if lower.starts_with("driver package deleted successfully") => {
+                result.deleted = true;
+            }
+            None => continue,
+        }
+        recognized = true;
+    }
+    recognized.then_some(result)
+}
+
+/// Report pnputil's output: the parsed summary and any failures, or the raw
+/// lines when it could not be parsed.
+fn report_pnputil_output(command: &str, stdout: &str) -> Option<PnputilResult> {
+    let Some(parsed) = parse_pnputil_output(stdout) else {
+        for line in stdout.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            print_info("pnputil", line);
+        }
+        return None;
+    };
+    let summary = parsed.summary();
+    if !summary.is_empty() {
+        print_info("pnputil", &summary);
+    }
+    for device in &parsed.installed_devices {
+        print_info("  Device", device);
+    }
+    for failure in &parsed.failures {
+        print_warn(&format!("pnputil {}: {}", command, failure));
+    }
+    Some(parsed)
+}
+
 /// Export the driver package from the driver store to a local directory.
 /// Returns the path to the .inf file inside the export directory.
 fn export_driver(oem_inf: &str, dest_dir: &Path) -> Result<PathBuf> {
@@ -116,47 +163,16 @@ fn export_driver(oem_inf: &str, dest_dir: &Path) -> Result<PathBuf> {
     bail!("No .inf file found in exported driver directory");
 }
 
-pub fn run_reinstall_driver() -> Result<()> {
-    if !crate::elevation::is_elevated()? {
-        bail!("This command requires Administrator privileges. Re-run as Administrator.");
-    }
-
-    print_header("Reinstall Biometric Driver");
-
-    // Step 1: Find biometric devices
-    print_step("Scanning for biometric devices...");
-    let devices = find_biometric_devices()?;
-
-    if devices.is_empty() {
-        bail!("No biometric devices found. Run 'check-hardware' to inspect PnP state.");
-    }
-
-    let device = &devices[0];
-    print_step(&format!(
-        "Found: {} ({})",
-        device.friendly_name, device.instance_id
-    ));
-
-    // Step 2: Identify the driver package
-    print_step("Identifying driver package...");
-    let oem_inf = get_driver_inf_name(&device.instance_id)?;
-    print_info("Driver INF", &oem_inf);
-
-    // Step 3: Export/backup the driver package before removing anything
-    print_step("Backing up driver package...");
-    let temp_dir = std::env::temp_dir().join("win-fp-debug-driver-backup");
-    if temp_dir.exists() {
-        std::fs::remove_dir_all(&temp_dir)?;
-    }
-    let inf_path = export_driver(&oem_inf, &temp_dir)?;
-    print_pass(&format!("Driver backed up to {}", temp_dir.display()));
-
+/// Steps 4–6: uninstall the driver, re-add it from the exported `inf_path`,
+/// and check the device came back. Returns whether any biometric device is
+/// present afterwards.
+fn uninstall_and_reinstall(oem_inf: &str, inf_path: &Path) -> Result<bool> {
     // Step 4: Delete driver from store AND uninstall from devices.
     // Using /uninstall keeps the device node alive (avoids USB re-enumeration)
     // but removes the driver, so re-adding it triggers a full INF install.
     print_step("Uninstalling driver from device and store...");
     let del_output = Command::new("pnputil")
-        .args(["/delete-driver", &oem_inf, "/uninstall", "/force"])
+        .args(["/delete-driver", oem_inf, "/uninstall", "/force"])
         .output()
         .map_err(|e| anyhow::anyhow!("Failed to run pnputil /delete-driver: {}", e))?;
 
@@ -164,6 +180,7 @@ pub fn run_reinstall_driver() -> Result<()> {
     let del_stderr = String::from_utf8_lossy(&del_output.stderr);
     if del_output.status.success() {
         print_pass("Driver uninstalled and removed from store");
+        report_pnputil_output("/delete-driver", &del_stdout);
     } else {
         print_warn(&format!(
             "pnputil /delete-driver /uninstall: {} {}",
@@ -185,16 +202,17 @@ pub fn run_reinstall_driver() -> Result<()> {
     let add_stderr = String::from_utf8_lossy(&add_output.stderr);
     if add_output.status.success() {
         print_pass("Driver reinstalled");
-        // Show pnputil output for transparency
-        let msg = add_stdout.trim();
-        if !msg.is_empty() {
-            for line in msg.lines() {
-                let line = line.trim();
-                if !line.is_empty() {
-                    print_info("pnputil", line);
-                }
-            }
-        }
+        let parsed = report_pnputil_output("/add-driver", &add_stdout);
+        record(
+            "add_driver",
+            json!({
+                "inf": inf_path.display().to_string(),
+                "parsed": parsed.is_some(),
+                "published_name": parsed.as_ref().and_then(|p| p.published_name.clone()),
+                "installed_devices": parsed.as_ref().map(|p| p.installed_devices.clone()),
+                "failures": parsed.as_ref().map(|p| p.failures.clone()),
+            }),
+        );
     } else {
         bail!(
             "pnputil /add-driver /install failed: {} {}",
@@ -207,7 +225,7 @@ pub fn run_reinstall_driver() -> Result<()> {
     print_step("Verifying device status...");
     std::thread::sleep(std::time::Duration::from_secs(2));
 
-    let after = find_biometric_devices()?;
+    let after = crate::pnp::enum_biometric_devices()?;
     if after.is_empty() {
         print_fail("No biometric device found after reinstallation");
     } else {
@@ -218,13 +236,115 @@ pub fn run_reinstall_driver() -> Result<()> {
             ));
         }
     }
+    Ok(!after.is_empty())
+}
+
+/// With `keep_backup`, the exported driver package is left in `%TEMP%` after a
+/// successful reinstall; it is always kept, and its path printed, on failure.
+pub fn run_reinstall_driver(device: Option<&str>, dry_run: bool, keep_backup: bool) -> Result<()> {
+    if !dry_run {
+        crate::elevation::require_elevated()?;
+    }
+
+    print_header("Reinstall Biometric Driver");
+
+    // Step 1: Find biometric devices
+    print_step("Scanning for biometric devices...");
+    let devices = crate::pnp::enum_biometric_devices()?;
+
+    if devices.is_empty() {
+        crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
+        bail!("No biometric devices found. Run 'check-hardware' to inspect PnP state.");
+    }
+
+    let device = match device {
+        Some(id) => match devices
+            .iter()
+            .find(|d| d.instance_id.eq_ignore_ascii_case(id))
+        {
+            Some(d) => d,
+            None => {
+                for d in &devices {
+                    print_info(
+                        "  Device",
+                        &format!("{} ({})", d.friendly_name, d.instance_id),
+                    );
+                }
+                bail!("No biometric device with instance ID '{}'", id);
+            }
+        },
+        None if devices.len() > 1 => {
+            print_warn(&format!("{} biometric devices found:", devices.len()));
+            for d in &devices {
+                print_info(
+                    "  Device",
+                    &format!("{} ({})", d.friendly_name, d.instance_id),
+                );
+            }
+            bail!("Choose the device to reinstall with --device <instance-id>");
+        }
+        None => &devices[0],
+    };
+    print_step(&format!(
+        "Found: {} ({})",
+        device.friendly_name, device.instance_id
+    ));
+
+    // Step 2: Identify the driver package
+    print_step("Identifying driver package...");
+    let oem_inf = get_driver_inf_name(&device.instance_id)?;
+    print_info("Driver INF", &oem_inf);
+
+    // A fresh directory per run, so a backup kept by an earlier run survives
+    let stamp: String = timestamp().chars().filter(char::is_ascii_digit).collect();
+    let temp_dir = std::env::temp_dir()
+        .join("win-fp-debug-driver-backup")
+        .join(format!("{}-{}", oem_inf.trim_end_matches(".inf"), stamp));
+
+    if dry_run {
+        print_step("Would run:");
+        print_info(
+            "  1",
+            &format!("pnputil /export-driver {} {}", oem_inf, temp_dir.display()),
+        );
+        print_info(
+            "  2",
+            &format!("pnputil /delete-driver {} /uninstall /force", oem_inf),
+        );
+        print_info(
+            "  3",
+            &format!(
+                "pnputil /add-driver {}\\<exported>.inf /install",
+                temp_dir.display()
+            ),
+        );
+        print_blank();
+        print_pass("Dry run — nothing was changed");
+        return Ok(());
+    }
 
-    // Clean up backup
-    if temp_dir.exists() {
+    // Step 3: Export/backup the driver package before removing anything
+    print_step("Backing up driver package...");
+    let inf_path = export_driver(&oem_inf, &temp_dir)?;
+    print_pass(&format!("Driver backed up to {}", temp_dir.display()));
+
+    let result = uninstall_and_reinstall(&oem_inf, &inf_path);
+
+    // Keep the backup whenever the device may need a manual reinstall
+    let keep = keep_backup || !matches!(result, Ok(true));
+    if keep {
+        print_blank();
+        print_info("Driver backup", &temp_dir.display().to_string());
+        print_step(&format!(
+            "To reinstall it manually: pnputil /add-driver \"{}\" /install",
+            inf_path.display()
+        ));
+    } else if temp_dir.exists() {
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
+    result?;
 
-    println!();
+    print_blank();
     print_step("Driver reinstallation complete. Run 'diagnose' to verify.");
 
     Ok(())
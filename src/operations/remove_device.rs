@@ -1,44 +1,28 @@
 use anyhow::{bail, Result};
-use std::process::Command;
 
 use crate::output::*;
 
-/// Find phantom biometric devices using PowerShell Get-PnpDevice.
-/// Returns a list of instance IDs where Problem == "CM_PROB_PHANTOM" (code 45).
+/// Find phantom biometric devices.
+/// Returns a list of instance IDs where Problem == CM_PROB_PHANTOM (code 45).
 fn find_phantom_biometric_devices() -> Result<Vec<String>> {
-    let ps_script = r#"
-        $devs = Get-PnpDevice -Class Biometric -ErrorAction SilentlyContinue
-        if ($null -eq $devs) { exit 0 }
-        $devs | Where-Object { $_.Problem -eq 'CM_PROB_PHANTOM' -or [string]$_.Problem -eq '45' } |
-            ForEach-Object { [string]$_.InstanceId } |
-            ConvertTo-Json -Compress
-    "#;
-
-    let output = Command::new("powershell")
-        .args(["-NoProfile", "-NonInteractive", "-Command", ps_script])
-        .output()
-        .map_err(|e| anyhow::anyhow!("Failed to run PowerShell: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stdout = stdout.trim();
-
-    if stdout.is_empty() {
-        return Ok(vec![]);
-    }
-
-    // PowerShell returns a single string (not array) for one result
-    let ids: Vec<String> = if stdout.starts_with('[') {
-        serde_json::from_str(stdout)?
-    } else {
-        let single: String = serde_json::from_str(stdout)?;
-        vec![single]
-    };
+    Ok(crate::pnp::enum_biometric_devices()?
+        .into_iter()
+        .filter(|d| d.is_phantom())
+        .map(|d| d.instance_id)
+        .collect())
+}
 
-    Ok(ids)
+/// What happened to one removal target.
+enum Removal {
+    Removed,
+    /// `--phantom` target that was reconnected after the scan; left alone.
+    NowPresent,
 }
 
-/// Remove a single device by instance ID using CfgMgr32 APIs.
-fn remove_device_by_instance_id(instance_id: &str) -> Result<()> {
+/// Remove a single device by instance ID using CfgMgr32 APIs. With
+/// `phantom_only`, the devnode is checked again first and left alone if the
+/// device came back since enumeration.
+fn remove_device_by_instance_id(instance_id: &str, phantom_only: bool) -> Result<Removal> {
     use windows::core::PCWSTR;
     use windows::Win32::Devices::DeviceAndDriverInstallation::*;
 
@@ -62,6 +46,22 @@ fn remove_device_by_instance_id(instance_id: &str) -> Result<()> {
             );
         }
 
+        if phantom_only {
+            let mut status = CM_DEVNODE_STATUS_FLAGS(0);
+            let mut problem = CM_PROB(0);
+            match CM_Get_DevNode_Status(&mut status, &mut problem, devnode, 0) {
+                // No live devnode: still a phantom
+                CR_NO_SUCH_DEVINST => {}
+                CR_SUCCESS if problem == CM_PROB_PHANTOM => {}
+                CR_SUCCESS => return Ok(Removal::NowPresent),
+                cr => bail!(
+                    "CM_Get_DevNode_Status failed for '{}': CONFIGRET={}",
+                    instance_id,
+                    cr.0
+                ),
+            }
+        }
+
         let cr = CM_Uninstall_DevNode(devnode, 0);
         if cr != CONFIGRET(0) {
             bail!(
@@ -72,16 +72,21 @@ fn remove_device_by_instance_id(instance_id: &str) -> Result<()> {
         }
     }
 
-    Ok(())
+    Ok(Removal::Removed)
 }
 
-pub fn run_remove_device(instance_id: Option<String>, phantom: bool) -> Result<()> {
+pub fn run_remove_device(
+    instance_id: Option<String>,
+    phantom: bool,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
     if instance_id.is_none() && !phantom {
         bail!("Either --instance-id <ID> or --phantom is required");
     }
 
-    if !crate::elevation::is_elevated()? {
-        bail!("This command requires Administrator privileges. Re-run as Administrator.");
+    if !dry_run {
+        crate::elevation::require_elevated()?;
     }
 
     print_header("Remove PnP Device");
@@ -99,28 +104,66 @@ pub fn run_remove_device(instance_id: Option<String>, phantom: bool) -> Result<(
         ids
     };
 
+    if dry_run {
+        for id in &targets {
+            print_step(&format!("Would remove: {} (CM_Uninstall_DevNode)", id));
+        }
+        print_blank();
+        print_pass("Dry run — nothing was changed");
+        return Ok(());
+    }
+
+    for id in &targets {
+        print_info("  Target", id);
+    }
+    let expected = if phantom { "ALL" } else { "yes" };
+    if !super::confirm_destructive(expected, yes)? {
+        return Ok(());
+    }
+
     let mut removed = 0u32;
-    let mut failed = 0u32;
+    let mut skipped = 0u32;
+    let mut failed: Vec<&str> = Vec::new();
 
     for id in &targets {
         print_step(&format!("Removing: {}", id));
-        match remove_device_by_instance_id(id) {
-            Ok(()) => {
+        match remove_device_by_instance_id(id, phantom) {
+            Ok(Removal::Removed) => {
                 print_pass(&format!("Removed: {}", id));
                 removed += 1;
             }
+            Ok(Removal::NowPresent) => {
+                print_warn(&format!(
+                    "Skipped {}: the device is connected again, so it is no longer a phantom",
+                    id
+                ));
+                skipped += 1;
+            }
             Err(e) => {
                 print_fail(&format!("Failed to remove {}: {}", id, e));
-                failed += 1;
+                failed.push(id);
             }
         }
     }
 
-    println!();
+    print_blank();
     print_info(
         "Summary",
-        &format!("{} removed, {} failed", removed, failed),
+        &format!(
+            "{} removed, {} skipped, {} failed",
+            removed,
+            skipped,
+            failed.len()
+        ),
     );
 
+    if !failed.is_empty() {
+        bail!(
+            "{} of {} device(s) could not be removed: {}",
+            failed.len(),
+            targets.len(),
+            failed.join(", ")
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,226 @@
+use anyhow::Result;
+use serde_json::json;
+use windows::Win32::Devices::BiometricFramework::*;
+
+use crate::output::*;
+use crate::winbio_helpers::{self, SensorPool};
+
+use super::SessionGuard;
+
+// WINBIO_PROPERTY_TYPE values (winbio_types.h)
+const WINBIO_PROPERTY_TYPE_UNIT: u32 = 2;
+const WINBIO_PROPERTY_TYPE_ACCOUNT: u32 = 4;
+
+// WINBIO_PROPERTY_ID values (winbio_types.h)
+const WINBIO_PROPERTY_SAMPLE_HINT: u32 = 1;
+const WINBIO_PROPERTY_EXTENDED_SENSOR_INFO: u32 = 2;
+const WINBIO_PROPERTY_EXTENDED_ENGINE_INFO: u32 = 3;
+const WINBIO_PROPERTY_EXTENDED_STORAGE_INFO: u32 = 4;
+const WINBIO_PROPERTY_EXTENDED_UNIT_STATUS: u32 = 6;
+
+/// How to turn a property buffer into a readable value.
+#[derive(Clone, Copy)]
+enum Decode {
+    /// A single ULONG.
+    Count,
+    /// WINBIO_EXTENDED_*_INFO: capabilities ULONG followed by the biometric factor.
+    CapabilitiesAndFactor,
+    /// WINBIO_EXTENDED_UNIT_STATUS: availability and reason code ULONGs.
+    UnitStatus,
+}
+
+struct PropertyQuery {
+    name: &'static str,
+    property_type: u32,
+    property_id: u32,
+    decode: Decode,
+}
+
+const PROPERTIES: &[PropertyQuery] = &[
+    PropertyQuery {
+        name: "Sample hint (samples per enrollment)",
+        property_type: WINBIO_PROPERTY_TYPE_ACCOUNT,
+        property_id: WINBIO_PROPERTY_SAMPLE_HINT,
+        decode: Decode::Count,
+    },
+    PropertyQuery {
+        name: "Extended sensor info",
+        property_type: WINBIO_PROPERTY_TYPE_UNIT,
+        property_id: WINBIO_PROPERTY_EXTENDED_SENSOR_INFO,
+        decode: Decode::CapabilitiesAndFactor,
+    },
+    PropertyQuery {
+        name: "Extended engine info",
+        property_type: WINBIO_PROPERTY_TYPE_UNIT,
+        property_id: WINBIO_PROPERTY_EXTENDED_ENGINE_INFO,
+        decode: Decode::CapabilitiesAndFactor,
+    },
+    PropertyQuery {
+        name: "Extended storage info",
+        property_type: WINBIO_PROPERTY_TYPE_UNIT,
+        property_id: WINBIO_PROPERTY_EXTENDED_STORAGE_INFO,
+        decode: Decode::CapabilitiesAndFactor,
+    },
+    PropertyQuery {
+        name: "Extended unit status",
+        property_type: WINBIO_PROPERTY_TYPE_UNIT,
+        property_id: WINBIO_PROPERTY_EXTENDED_UNIT_STATUS,
+        decode: Decode::UnitStatus,
+    },
+];
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn decode_property(data: &[u8], decode: Decode) -> String {
+    let decoded = match decode {
+        Decode::Count => read_u32(data, 0).map(|n| n.to_string()),
+        Decode::CapabilitiesAndFactor => {
+            read_u32(data, 0)
+                .zip(read_u32(data, 4))
+                .map(|(caps, factor)| {
+                    format!(
+                        "capabilities {}, factor {}",
+                        winbio_helpers::capabilities_string(caps),
+                        match factor {
+                            winbio_helpers::WINBIO_TYPE_FINGERPRINT => "Fingerprint".to_string(),
+                            winbio_helpers::WINBIO_TYPE_FACIAL_FEATURES => "Face".to_string(),
+                            _ => format!("0x{:08X}", factor),
+                        }
+                    )
+                })
+        }
+        Decode::UnitStatus => {
+            read_u32(data, 0)
+                .zip(read_u32(data, 4))
+                .map(|(availability, reason)| {
+                    format!("availability {}, reason code {}", availability, reason)
+                })
+        }
+    };
+    decoded.unwrap_or_else(|| format!("{} byte(s), too short to decode", data.len()))
+}
+
+/// Query one property and copy out its raw buffer. Adapters return an error
+/// for properties they don't implement.
+unsafe fn query_property(
+    session: u32,
+    unit_id: u32,
+    identity: Option<&WINBIO_IDENTITY>,
+    query: &PropertyQuery,
+) -> std::result::Result<Vec<u8>, windows::core::Error> {
+    let mut buffer: *mut std::ffi::c_void = std::ptr::null_mut();
+    let mut size: usize = 0;
+    let result = winbio_helpers::trace_call(
+        "WinBioGetProperty",
+        &format!(
+            "session={}, type={}, id={}, unit={}",
+            session, query.property_type, query.property_id, unit_id
+        ),
+        WinBioGetProperty(
+            session,
+            query.property_type,
+            query.property_id,
+            Some(unit_id),
+            identity.map(|i| i as *const _),
+            None,
+            &mut buffer,
+            Some(&mut size),
+        ),
+    );
+
+    let data = if buffer.is_null() {
+        Vec::new()
+    } else {
+        let data = std::slice::from_raw_parts(buffer as *const u8, size).to_vec();
+        winbio_helpers::winbio_free(buffer as *const _);
+        data
+    };
+    result.map(|()| data)
+}
+
+/// The adapter's `WINBIO_PROPERTY_SAMPLE_HINT` for the current user: how many
+/// good samples an enrollment on `unit_id` needs. `None` when not reported.
+pub fn sample_hint(session: u32, unit_id: u32) -> Option<u32> {
+    let sid = crate::elevation::current_user_sid().ok()?;
+    let identity = winbio_helpers::sid_identity(&sid)?;
+    let data = unsafe { query_property(session, unit_id, Some(&identity), &PROPERTIES[0]) }.ok()?;
+    read_u32(&data, 0).filter(|&n| n > 0)
+}
+
+pub fn run_sensor_properties(unit: Option<u32>, pool: SensorPool) -> Result<()> {
+    print_header("Sensor Properties");
+
+    crate::elevation::warn_if_not_elevated();
+    let unit_id = winbio_helpers::resolve_unit_id(unit)?;
+
+    // Account properties are looked up for the current user
+    let identity = crate::elevation::current_user_sid()
+        .ok()
+        .and_then(|sid| winbio_helpers::sid_identity(&sid));
+
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, false)?;
+    print_blank();
+
+    let mut supported = 0usize;
+    let mut records = Vec::new();
+    for query in PROPERTIES {
+        let identity = if query.property_type == WINBIO_PROPERTY_TYPE_ACCOUNT {
+            match identity.as_ref() {
+                Some(identity) => Some(identity),
+                None => {
+                    print_info(query.name, "skipped (current user SID unavailable)");
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+
+        match unsafe { query_property(guard.session, unit_id, identity, query) } {
+            Ok(data) => {
+                supported += 1;
+                let value = decode_property(&data, query.decode);
+                print_info(query.name, &value);
+                records.push(json!({
+                    "name": query.name,
+                    "type": query.property_type,
+                    "id": query.property_id,
+                    "supported": true,
+                    "value": value,
+                    "size": data.len(),
+                }));
+            }
+            Err(e) => {
+                let code = crate::error::error_code(&e);
+                print_info(
+                    query.name,
+                    &format!("not supported (0x{:08X} {})", code, e.message()),
+                );
+                records.push(json!({
+                    "name": query.name,
+                    "type": query.property_type,
+                    "id": query.property_id,
+                    "supported": false,
+                    "error": format!("0x{:08X}", code),
+                }));
+            }
+        }
+    }
+    record("properties", json!(records));
+
+    print_blank();
+    if supported == 0 {
+        print_warn("The sensor's adapters did not return any of the queried properties");
+    } else {
+        print_pass(&format!(
+            "{} of {} properties returned",
+            supported,
+            PROPERTIES.len()
+        ));
+    }
+
+    Ok(())
+}
@@ -4,80 +4,146 @@ use windows::Win32::System::Services::*;
 
 use crate::output::*;
 
-unsafe fn query_service_state() -> Result<u32> {
-    let scm = OpenSCManagerW(None, None, SC_MANAGER_CONNECT)
-        .map_err(|e| anyhow::anyhow!("Cannot open Service Control Manager: {}", e))?;
-
-    let service = match OpenServiceW(scm, w!("WbioSrvc"), SERVICE_QUERY_STATUS) {
-        Ok(s) => s,
-        Err(e) => {
-            let _ = CloseServiceHandle(scm);
-            bail!("Cannot open WbioSrvc service: {}", e);
-        }
-    };
+/// Open SCM and WbioSrvc handles with the requested access; both are closed on drop.
+struct WbioSrvc {
+    scm: SC_HANDLE,
+    service: SC_HANDLE,
+}
 
-    let mut status = SERVICE_STATUS::default();
-    let result = QueryServiceStatus(service, &mut status);
-    let _ = CloseServiceHandle(service);
-    let _ = CloseServiceHandle(scm);
-    result.map_err(|e| anyhow::anyhow!("QueryServiceStatus failed: {}", e))?;
+impl WbioSrvc {
+    unsafe fn open(access: u32) -> Result<Self> {
+        let scm = OpenSCManagerW(None, None, SC_MANAGER_CONNECT)
+            .map_err(|e| anyhow::anyhow!("Cannot open Service Control Manager: {}", e))?;
 
-    Ok(status.dwCurrentState.0)
+        match OpenServiceW(scm, w!("WbioSrvc"), access) {
+            Ok(service) => Ok(Self { scm, service }),
+            Err(e) => {
+                let _ = CloseServiceHandle(scm);
+                bail!("Cannot open WbioSrvc service: {}", e);
+            }
+        }
+    }
+
+    /// Poll every 500 ms (up to 15 s) until the service reaches `state`.
+    unsafe fn wait_for_state(&self, state: SERVICE_STATUS_CURRENT_STATE) -> bool {
+        for _ in 0..30 {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            let mut poll_status = SERVICE_STATUS::default();
+            let _ = QueryServiceStatus(self.service, &mut poll_status);
+            if poll_status.dwCurrentState == state {
+                return true;
+            }
+        }
+        false
+    }
 }
 
-pub fn run_stop_service() -> Result<()> {
-    if !crate::elevation::is_elevated()? {
-        bail!("This command requires Administrator privileges. Re-run as Administrator.");
+impl Drop for WbioSrvc {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseServiceHandle(self.service);
+            let _ = CloseServiceHandle(self.scm);
+        }
     }
+}
 
-    print_header("Stop WbioSrvc Service");
+/// Whether WbioSrvc is registered with the Service Control Manager. `None`
+/// when that can't be determined (e.g. the SCM itself can't be opened).
+pub fn service_installed() -> Option<bool> {
+    unsafe {
+        let scm = OpenSCManagerW(None, None, SC_MANAGER_CONNECT).ok()?;
+        let result = OpenServiceW(scm, w!("WbioSrvc"), SERVICE_QUERY_STATUS);
+        let _ = CloseServiceHandle(scm);
+        match result {
+            Ok(service) => {
+                let _ = CloseServiceHandle(service);
+                Some(true)
+            }
+            // HRESULT_FROM_WIN32(ERROR_SERVICE_DOES_NOT_EXIST)
+            Err(e) if crate::error::error_code(&e) == 0x8007_0424 => Some(false),
+            Err(_) => None,
+        }
+    }
+}
 
-    let state = unsafe { query_service_state()? };
-    if state == SERVICE_STOPPED.0 {
-        print_info("WbioSrvc", "already stopped");
-        return Ok(());
+/// Human-readable name of a service `dwCurrentState`.
+pub fn state_name(state: SERVICE_STATUS_CURRENT_STATE) -> &'static str {
+    match state {
+        SERVICE_STOPPED => "Stopped",
+        SERVICE_START_PENDING => "Start Pending",
+        SERVICE_STOP_PENDING => "Stop Pending",
+        SERVICE_RUNNING => "Running",
+        SERVICE_CONTINUE_PENDING => "Continue Pending",
+        SERVICE_PAUSE_PENDING => "Pause Pending",
+        SERVICE_PAUSED => "Paused",
+        _ => "Unknown",
     }
+}
+
+/// Current `dwCurrentState` of WbioSrvc (e.g. `SERVICE_RUNNING.0`).
+pub unsafe fn query_service_state() -> Result<u32> {
+    let handle = WbioSrvc::open(SERVICE_QUERY_STATUS)?;
+
+    let mut status = SERVICE_STATUS::default();
+    QueryServiceStatus(handle.service, &mut status)
+        .map_err(|e| anyhow::anyhow!("QueryServiceStatus failed: {}", e))?;
+
+    Ok(status.dwCurrentState.0)
+}
 
+fn stop_service() -> Result<()> {
     print_step("Stopping WbioSrvc...");
 
     unsafe {
-        let scm = OpenSCManagerW(None, None, SC_MANAGER_CONNECT)
-            .map_err(|e| anyhow::anyhow!("Cannot open Service Control Manager: {}", e))?;
-
-        let service = match OpenServiceW(scm, w!("WbioSrvc"), SERVICE_STOP | SERVICE_QUERY_STATUS) {
-            Ok(s) => s,
-            Err(e) => {
-                let _ = CloseServiceHandle(scm);
-                bail!("Cannot open WbioSrvc service: {} — run as Administrator", e);
-            }
-        };
+        let handle = WbioSrvc::open(SERVICE_STOP | SERVICE_QUERY_STATUS)?;
 
         let mut stop_status = SERVICE_STATUS::default();
-        ControlService(service, SERVICE_CONTROL_STOP, &mut stop_status)
+        ControlService(handle.service, SERVICE_CONTROL_STOP, &mut stop_status)
             .map_err(|e| anyhow::anyhow!("Failed to stop WbioSrvc: {}", e))?;
 
-        for _ in 0..30 {
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            let mut poll_status = SERVICE_STATUS::default();
-            let _ = QueryServiceStatus(service, &mut poll_status);
-            if poll_status.dwCurrentState == SERVICE_STOPPED {
-                let _ = CloseServiceHandle(service);
-                let _ = CloseServiceHandle(scm);
-                print_pass("WbioSrvc stopped");
-                return Ok(());
-            }
+        if !handle.wait_for_state(SERVICE_STOPPED) {
+            bail!("WbioSrvc did not stop in time");
         }
+    }
 
-        let _ = CloseServiceHandle(service);
-        let _ = CloseServiceHandle(scm);
-        bail!("WbioSrvc did not stop in time");
+    print_pass("WbioSrvc stopped");
+    Ok(())
+}
+
+fn start_service() -> Result<()> {
+    print_step("Starting WbioSrvc...");
+
+    unsafe {
+        let handle = WbioSrvc::open(SERVICE_START | SERVICE_QUERY_STATUS)?;
+
+        StartServiceW(handle.service, None)
+            .map_err(|e| anyhow::anyhow!("Failed to start WbioSrvc: {}", e))?;
+
+        if !handle.wait_for_state(SERVICE_RUNNING) {
+            bail!("WbioSrvc did not start in time");
+        }
+    }
+
+    print_pass("WbioSrvc started");
+    Ok(())
+}
+
+pub fn run_stop_service() -> Result<()> {
+    crate::elevation::require_elevated()?;
+
+    print_header("Stop WbioSrvc Service");
+
+    let state = unsafe { query_service_state()? };
+    if state == SERVICE_STOPPED.0 {
+        print_info("WbioSrvc", "already stopped");
+        return Ok(());
     }
+
+    stop_service()
 }
 
 pub fn run_start_service() -> Result<()> {
-    if !crate::elevation::is_elevated()? {
-        bail!("This command requires Administrator privileges. Re-run as Administrator.");
-    }
+    crate::elevation::require_elevated()?;
 
     print_header("Start WbioSrvc Service");
 
@@ -87,38 +153,63 @@ pub fn run_start_service() -> Result<()> {
         return Ok(());
     }
 
-    print_step("Starting WbioSrvc...");
+    start_service()
+}
 
-    unsafe {
-        let scm = OpenSCManagerW(None, None, SC_MANAGER_CONNECT)
-            .map_err(|e| anyhow::anyhow!("Cannot open Service Control Manager: {}", e))?;
+pub fn run_restart_service() -> Result<()> {
+    crate::elevation::require_elevated()?;
 
-        let service = match OpenServiceW(scm, w!("WbioSrvc"), SERVICE_START | SERVICE_QUERY_STATUS)
-        {
-            Ok(s) => s,
-            Err(e) => {
-                let _ = CloseServiceHandle(scm);
-                bail!("Cannot open WbioSrvc service: {}", e);
-            }
-        };
+    print_header("Restart WbioSrvc Service");
 
-        StartServiceW(service, None)
-            .map_err(|e| anyhow::anyhow!("Failed to start WbioSrvc: {}", e))?;
+    let state = unsafe { query_service_state()? };
+    if state == SERVICE_STOPPED.0 {
+        print_info("WbioSrvc", "already stopped — starting only");
+    } else {
+        stop_service()?;
+    }
 
-        for _ in 0..30 {
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            let mut poll_status = SERVICE_STATUS::default();
-            let _ = QueryServiceStatus(service, &mut poll_status);
-            if poll_status.dwCurrentState == SERVICE_RUNNING {
-                let _ = CloseServiceHandle(service);
-                let _ = CloseServiceHandle(scm);
-                print_pass("WbioSrvc started");
-                return Ok(());
+    start_service()
+}
+
+/// Poll WbioSrvc every 250 ms and print a timestamped line for every state
+/// change until Ctrl+C, to catch a service that starts and then crashes in a loop.
+pub fn run_monitor_service() -> Result<()> {
+    print_header("Monitor WbioSrvc (Ctrl+C to stop)");
+
+    unsafe {
+        let handle = WbioSrvc::open(SERVICE_QUERY_STATUS)?;
+        let mut last = None;
+
+        loop {
+            let mut status = SERVICE_STATUS::default();
+            QueryServiceStatus(handle.service, &mut status)
+                .map_err(|e| anyhow::anyhow!("QueryServiceStatus failed: {}", e))?;
+
+            if last != Some(status.dwCurrentState) {
+                let line = format!("{}  {}", timestamp(), state_name(status.dwCurrentState));
+                match status.dwCurrentState {
+                    SERVICE_RUNNING => print_pass(&line),
+                    SERVICE_STOPPED if status.dwWin32ExitCode != 0 => {
+                        print_fail(&format!(
+                            "{} (exit code {}, service-specific {})",
+                            line, status.dwWin32ExitCode, status.dwServiceSpecificExitCode
+                        ));
+                    }
+                    SERVICE_STOPPED => print_warn(&line),
+                    _ => print_step(&line),
+                }
+                record(
+                    "transitions",
+                    serde_json::json!({
+                        "time": timestamp(),
+                        "state": state_name(status.dwCurrentState),
+                        "exit_code": status.dwWin32ExitCode,
+                    }),
+                );
+                last = Some(status.dwCurrentState);
             }
-        }
 
-        let _ = CloseServiceHandle(service);
-        let _ = CloseServiceHandle(scm);
-        bail!("WbioSrvc did not start in time");
+            std::thread::sleep(std::time::Duration::from_millis(250));
+        }
     }
 }
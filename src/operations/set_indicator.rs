@@ -0,0 +1,121 @@
+use anyhow::Result;
+use serde_json::json;
+use windows::Win32::Devices::BiometricFramework::*;
+
+use crate::output::*;
+use crate::winbio_helpers::{self, SensorPool};
+
+use super::SessionGuard;
+
+// WINBIO_INDICATOR_STATUS values (winbio_adapter.h)
+const WINBIO_INDICATOR_ON: u32 = 1;
+const WINBIO_INDICATOR_OFF: u32 = 2;
+
+/// WINBIO_E_INVALID_CONTROL_CODE
+const WINBIO_E_INVALID_CONTROL_CODE: u32 = 0x8009_8009;
+/// E_NOTIMPL
+const E_NOTIMPL: u32 = 0x8000_4001;
+
+/// Switch a reader's indicator LED on or off, to check which physical device
+/// a unit ID belongs to and that the sensor takes commands at all.
+///
+/// WinBio has no public indicator property (`WinBioSetProperty` only accepts
+/// the anti-spoof policy), so the `WINBIO_INDICATOR_STATUS` value is sent to
+/// the sensor adapter with `WinBioControlUnit` under the vendor's
+/// `control_code`. Units that don't report the Indicator capability are refused.
+pub fn run_set_indicator(
+    unit: Option<u32>,
+    on: bool,
+    control_code: u32,
+    pool: SensorPool,
+) -> Result<()> {
+    print_header("Set Sensor Indicator");
+
+    crate::elevation::warn_if_not_elevated();
+    let unit_id = winbio_helpers::resolve_unit_id(unit)?;
+    winbio_helpers::require_capabilities(
+        Some(unit_id),
+        winbio_helpers::SensorOperation::Indicator,
+    )?;
+
+    let status = if on {
+        WINBIO_INDICATOR_ON
+    } else {
+        WINBIO_INDICATOR_OFF
+    };
+    print_info("Indicator", if on { "on" } else { "off" });
+    print_info("Control code", &format!("0x{:08X}", control_code));
+
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, false)?;
+
+    let mut received = [0u8; 64];
+    let mut received_size: usize = 0;
+    let mut operation_status: u32 = 0;
+    let result = unsafe {
+        winbio_helpers::with_busy_retry(|| {
+            winbio_helpers::trace_call(
+                "WinBioLockUnit",
+                &format!("session={}, unit={}", guard.session, unit_id),
+                WinBioLockUnit(guard.session, unit_id),
+            )
+        })
+        .map_err(|e| crate::error::wrap_winbio_error("WinBioLockUnit", &e))?;
+
+        let result = winbio_helpers::trace_call(
+            "WinBioControlUnit",
+            &format!(
+                "session={}, unit={}, component=sensor, code=0x{:08X}, status={}",
+                guard.session, unit_id, control_code, status
+            ),
+            WinBioControlUnit(
+                guard.session,
+                unit_id,
+                WINBIO_COMPONENT_SENSOR,
+                control_code,
+                &status.to_le_bytes(),
+                &mut received,
+                &mut received_size,
+                Some(&mut operation_status),
+            ),
+        );
+        let _ = WinBioUnlockUnit(guard.session, unit_id);
+        result
+    };
+
+    record(
+        "indicator",
+        json!({
+            "unit_id": unit_id,
+            "on": on,
+            "control_code": control_code,
+            "hresult": result.as_ref().err().map(crate::error::error_code),
+            "operation_status": operation_status,
+        }),
+    );
+
+    if let Err(e) = result {
+        let code = crate::error::error_code(&e);
+        if matches!(code, WINBIO_E_INVALID_CONTROL_CODE | E_NOTIMPL) {
+            print_fail(&format!(
+                "The sensor adapter does not accept control code 0x{:08X}",
+                control_code
+            ));
+            print_step(
+                "Indicator control codes are vendor-specific; check the reader's documentation",
+            );
+            return Ok(());
+        }
+        return Err(crate::error::wrap_winbio_error("WinBioControlUnit", &e));
+    }
+
+    print_pass(&format!(
+        "Indicator switched {} on unit {}",
+        if on { "on" } else { "off" },
+        unit_id
+    ));
+    if operation_status != 0 {
+        print_info("Adapter status", &format!("0x{:08X}", operation_status));
+    }
+    print_step("Check that the LED on the reader you expect changed");
+    Ok(())
+}
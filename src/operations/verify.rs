@@ -2,94 +2,256 @@ use anyhow::Result;
 use windows::Win32::Devices::BiometricFramework::*;
 
 use crate::output::*;
-use crate::winbio_helpers;
+use crate::winbio_helpers::{self, SensorPool};
 
 use super::SessionGuard;
 
-pub fn run_verify(finger: u8) -> Result<()> {
+pub fn run_verify(
+    finger: u8,
+    unit: Option<u32>,
+    timeout: Option<u64>,
+    no_identify: bool,
+    pool: SensorPool,
+) -> Result<()> {
     print_header(&format!(
         "Verify Finger {} ({})",
         finger,
         winbio_helpers::subfactor_name(finger)
     ));
 
-    if !(1..=10).contains(&finger) {
-        print_fail("Finger must be 1–10");
+    if !(1..=10).contains(&finger) && finger != winbio_helpers::WINBIO_SUBTYPE_ANY {
+        print_fail("Finger must be 1–10 or any");
         return Ok(());
     }
 
     crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(unit, winbio_helpers::SensorOperation::Verify)?;
 
-    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, true)?;
+    if unit.is_some() {
+        winbio_helpers::resolve_unit_id(unit)?;
+    }
 
-    // First identify to get the WINBIO_IDENTITY
-    print_step("Touch the sensor to identify yourself first...");
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
 
     unsafe {
         let mut unit_id = 0u32;
-        let mut identity = WINBIO_IDENTITY::default();
-        let mut subfactor = 0u8;
-        let mut reject_detail = 0u32;
-
-        WinBioIdentify(
-            guard.session,
-            Some(&mut unit_id),
-            Some(&mut identity),
-            Some(&mut subfactor),
-            Some(&mut reject_detail),
-        )
-        .map_err(|e| crate::error::wrap_winbio_error("WinBioIdentify", &e))?;
-
-        print_pass("User identified");
-
-        // Now verify the specific finger
-        print_step(&format!(
-            "Now touch with finger {} ({}) to verify...",
-            finger,
-            winbio_helpers::subfactor_name(finger)
-        ));
+        let identity = if no_identify {
+            // Verify against the logged-in user directly — one touch instead of two
+            let sid = crate::elevation::current_user_sid().map_err(|e| {
+                anyhow::anyhow!(
+                    "Could not read the current user's SID ({}) — retry without --no-identify",
+                    e
+                )
+            })?;
+            let identity = winbio_helpers::sid_identity(&sid).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Current user's SID is too long for WINBIO_IDENTITY — retry without --no-identify"
+                )
+            })?;
+            print_pass("Using the current user's SID as the identity");
+            identity
+        } else {
+            // First identify to get the WINBIO_IDENTITY
+            print_step("Touch the sensor to identify yourself first...");
+
+            let mut identity = WINBIO_IDENTITY::default();
+            let mut subfactor = 0u8;
+            let mut reject_detail = 0u32;
+
+            guard
+                .run_with_timeout(timeout, |session| {
+                    winbio_helpers::with_busy_retry(|| {
+                        winbio_helpers::trace_call(
+                            "WinBioIdentify",
+                            &format!("session={}", session),
+                            WinBioIdentify(
+                                session,
+                                Some(&mut unit_id),
+                                Some(&mut identity),
+                                Some(&mut subfactor),
+                                Some(&mut reject_detail),
+                            ),
+                        )
+                    })
+                })?
+                .map_err(|e| crate::error::wrap_winbio_error("WinBioIdentify", &e))?;
 
-        let mut match_result: u8 = 0;
-        let mut verify_reject = 0u32;
-
-        let result = WinBioVerify(
-            guard.session,
-            &identity,
-            finger,
-            Some(&mut unit_id),
-            Some(&mut match_result),
-            Some(&mut verify_reject),
-        );
-
-        if let Err(e) = result {
-            let code = crate::error::error_code(&e);
-            if code == 0x8009_8005 {
-                print_fail("Verification failed — NO MATCH");
-                if verify_reject != 0 {
-                    print_info(
-                        "Reject reason",
-                        winbio_helpers::reject_reason(verify_reject),
-                    );
+            print_pass("User identified");
+            identity
+        };
+
+        if finger != winbio_helpers::WINBIO_SUBTYPE_ANY {
+            // Spare the second touch when the finger isn't enrolled at all
+            let known_unit = unit.or((unit_id != 0).then_some(unit_id));
+            if let Some(unit_id) = known_unit {
+                if !super::delete::require_enrolled(guard.session, unit_id, &identity, finger) {
+                    crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+                    return Ok(());
                 }
-                return Ok(());
             }
-            if code == 0x8009_8008 {
-                print_fail("Bad capture — try again");
+            crate::finger_guide::print_finger_guide(finger);
+            print_step(&format!(
+                "Now touch with finger {} ({}) to verify...",
+                finger,
+                winbio_helpers::subfactor_name(finger)
+            ));
+            let outcome = verify_once(&guard, &identity, finger, unit, timeout, false)?;
+            if outcome == VerifyOutcome::NoMatch {
+                crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+            }
+            return Ok(());
+        }
+
+        print_step("Now touch with any enrolled finger to verify...");
+        match verify_once(&guard, &identity, finger, unit, timeout, false)? {
+            VerifyOutcome::Matched => {
                 print_info(
-                    "Reject reason",
-                    winbio_helpers::reject_reason(verify_reject),
+                    "Matched finger",
+                    "not reported by WinBioVerify for WINBIO_SUBTYPE_ANY",
                 );
                 return Ok(());
             }
-            return Err(crate::error::wrap_winbio_error("WinBioVerify", &e));
+            VerifyOutcome::NoMatch => {
+                crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+                return Ok(());
+            }
+            VerifyOutcome::Unsupported => {}
+            VerifyOutcome::BadCapture | VerifyOutcome::WrongUnit => return Ok(()),
         }
 
-        if match_result != 0 {
-            print_pass("Verification SUCCEEDED — finger matches");
-        } else {
-            print_fail("Verification FAILED — finger does not match");
+        // The adapter rejected WINBIO_SUBTYPE_ANY: try each enrolled finger in turn
+        let unit_id = match (unit, unit_id) {
+            (Some(id), _) => id,
+            (None, 0) => {
+                winbio_helpers::resolve_unit_for(None, winbio_helpers::SensorOperation::Verify)?
+            }
+            (None, id) => id,
+        };
+        let enrolled = super::delete::enum_enrollments(guard.session, unit_id, &identity)?;
+        if enrolled.is_empty() {
+            crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+            print_fail("No fingers are enrolled for this identity on the unit");
+            return Ok(());
         }
+        print_warn(&format!(
+            "The sensor does not accept \"any finger\" — verifying the {} enrolled finger(s) one by one",
+            enrolled.len()
+        ));
+        for &subfactor in &enrolled {
+            crate::finger_guide::print_finger_guide(subfactor);
+            print_step(&format!(
+                "Touch with finger {} ({})...",
+                subfactor,
+                winbio_helpers::subfactor_name(subfactor)
+            ));
+            if verify_once(&guard, &identity, subfactor, unit, timeout, true)?
+                == VerifyOutcome::Matched
+            {
+                print_info("Matched finger", &winbio_helpers::subfactor_name(subfactor));
+                return Ok(());
+            }
+        }
+        crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+        print_fail("None of the enrolled fingers matched");
     }
 
     Ok(())
 }
+
+/// How one WinBioVerify ended.
+#[derive(PartialEq, Eq)]
+enum VerifyOutcome {
+    Matched,
+    NoMatch,
+    BadCapture,
+    /// The touch landed on a unit other than `--unit`.
+    WrongUnit,
+    /// The adapter rejected `WINBIO_SUBTYPE_ANY` as the subfactor.
+    Unsupported,
+}
+
+/// Run one WinBioVerify of `subfactor` against `identity` and print the
+/// result. With `tally`, a non-matching finger is a warning rather than a
+/// failure, since another finger may still match.
+unsafe fn verify_once(
+    guard: &SessionGuard,
+    identity: &WINBIO_IDENTITY,
+    subfactor: u8,
+    unit: Option<u32>,
+    timeout: Option<u64>,
+    tally: bool,
+) -> Result<VerifyOutcome> {
+    let mut unit_id = 0u32;
+    let mut match_result: u8 = 0;
+    let mut verify_reject = 0u32;
+
+    let result = guard.run_with_timeout(timeout, |session| {
+        winbio_helpers::with_busy_retry(|| {
+            winbio_helpers::trace_call(
+                "WinBioVerify",
+                &format!("session={}, subfactor=0x{:02X}", session, subfactor),
+                WinBioVerify(
+                    session,
+                    identity,
+                    subfactor,
+                    Some(&mut unit_id),
+                    Some(&mut match_result),
+                    Some(&mut verify_reject),
+                ),
+            )
+        })
+    })?;
+
+    if let Err(e) = result {
+        let code = crate::error::error_code(&e);
+        if subfactor == winbio_helpers::WINBIO_SUBTYPE_ANY
+            && matches!(code, 0x8007_0057 | 0x8000_4001)
+        {
+            // E_INVALIDARG, E_NOTIMPL
+            return Ok(VerifyOutcome::Unsupported);
+        }
+        if code == 0x8009_8005 {
+            // WINBIO_E_NO_MATCH
+            let message = "Verification failed — NO MATCH";
+            if tally {
+                print_warn(message);
+            } else {
+                print_fail(message);
+            }
+            if verify_reject != 0 {
+                print_info(
+                    "Reject reason",
+                    &winbio_helpers::reject_reason(verify_reject),
+                );
+            }
+            return Ok(VerifyOutcome::NoMatch);
+        }
+        if code == 0x8009_8008 {
+            // WINBIO_E_BAD_CAPTURE
+            print_fail("Bad capture — try again");
+            print_info(
+                "Reject reason",
+                &winbio_helpers::reject_reason(verify_reject),
+            );
+            return Ok(VerifyOutcome::BadCapture);
+        }
+        return Err(crate::error::wrap_winbio_error("WinBioVerify", &e));
+    }
+
+    if !super::check_responding_unit(unit, unit_id) {
+        return Ok(VerifyOutcome::WrongUnit);
+    }
+
+    if match_result != 0 {
+        print_pass("Verification SUCCEEDED — finger matches");
+        Ok(VerifyOutcome::Matched)
+    } else {
+        let message = "Verification FAILED — finger does not match";
+        if tally {
+            print_warn(message);
+        } else {
+            print_fail(message);
+        }
+        Ok(VerifyOutcome::NoMatch)
+    }
+}
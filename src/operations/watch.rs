@@ -0,0 +1,111 @@
+use anyhow::Result;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use windows::Win32::Devices::BiometricFramework::*;
+
+use crate::output::*;
+use crate::winbio_helpers::{self, SensorPool};
+
+use super::SessionGuard;
+
+pub fn run_watch(pool: SensorPool) -> Result<()> {
+    print_header("Watch Sensor Touches (Ctrl+C to stop)");
+
+    crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(None, winbio_helpers::SensorOperation::RawCapture)?;
+
+    // Raw capture requires WINBIO_FLAG_RAW
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_RAW, pool, false)?;
+
+    print_step("Session opened in RAW mode. Touch the sensor repeatedly...");
+    print_blank();
+
+    let mut touches = 0u32;
+    let mut good = 0u32;
+    let mut rejects: HashMap<Cow<'static, str>, u32> = HashMap::new();
+    let mut fatal = None;
+
+    while !super::cancel_requested() {
+        let mut sample: *mut WINBIO_BIR = std::ptr::null_mut();
+        let mut sample_size: usize = 0;
+        let mut unit_id = 0u32;
+        let mut reject_detail = 0u32;
+
+        let result = unsafe {
+            winbio_helpers::trace_call(
+                "WinBioCaptureSample",
+                &format!("session={}, flags=RAW", guard.session),
+                WinBioCaptureSample(
+                    guard.session,
+                    winbio_helpers::WINBIO_PURPOSE_NO_PURPOSE_AVAILABLE,
+                    WINBIO_DATA_FLAG_RAW as u8,
+                    Some(&mut unit_id),
+                    &mut sample,
+                    Some(&mut sample_size),
+                    Some(&mut reject_detail),
+                ),
+            )
+        };
+
+        let empty = winbio_helpers::is_empty_sample(sample, sample_size);
+        if !sample.is_null() {
+            unsafe { winbio_helpers::winbio_free(sample as *const _) };
+        }
+
+        match result {
+            Ok(()) if empty => {
+                touches += 1;
+                *rejects.entry(Cow::Borrowed("Empty sample")).or_default() += 1;
+                print_warn(&format!(
+                    "{}  unit {}  sensor returned an empty sample ({} bytes)",
+                    timestamp(),
+                    unit_id,
+                    sample_size
+                ));
+            }
+            Ok(()) => {
+                touches += 1;
+                good += 1;
+                print_pass(&format!(
+                    "{}  unit {}  good capture ({} bytes)",
+                    timestamp(),
+                    unit_id,
+                    sample_size
+                ));
+            }
+            Err(e) if crate::error::error_code(&e) == 0x8009_8008 => {
+                // WINBIO_E_BAD_CAPTURE
+                touches += 1;
+                let reason = winbio_helpers::reject_reason(reject_detail);
+                *rejects.entry(reason.clone()).or_default() += 1;
+                print_warn(&format!(
+                    "{}  unit {}  bad capture: {}",
+                    timestamp(),
+                    unit_id,
+                    reason
+                ));
+            }
+            Err(_) if super::cancel_requested() => break,
+            Err(e) => {
+                fatal = Some(crate::error::wrap_winbio_error("WinBioCaptureSample", &e));
+                break;
+            }
+        }
+    }
+
+    print_header("Watch Summary");
+    print_info("Touches", &touches.to_string());
+    print_info("Good captures", &good.to_string());
+    print_info("Bad captures", &(touches - good).to_string());
+
+    let mut histogram: Vec<(Cow<'static, str>, u32)> = rejects.into_iter().collect();
+    histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    for (reason, count) in &histogram {
+        print_info(&format!("  {}", reason), &count.to_string());
+    }
+
+    match fatal {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use serde_json::json;
+use windows::Win32::Devices::BiometricFramework::*;
+
+use crate::output::*;
+use crate::winbio_helpers::{self, SensorPool};
+
+use super::SessionGuard;
+
+/// Identify once, then report who matched, their enrolled fingers on that
+/// unit, and whether a Windows Hello credential is linked — what `identify`,
+/// `list-fingerprints`, and `credential-state` report, from a single touch on
+/// one session.
+pub fn run_whoami(timeout: Option<u64>, pool: SensorPool) -> Result<()> {
+    print_header("Who Am I");
+
+    crate::elevation::warn_if_not_elevated();
+    winbio_helpers::require_capabilities(None, winbio_helpers::SensorOperation::Identify)?;
+
+    let guard = SessionGuard::new(winbio_helpers::WINBIO_FLAG_DEFAULT, pool, true)?;
+    print_step("Session opened with focus. Touch the sensor to identify yourself...");
+
+    unsafe {
+        let mut unit_id = 0u32;
+        let mut identity = WINBIO_IDENTITY::default();
+        let mut subfactor = 0u8;
+        let mut reject_detail = 0u32;
+
+        guard
+            .run_with_timeout(timeout, |session| {
+                winbio_helpers::trace_call(
+                    "WinBioIdentify",
+                    &format!("session={}", session),
+                    WinBioIdentify(
+                        session,
+                        Some(&mut unit_id),
+                        Some(&mut identity),
+                        Some(&mut subfactor),
+                        Some(&mut reject_detail),
+                    ),
+                )
+            })?
+            .map_err(|e| {
+                let code = crate::error::error_code(&e);
+                if matches!(code, 0x8009_8003 | 0x8009_8005) {
+                    // WINBIO_E_UNKNOWN_ID, WINBIO_E_NO_MATCH
+                    crate::error::set_exit_code(crate::error::ExitCode::NoMatch);
+                    anyhow::anyhow!("No match — finger not enrolled. Cannot identify you.")
+                } else {
+                    crate::error::wrap_winbio_error("WinBioIdentify", &e)
+                }
+            })?;
+
+        print_pass("User identified on sensor");
+        winbio_helpers::print_identity(&identity);
+        print_info("Unit ID", &unit_id.to_string());
+        print_info("Matched finger", &winbio_helpers::subfactor_name(subfactor));
+
+        print_blank();
+        let enrolled = super::delete::enum_enrollments(guard.session, unit_id, &identity)?;
+        let fingers: Vec<String> = enrolled
+            .iter()
+            .map(|&sf| format!("{} ({})", sf, winbio_helpers::subfactor_name(sf)))
+            .collect();
+        print_info(
+            &format!("Enrolled fingers ({})", enrolled.len()),
+            &fingers.join(", "),
+        );
+
+        let credential_set = super::credential_state::check_credential_state(identity)?;
+
+        record(
+            "whoami",
+            json!({
+                "identity": winbio_helpers::identity_description(&identity),
+                "identity_type": winbio_helpers::identity_type_name(identity.Type),
+                "unit_id": unit_id,
+                "matched_subfactor": subfactor,
+                "enrolled_subfactors": enrolled,
+                "credential_set": credential_set,
+            }),
+        );
+    }
+
+    Ok(())
+}
@@ -1,26 +1,483 @@
 use colored::Colorize;
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static JSON_MODE: AtomicBool = AtomicBool::new(false);
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+static FAIL_COUNT: AtomicU32 = AtomicU32::new(0);
+static REPORT: Mutex<Option<Report>> = Mutex::new(None);
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+static TIMINGS: Mutex<Option<Timings>> = Mutex::new(None);
+
+/// Version of the `--json` document layout. Bump it when a field is renamed,
+/// removed, or changes meaning; adding fields does not need a bump.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Report arrays whose items are sorted by the given field before printing, so
+/// that documents from two runs diff cleanly whatever order Windows enumerated in.
+const SORTED_ARRAYS: &[(&str, &str)] = &[
+    ("databases", "index"),
+    ("devices", "InstanceId"),
+    ("enrollments", "unit_id"),
+    ("sensor_summary", "unit_id"),
+    ("units", "unit_id"),
+];
+
+/// Step durations collected with `--timings`.
+struct Timings {
+    start: Instant,
+    steps: Vec<(&'static str, Duration)>,
+}
+
+/// Structured result accumulated while a command runs in `--json` mode.
+/// Every `print_*` call is recorded as a message; commands add machine-readable
+/// data (units, databases, ...) via `record`.
+struct Report {
+    command: String,
+    compact: bool,
+    failed: bool,
+    messages: Vec<Value>,
+    data: Map<String, Value>,
+    errors: Vec<String>,
+}
+
+/// Switch the output module into JSON mode for the given command; `compact`
+/// prints the document on one line. Must be called once at startup, before
+/// anything is printed.
+pub fn enable_json(command: &str, compact: bool) {
+    JSON_MODE.store(true, Ordering::Relaxed);
+    *REPORT.lock().unwrap() = Some(Report::new(command, compact));
+}
+
+impl Report {
+    fn new(command: &str, compact: bool) -> Self {
+        Self {
+            command: command.to_string(),
+            compact,
+            failed: false,
+            messages: Vec::new(),
+            data: Map::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+/// Run `f` with its output collected into a separate JSON document instead of
+/// printed, and return that document as `--json` would print it. Its `[FAIL]`
+/// lines and exit codes don't count towards the running command. Used by
+/// `collect` to save each check's structured result.
+pub fn capture_json(command: &str, f: impl FnOnce() -> anyhow::Result<()>) -> Value {
+    let was_json = JSON_MODE.swap(true, Ordering::Relaxed);
+    let fails = FAIL_COUNT.load(Ordering::Relaxed);
+    let outer = REPORT.lock().unwrap().replace(Report::new(command, false));
+
+    let result = crate::error::without_exit_code(f);
+
+    let captured = std::mem::replace(&mut *REPORT.lock().unwrap(), outer);
+    FAIL_COUNT.store(fails, Ordering::Relaxed);
+    JSON_MODE.store(was_json, Ordering::Relaxed);
+    captured
+        .map(|report| report_document(report, result.as_ref().err()).0)
+        .unwrap_or(Value::Null)
+}
+
+/// Tee all output to `path` as plain text (`--log-file`), starting with a
+/// header naming the tool version, time, and command line.
+/// Must be called once at startup, before anything is printed.
+pub fn open_log_file(path: &Path) -> anyhow::Result<()> {
+    let mut file = File::create(path)
+        .map_err(|e| anyhow::anyhow!("Cannot create log file {}: {}", path.display(), e))?;
+    let args: Vec<String> = std::env::args().collect();
+    writeln!(
+        file,
+        "win-fp-debug {} — {}",
+        env!("CARGO_PKG_VERSION"),
+        timestamp()
+    )?;
+    writeln!(file, "Command: {}", args.join(" "))?;
+    *LOG_FILE.lock().unwrap() = Some(file);
+    Ok(())
+}
+
+/// Append a plain-text line to the `--log-file`, if one is open.
+fn log_line(plain: &str) {
+    if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+        let _ = writeln!(file, "{}", plain);
+    }
+}
+
+/// Central sink for everything printed to stdout: the colored line goes to the
+/// terminal, the plain one to the log file.
+fn emit(colored: impl std::fmt::Display, plain: &str) {
+    println!("{}", colored);
+    log_line(plain);
+}
+
+/// Like `emit`, for headers, info, steps, and blank lines: with `--quiet`
+/// they go only to the log file, leaving the terminal to PASS/FAIL/WARN.
+fn emit_detail(colored: impl std::fmt::Display, plain: &str) {
+    if QUIET.load(Ordering::Relaxed) {
+        log_line(plain);
+    } else {
+        emit(colored, plain);
+    }
+}
+
+/// Print unformatted output (CSV rows, JSON documents) through the sink.
+pub fn print_raw(text: &str) {
+    emit(text, text);
+}
+
+/// Print the error a command failed with to stderr (and the log file).
+pub fn print_error(e: &anyhow::Error) {
+    eprintln!("Error: {:?}", e);
+    log_line(&format!("Error: {:?}", e));
+}
+
+pub fn json_mode() -> bool {
+    JSON_MODE.load(Ordering::Relaxed)
+}
+
+/// Decide once at startup whether to emit ANSI colors: off with `--no-color`,
+/// a non-empty `NO_COLOR` environment variable, or when stdout is not a terminal.
+pub fn init_color(no_color: bool) {
+    use std::io::IsTerminal;
+
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    if no_color || no_color_env || !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+}
+
+/// Enable `print_trace` output. Called once at startup from `--verbose`.
+pub fn set_verbose(enabled: bool) {
+    VERBOSE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// Print only PASS/FAIL/WARN lines (and raw CSV/JSON) to the terminal.
+/// Called once at startup from `--quiet`; the JSON report is unaffected.
+pub fn set_quiet(enabled: bool) {
+    QUIET.store(enabled, Ordering::Relaxed);
+}
+
+/// Start collecting step timings (`--timings`). Called once at startup.
+pub fn enable_timings() {
+    *TIMINGS.lock().unwrap() = Some(Timings {
+        start: Instant::now(),
+        steps: Vec::new(),
+    });
+}
+
+/// Records how long a step took when dropped, if `--timings` is on.
+pub struct StepTimer {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl Drop for StepTimer {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            if let Some(timings) = TIMINGS.lock().unwrap().as_mut() {
+                timings.steps.push((self.name, start.elapsed()));
+            }
+        }
+    }
+}
+
+/// Time the rest of the enclosing scope as `name`: `let _timer = time_step("...");`
+pub fn time_step(name: &'static str) -> StepTimer {
+    let enabled = TIMINGS.lock().unwrap().is_some();
+    StepTimer {
+        name,
+        start: enabled.then(Instant::now),
+    }
+}
+
+/// Print the collected step timings, slowest first. Steps can nest, so the
+/// durations don't add up to the total. No-op without `--timings`.
+pub fn print_timings() {
+    let Some(timings) = TIMINGS.lock().unwrap().take() else {
+        return;
+    };
+    let mut steps = timings.steps;
+    steps.sort_by_key(|&(_, duration)| std::cmp::Reverse(duration));
+
+    print_header("Timings");
+    for (name, duration) in &steps {
+        print_info(name, &format!("{:.1} ms", duration.as_secs_f64() * 1000.0));
+        record(
+            "timings",
+            json!({ "step": name, "ms": duration.as_secs_f64() * 1000.0 }),
+        );
+    }
+    print_info(
+        "Total (wall clock)",
+        &format!("{:.1} ms", timings.start.elapsed().as_secs_f64() * 1000.0),
+    );
+}
+
+/// Number of `[FAIL]` lines printed (or recorded in JSON mode) so far.
+pub fn fail_count() -> u32 {
+    FAIL_COUNT.load(Ordering::Relaxed)
+}
+
+fn with_report(f: impl FnOnce(&mut Report)) {
+    if let Some(report) = REPORT.lock().unwrap().as_mut() {
+        f(report);
+    }
+}
+
+fn record_message(level: &str, message: Value) {
+    with_report(|r| {
+        if level == "fail" {
+            r.failed = true;
+        }
+        let mut entry = json!({ "level": level });
+        if let (Some(entry), Value::Object(fields)) = (entry.as_object_mut(), message) {
+            entry.extend(fields);
+        }
+        r.messages.push(entry);
+    });
+}
+
+/// Append a structured item to the array `key` of the JSON report.
+/// No-op outside JSON mode.
+pub fn record(key: &str, value: Value) {
+    with_report(|r| {
+        let entry = r
+            .data
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(items) = entry {
+            items.push(value);
+        }
+    });
+}
+
+/// Print the accumulated JSON report, including `error` if the command failed.
+/// Returns true if the command failed (an error or any `[FAIL]` line).
+pub fn finish_json(error: Option<&anyhow::Error>) -> bool {
+    let Some(report) = REPORT.lock().unwrap().take() else {
+        return error.is_some();
+    };
+
+    let compact = report.compact;
+    let (doc, failed) = report_document(report, error);
+    if compact {
+        print_raw(&doc.to_string());
+    } else {
+        print_raw(&serde_json::to_string_pretty(&doc).unwrap_or_else(|_| doc.to_string()));
+    }
+    failed
+}
+
+/// The JSON document for a finished `report`, and whether the command failed.
+fn report_document(mut report: Report, error: Option<&anyhow::Error>) -> (Value, bool) {
+    let mut error_object = Value::Null;
+    if let Some(e) = error {
+        report.failed = true;
+        report.errors.push(format!("{:#}", e));
+        error_object = crate::error::error_json(e);
+    }
+
+    for (key, field) in SORTED_ARRAYS {
+        if let Some(Value::Array(items)) = report.data.get_mut(*key) {
+            // Numbers and strings never share a field, so the tuple orders either kind
+            items.sort_by_key(|item| {
+                let value = &item[*field];
+                (value.as_u64(), value.as_str().map(str::to_lowercase))
+            });
+        }
+    }
+
+    // serde_json's default map is a BTreeMap, so object keys come out sorted
+    let mut doc = json!({
+        "schema_version": JSON_SCHEMA_VERSION,
+        "command": report.command,
+        "status": if report.failed { "fail" } else { "pass" },
+        "messages": report.messages,
+        "errors": report.errors,
+        "error": error_object,
+    });
+    if let Some(doc) = doc.as_object_mut() {
+        doc.extend(report.data);
+    }
+    (doc, report.failed)
+}
 
 pub fn print_header(title: &str) {
-    println!();
-    println!("{}", format!("=== {} ===", title).bold().cyan());
+    if json_mode() {
+        record_message("header", json!({ "message": title }));
+        return;
+    }
+    let line = format!("=== {} ===", title);
+    emit_detail("", "");
+    emit_detail(line.bold().cyan(), &line);
 }
 
 pub fn print_pass(msg: &str) {
-    println!("  {} {}", "[PASS]".bold().green(), msg);
+    if json_mode() {
+        record_message("pass", json!({ "message": msg.trim() }));
+        return;
+    }
+    emit(
+        format!("  {} {}", "[PASS]".bold().green(), msg),
+        &format!("  [PASS] {}", msg),
+    );
 }
 
 pub fn print_fail(msg: &str) {
-    println!("  {} {}", "[FAIL]".bold().red(), msg);
+    FAIL_COUNT.fetch_add(1, Ordering::Relaxed);
+    if json_mode() {
+        record_message("fail", json!({ "message": msg.trim() }));
+        return;
+    }
+    emit(
+        format!("  {} {}", "[FAIL]".bold().red(), msg),
+        &format!("  [FAIL] {}", msg),
+    );
 }
 
 pub fn print_warn(msg: &str) {
-    println!("  {} {}", "[WARN]".bold().yellow(), msg);
+    if json_mode() {
+        record_message("warn", json!({ "message": msg.trim() }));
+        return;
+    }
+    emit(
+        format!("  {} {}", "[WARN]".bold().yellow(), msg),
+        &format!("  [WARN] {}", msg),
+    );
 }
 
 pub fn print_info(label: &str, value: &str) {
-    println!("  {}: {}", label.bold(), value);
+    if json_mode() {
+        record_message("info", json!({ "label": label.trim(), "value": value }));
+        return;
+    }
+    emit_detail(
+        format!("  {}: {}", label.bold(), value),
+        &format!("  {}: {}", label, value),
+    );
 }
 
 pub fn print_step(msg: &str) {
-    println!("  {} {}", "-->".bold().blue(), msg);
+    if json_mode() {
+        record_message("step", json!({ "message": msg.trim() }));
+        return;
+    }
+    emit_detail(
+        format!("  {} {}", "-->".bold().blue(), msg),
+        &format!("  --> {}", msg),
+    );
+}
+
+/// Print a low-level trace line (API calls, raw HRESULTs). No-op unless `--verbose`.
+pub fn print_trace(msg: &str) {
+    if !verbose() {
+        return;
+    }
+    if json_mode() {
+        record_message("trace", json!({ "message": msg }));
+        return;
+    }
+    emit(
+        format!("  {} {}", "[TRACE]".dimmed(), msg.dimmed()),
+        &format!("  [TRACE] {}", msg),
+    );
+}
+
+/// Print a line of a text diagram, indented like the other output. `colored`
+/// may carry ANSI highlighting; `plain` is what goes to the log file.
+/// Suppressed in JSON mode.
+pub fn print_art(colored: &str, plain: &str) {
+    if !json_mode() {
+        emit_detail(format!("    {}", colored), &format!("    {}", plain));
+    }
+}
+
+/// Print an empty separator line (suppressed in JSON mode).
+pub fn print_blank() {
+    if !json_mode() {
+        emit_detail("", "");
+    }
+}
+
+/// Format a `SystemTime` as `YYYY-MM-DD HH:MM:SS UTC`.
+pub fn format_system_time(time: std::time::SystemTime) -> String {
+    let since_unix = match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(d) => d,
+        Err(_) => return format!("{:?}", time),
+    };
+
+    let secs = since_unix.as_secs() as i64;
+
+    let mut days = secs / 86400;
+    let day_secs = secs % 86400;
+    let hour = day_secs / 3600;
+    let minute = (day_secs % 3600) / 60;
+    let second = day_secs % 60;
+
+    let mut year = 1970i32;
+    loop {
+        let days_in_year = if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) {
+            366
+        } else {
+            365
+        };
+        if days < days_in_year {
+            break;
+        }
+        days -= days_in_year;
+        year += 1;
+    }
+
+    let is_leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = [
+        31,
+        if is_leap { 29 } else { 28 },
+        31,
+        30,
+        31,
+        30,
+        31,
+        31,
+        30,
+        31,
+        30,
+        31,
+    ];
+
+    let mut month = 0usize;
+    for (i, &md) in month_days.iter().enumerate() {
+        if days < md {
+            month = i;
+            break;
+        }
+        days -= md;
+    }
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month + 1,
+        days + 1,
+        hour,
+        minute,
+        second
+    )
+}
+
+/// The current time, formatted by `format_system_time`.
+pub fn timestamp() -> String {
+    format_system_time(std::time::SystemTime::now())
 }
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use std::process::Command;
+use windows::core::PCWSTR;
+use windows::Win32::Devices::DeviceAndDriverInstallation::*;
+
+use crate::output::*;
+use crate::winbio_helpers;
+
+/// A Biometric-class PnP device, as reported by Device Manager.
+pub struct PnpDevice {
+    pub friendly_name: String,
+    pub instance_id: String,
+    pub manufacturer: String,
+    /// "OK", "Error", or "Unknown" (not present), matching `Get-PnpDevice`.
+    pub status: String,
+    /// `CM_PROB_*` problem code; 0 when the device has no problem.
+    pub problem: u32,
+}
+
+impl PnpDevice {
+    /// Not currently connected, but still registered (CM_PROB_PHANTOM).
+    pub fn is_phantom(&self) -> bool {
+        self.problem == CM_PROB_PHANTOM.0
+    }
+}
+
+/// The `--instance-id` filter: a case-insensitive prefix of the device
+/// instance ID. No filter matches every device.
+pub fn instance_id_matches(filter: Option<&str>, instance_id: &str) -> bool {
+    filter.is_none_or(|f| instance_id.to_uppercase().starts_with(&f.to_uppercase()))
+}
+
+/// Enumerate all Biometric-class devices, including non-present ones.
+/// Uses SetupAPI/CfgMgr32 directly and falls back to PowerShell `Get-PnpDevice`
+/// only if the native enumeration fails.
+pub fn enum_biometric_devices() -> Result<Vec<PnpDevice>> {
+    match enum_native() {
+        Ok(devices) => Ok(devices),
+        Err(e) => {
+            print_warn(&format!(
+                "Native device enumeration failed ({}) — falling back to PowerShell",
+                e
+            ));
+            enum_powershell()
+        }
+    }
+}
+
+/// Read a string registry property (FriendlyName, Mfg, ...) of a device.
+unsafe fn device_property(
+    set: HDEVINFO,
+    info: &SP_DEVINFO_DATA,
+    property: SETUP_DI_REGISTRY_PROPERTY,
+) -> Option<String> {
+    let mut buf = [0u8; 1024];
+    SetupDiGetDeviceRegistryPropertyW(set, info, property, None, Some(&mut buf), None).ok()?;
+    let wide: Vec<u16> = buf
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    let value = winbio_helpers::wchar_to_string(&wide);
+    (!value.is_empty()).then_some(value)
+}
+
+fn enum_native() -> Result<Vec<PnpDevice>> {
+    let _timer = crate::output::time_step("PnP enumeration (SetupAPI)");
+    let mut devices = Vec::new();
+
+    unsafe {
+        // No DIGCF_PRESENT: phantom devices are part of what we diagnose
+        let set = SetupDiGetClassDevsW(
+            Some(&GUID_DEVCLASS_BIOMETRIC),
+            PCWSTR::null(),
+            None,
+            SETUP_DI_GET_CLASS_DEVS_FLAGS(0),
+        )
+        .map_err(|e| anyhow::anyhow!("SetupDiGetClassDevsW failed: {}", e))?;
+
+        let mut index = 0u32;
+        loop {
+            let mut info = SP_DEVINFO_DATA {
+                cbSize: std::mem::size_of::<SP_DEVINFO_DATA>() as u32,
+                ..Default::default()
+            };
+            if SetupDiEnumDeviceInfo(set, index, &mut info).is_err() {
+                break;
+            }
+            index += 1;
+
+            let mut id_buf = [0u16; 512];
+            if SetupDiGetDeviceInstanceIdW(set, &info, Some(&mut id_buf), None).is_err() {
+                continue;
+            }
+            let instance_id = winbio_helpers::wchar_to_string(&id_buf);
+
+            let friendly_name = device_property(set, &info, SPDRP_FRIENDLYNAME)
+                .or_else(|| device_property(set, &info, SPDRP_DEVICEDESC))
+                .unwrap_or_default();
+            let manufacturer = device_property(set, &info, SPDRP_MFG).unwrap_or_default();
+
+            let mut status = CM_DEVNODE_STATUS_FLAGS(0);
+            let mut problem = CM_PROB(0);
+            let (status, problem) =
+                match CM_Get_DevNode_Status(&mut status, &mut problem, info.DevInst, 0) {
+                    CR_SUCCESS if status.0 & DN_HAS_PROBLEM.0 != 0 => ("Error", problem.0),
+                    CR_SUCCESS => ("OK", 0),
+                    // No live devnode: the device is registered but not connected
+                    CR_NO_SUCH_DEVINST => ("Unknown", CM_PROB_PHANTOM.0),
+                    _ => ("Unknown", 0),
+                };
+
+            devices.push(PnpDevice {
+                friendly_name,
+                instance_id,
+                manufacturer,
+                status: status.to_string(),
+                problem,
+            });
+        }
+
+        let _ = SetupDiDestroyDeviceInfoList(set);
+    }
+
+    Ok(devices)
+}
+
+fn enum_powershell() -> Result<Vec<PnpDevice>> {
+    let _timer = crate::output::time_step("PnP enumeration (PowerShell)");
+    let ps_script = r#"
+        $devs = Get-PnpDevice -Class Biometric -ErrorAction SilentlyContinue
+        if ($null -eq $devs) { exit 0 }
+        $devs | ForEach-Object {
+            [PSCustomObject]@{
+                FriendlyName = [string]$_.FriendlyName
+                InstanceId   = [string]$_.InstanceId
+                Status       = [string]$_.Status
+                Problem      = [int]$_.Problem
+                Manufacturer = [string]$_.Manufacturer
+            }
+        } | ConvertTo-Json -Compress
+    "#;
+
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", ps_script])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run PowerShell: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stdout = stdout.trim();
+
+    if stdout.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // PowerShell returns a single object (not array) for one device
+    let raw: Vec<serde_json::Value> = if stdout.starts_with('[') {
+        serde_json::from_str(stdout)?
+    } else {
+        vec![serde_json::from_str(stdout)?]
+    };
+
+    let devices = raw
+        .into_iter()
+        .map(|v| PnpDevice {
+            friendly_name: v["FriendlyName"].as_str().unwrap_or_default().to_string(),
+            instance_id: v["InstanceId"].as_str().unwrap_or_default().to_string(),
+            manufacturer: v["Manufacturer"].as_str().unwrap_or_default().to_string(),
+            status: v["Status"].as_str().unwrap_or("Unknown").to_string(),
+            problem: v["Problem"].as_u64().unwrap_or(0) as u32,
+        })
+        .filter(|d| !d.instance_id.is_empty())
+        .collect();
+
+    Ok(devices)
+}
@@ -1,33 +1,589 @@
 use anyhow::Result;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use windows::core::{w, PCWSTR, PWSTR};
 use windows::Win32::Devices::BiometricFramework::*;
+use windows::Win32::Foundation::{LocalFree, HLOCAL};
+use windows::Win32::Security::Authorization::ConvertSidToStringSidW;
+use windows::Win32::Security::{IsValidSid, LookupAccountSidW, PSID, SID_NAME_USE};
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_DWORD};
 
 // Constants not exported by the windows crate v0.59
+pub const WINBIO_TYPE_FACIAL_FEATURES: u32 = 0x0000_0002;
 pub const WINBIO_TYPE_FINGERPRINT: u32 = 0x0000_0008;
 pub const WINBIO_FLAG_DEFAULT: u32 = 0x0000_0000;
 pub const WINBIO_FLAG_RAW: u32 = 0x2000_0000;
 pub const WINBIO_PURPOSE_NO_PURPOSE_AVAILABLE: u8 = 0x00;
+pub const WINBIO_ID_TYPE_NULL: u32 = 0;
+pub const WINBIO_ID_TYPE_WILDCARD: u32 = 1;
+pub const WINBIO_ID_TYPE_GUID: u32 = 2;
 pub const WINBIO_ID_TYPE_SID: u32 = 3;
+/// `Value.Wildcard` of a wildcard identity (WINBIO_IDENTITY_WILDCARD).
+const WINBIO_IDENTITY_WILDCARD: u32 = 0x2506_6282;
+pub const WINBIO_SUBTYPE_ANY: u8 = 0xFF;
+pub const WINBIO_CAPABILITY_SENSOR: u32 = 0x01;
+pub const WINBIO_CAPABILITY_MATCHING: u32 = 0x02;
+/// The sensor keeps its own (on-chip) template database.
+pub const WINBIO_CAPABILITY_DATABASE: u32 = 0x04;
+/// The reader has an indicator (LED) the sensor adapter can switch.
+pub const WINBIO_CAPABILITY_INDICATOR: u32 = 0x40;
 
-/// Open a WinBio session with the given flags.
-/// Use `WINBIO_FLAG_DEFAULT` for normal operations,
+/// Log an API call and its raw HRESULT via `print_trace` (only with `--verbose`).
+pub fn trace_hresult(api: &str, args: &str, hr: windows::core::HRESULT) {
+    if crate::output::verbose() {
+        crate::output::print_trace(&format!("{}({}) -> 0x{:08X}", api, args, hr.0 as u32));
+    }
+}
+
+/// Trace a WinBio call's result and pass it through unchanged, so calls can be
+/// wrapped inline: `trace_call("WinBioIdentify", "session=1", WinBioIdentify(..))`.
+pub fn trace_call<T>(
+    api: &str,
+    args: &str,
+    result: windows::core::Result<T>,
+) -> windows::core::Result<T> {
+    let hr = match &result {
+        Ok(_) => windows::core::HRESULT(0),
+        Err(e) => e.code(),
+    };
+    trace_hresult(api, args, hr);
+    result
+}
+
+static BUSY_RETRIES: AtomicU32 = AtomicU32::new(3);
+static BIOMETRIC_FACTOR: AtomicU32 = AtomicU32::new(WINBIO_TYPE_FINGERPRINT);
+static SESSION_FLAGS_OVERRIDE: Mutex<Option<u32>> = Mutex::new(None);
+
+/// Set how many times `with_busy_retry` retries a busy sensor (`--retries`).
+pub fn set_busy_retries(retries: u32) {
+    BUSY_RETRIES.store(retries, Ordering::Relaxed);
+}
+
+/// Run a WinBio call, retrying with exponential backoff (250 ms, 500 ms, 1 s, ...)
+/// while it fails with WINBIO_E_DEVICE_BUSY or WINBIO_E_SESSION_BUSY — typically
+/// because Windows Hello grabbed the sensor a moment earlier.
+pub fn with_busy_retry<T>(
+    mut call: impl FnMut() -> windows::core::Result<T>,
+) -> windows::core::Result<T> {
+    let retries = BUSY_RETRIES.load(Ordering::Relaxed);
+    let mut attempt = 0;
+    loop {
+        let result = call();
+        let code = match &result {
+            Err(e) => e.code().0 as u32,
+            Ok(_) => return result,
+        };
+        // WINBIO_E_DEVICE_BUSY, WINBIO_E_SESSION_BUSY
+        if !matches!(code, 0x8009_8010 | 0x8009_802D) || attempt >= retries {
+            return result;
+        }
+        attempt += 1;
+        let delay = std::time::Duration::from_millis(250 << (attempt - 1).min(6));
+        crate::output::print_warn(&format!(
+            "Sensor busy — retrying in {} ms (attempt {}/{})",
+            delay.as_millis(),
+            attempt,
+            retries
+        ));
+        std::thread::sleep(delay);
+    }
+}
+
+/// Sensor pool that sessions are opened against (`--pool`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SensorPool {
+    /// Sensors shared by Windows Hello and all applications
+    #[default]
+    System,
+    /// Sensors reserved for a single application (enterprise setups)
+    Private,
+}
+
+/// Biometric factor that units, databases, and sessions are looked up for (`--type`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BiometricType {
+    /// Fingerprint readers
+    #[default]
+    Fingerprint,
+    /// Windows Hello face (IR camera) units
+    Face,
+}
+
+impl BiometricType {
+    /// The `WINBIO_BIOMETRIC_TYPE` value passed to WinBio.
+    pub fn factor(self) -> u32 {
+        match self {
+            BiometricType::Fingerprint => WINBIO_TYPE_FINGERPRINT,
+            BiometricType::Face => WINBIO_TYPE_FACIAL_FEATURES,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BiometricType::Fingerprint => "fingerprint",
+            BiometricType::Face => "face",
+        }
+    }
+}
+
+/// Set the biometric factor every WinBio call is made for. Called once at startup.
+pub fn set_biometric_type(biometric_type: BiometricType) {
+    BIOMETRIC_FACTOR.store(biometric_type.factor(), Ordering::Relaxed);
+}
+
+/// The biometric type selected with `--type` (fingerprint by default).
+pub fn biometric_type() -> BiometricType {
+    if BIOMETRIC_FACTOR.load(Ordering::Relaxed) == WINBIO_TYPE_FACIAL_FEATURES {
+        BiometricType::Face
+    } else {
+        BiometricType::Fingerprint
+    }
+}
+
+/// Find the database registered for the private pool, i.e. the one whose
+/// `WbioSrvc\Databases\{GUID}\SensorPool` registry value is 2.
+fn private_pool_database() -> Result<windows::core::GUID> {
+    unsafe {
+        let mut schema_array: *mut WINBIO_STORAGE_SCHEMA = std::ptr::null_mut();
+        let mut schema_count: usize = 0;
+        trace_call(
+            "WinBioEnumDatabases",
+            &format!("factor={}", biometric_type().name()),
+            WinBioEnumDatabases(
+                biometric_type().factor(),
+                &mut schema_array,
+                &mut schema_count,
+            ),
+        )
+        .map_err(|e| crate::error::wrap_winbio_error("WinBioEnumDatabases", &e))?;
+
+        let mut found = None;
+        if schema_count > 0 {
+            for schema in winbio_slice(schema_array, schema_count) {
+                let subkey = format!(
+                    "SYSTEM\\CurrentControlSet\\Services\\WbioSrvc\\Databases\\{}",
+                    format_guid(&schema.DatabaseId)
+                );
+                let subkey_wide: Vec<u16> =
+                    subkey.encode_utf16().chain(std::iter::once(0)).collect();
+                let mut pool = 0u32;
+                let mut size = std::mem::size_of::<u32>() as u32;
+                let status = RegGetValueW(
+                    HKEY_LOCAL_MACHINE,
+                    PCWSTR(subkey_wide.as_ptr()),
+                    w!("SensorPool"),
+                    RRF_RT_REG_DWORD,
+                    None,
+                    Some(&mut pool as *mut u32 as *mut _),
+                    Some(&mut size),
+                );
+                if status.is_ok() && pool == WINBIO_POOL_PRIVATE.0 {
+                    found = Some(schema.DatabaseId);
+                    break;
+                }
+            }
+        }
+        winbio_free(schema_array as *const _);
+
+        found.ok_or_else(|| anyhow::anyhow!("No database is registered for the private pool"))
+    }
+}
+
+/// Open every session with `flags` instead of the command's own choice
+/// (`--session-flags`). Called once at startup.
+pub fn set_session_flags_override(flags: Option<u32>) {
+    *SESSION_FLAGS_OVERRIDE.lock().unwrap() = flags;
+}
+
+/// Everything `WinBioOpenSession`/`WinBioAsyncOpenSession` need besides the
+/// factor: the flags after any `--session-flags` override, and for the private
+/// pool its units and database.
+struct SessionParams {
+    flags: u32,
+    pool_type: WINBIO_POOL,
+    units: Vec<u32>,
+    database: Option<windows::core::GUID>,
+}
+
+/// The private pool must name its units and database explicitly, so those are
+/// looked up from the unit schemas and the database registry entries.
+fn session_params(flags: u32, pool: SensorPool) -> Result<SessionParams> {
+    let flags = match *SESSION_FLAGS_OVERRIDE.lock().unwrap() {
+        Some(forced) => {
+            crate::output::print_trace(&format!(
+                "--session-flags: opening with 0x{:08X} instead of 0x{:08X}",
+                forced, flags
+            ));
+            forced
+        }
+        None => flags,
+    };
+    let (pool_type, units, database) = match pool {
+        SensorPool::System => (WINBIO_POOL_SYSTEM, Vec::new(), None),
+        SensorPool::Private => {
+            let units: Vec<u32> = enum_units()?
+                .iter()
+                .filter(|u| u.PoolType == WINBIO_POOL_PRIVATE.0)
+                .map(|u| u.UnitId)
+                .collect();
+            if units.is_empty() {
+                anyhow::bail!("No sensor units are configured for the private pool");
+            }
+            (WINBIO_POOL_PRIVATE, units, Some(private_pool_database()?))
+        }
+    };
+    Ok(SessionParams {
+        flags,
+        pool_type,
+        units,
+        database,
+    })
+}
+
+/// Open a WinBio session with the given flags (unless `--session-flags`
+/// overrides them). Use `WINBIO_FLAG_DEFAULT` for normal operations,
 /// `WINBIO_FLAG_RAW` for raw capture.
-pub fn open_session(flags: u32) -> Result<u32> {
+pub fn open_session(flags: u32, pool: SensorPool) -> Result<u32> {
+    let _timer = crate::output::time_step("WinBioOpenSession");
+    let params = session_params(flags, pool)?;
+
     unsafe {
-        WinBioOpenSession(
-            WINBIO_TYPE_FINGERPRINT,
-            WINBIO_POOL_SYSTEM,
-            flags,
+        let result = WinBioOpenSession(
+            biometric_type().factor(),
+            params.pool_type,
+            params.flags,
+            (!params.units.is_empty()).then_some(params.units.as_slice()),
+            params.database.as_ref().map(|g| g as *const _),
+        );
+        let args = format!(
+            "factor=0x{:08X}, pool={:?}, flags=0x{:08X}, units={:?}",
+            biometric_type().factor(),
+            pool,
+            params.flags,
+            params.units
+        );
+        trace_call("WinBioOpenSession", &args, result)
+            .inspect(|session| {
+                crate::output::print_trace(&format!("  session handle = {}", session))
+            })
+            .map_err(|e| crate::error::wrap_winbio_error("WinBioOpenSession", &e))
+    }
+}
+
+/// Open an asynchronous session like `open_session`: the open itself completes
+/// before this returns, but every later operation on the session returns at
+/// once and delivers its result to `callback` on a framework thread.
+pub fn open_async_session(
+    flags: u32,
+    pool: SensorPool,
+    callback: PWINBIO_ASYNC_COMPLETION_CALLBACK,
+) -> Result<u32> {
+    let _timer = crate::output::time_step("WinBioAsyncOpenSession");
+    let params = session_params(flags, pool)?;
+
+    unsafe {
+        let mut session = 0u32;
+        let result = WinBioAsyncOpenSession(
+            biometric_type().factor(),
+            params.pool_type,
+            params.flags,
+            (!params.units.is_empty()).then_some(params.units.as_slice()),
+            params.database.as_ref().map(|g| g as *const _),
+            WINBIO_ASYNC_NOTIFY_CALLBACK,
             None,
             None,
-        )
-        .map_err(|e| crate::error::wrap_winbio_error("WinBioOpenSession", &e))
+            Some(callback),
+            None,
+            false,
+            Some(&mut session),
+        );
+        let args = format!(
+            "factor=0x{:08X}, pool={:?}, flags=0x{:08X}, units={:?}, notify=CALLBACK",
+            biometric_type().factor(),
+            pool,
+            params.flags,
+            params.units
+        );
+        trace_call("WinBioAsyncOpenSession", &args, result)
+            .map(|()| session)
+            .inspect(|session| {
+                crate::output::print_trace(&format!("  session handle = {}", session))
+            })
+            .map_err(|e| crate::error::wrap_winbio_error("WinBioAsyncOpenSession", &e))
     }
 }
 
 /// Close a WinBio session.
 pub fn close_session(session: u32) {
+    let _timer = crate::output::time_step("WinBioCloseSession");
     unsafe {
-        let _ = WinBioCloseSession(session);
+        let _ = trace_call(
+            "WinBioCloseSession",
+            &format!("session={}", session),
+            WinBioCloseSession(session),
+        );
+    }
+}
+
+/// Enumerate fingerprint biometric units, copying the schemas out of the
+/// WinBio-allocated array.
+pub fn enum_units() -> Result<Vec<WINBIO_UNIT_SCHEMA>> {
+    let _timer = crate::output::time_step("WinBioEnumBiometricUnits");
+    unsafe {
+        let mut unit_array: *mut WINBIO_UNIT_SCHEMA = std::ptr::null_mut();
+        let mut unit_count: usize = 0;
+
+        trace_call(
+            "WinBioEnumBiometricUnits",
+            &format!("factor=0x{:08X}", biometric_type().factor()),
+            WinBioEnumBiometricUnits(biometric_type().factor(), &mut unit_array, &mut unit_count),
+        )
+        .map_err(|e| crate::error::wrap_winbio_error("WinBioEnumBiometricUnits", &e))?;
+
+        let units = if unit_count == 0 {
+            Vec::new()
+        } else {
+            winbio_slice(unit_array, unit_count).to_vec()
+        };
+        winbio_free(unit_array as *const _);
+        Ok(units)
+    }
+}
+
+/// Fail with `ExitCode::FrameworkUnavailable` and a plain message when the
+/// Windows Biometric Framework is missing (stripped images, some SKUs), instead
+/// of letting the first WinBio call surface an RPC HRESULT. Only the WbioSrvc
+/// registration is probed: winbio.dll is a load-time import, so without it the
+/// process never gets this far.
+pub fn require_framework() -> Result<()> {
+    if crate::operations::service::service_installed() == Some(false) {
+        crate::error::set_exit_code(crate::error::ExitCode::FrameworkUnavailable);
+        anyhow::bail!(
+            "Windows Biometric Framework is not available on this system (the WbioSrvc service is not installed)"
+        );
+    }
+    Ok(())
+}
+
+/// Poll `WinBioEnumBiometricUnits` once a second until a unit of the `--type`
+/// appears (`--wait-for-sensor`), e.g. while a freshly reinstalled driver or
+/// a restarting WbioSrvc brings the sensor up. Fails once `timeout` expires.
+pub fn wait_for_sensor(timeout: std::time::Duration) -> Result<()> {
+    let start = std::time::Instant::now();
+    loop {
+        // Errors (service still starting, ...) just mean "not ready yet"
+        if let Ok(units) = enum_units() {
+            if !units.is_empty() {
+                crate::output::print_pass(&format!(
+                    "{} {} unit(s) available after {:.1}s",
+                    units.len(),
+                    biometric_type().name(),
+                    start.elapsed().as_secs_f64()
+                ));
+                return Ok(());
+            }
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
+            anyhow::bail!(
+                "No {} sensor appeared within {} seconds",
+                biometric_type().name(),
+                timeout.as_secs()
+            );
+        }
+        crate::output::print_step(&format!(
+            "Waiting for a {} sensor... ({}s / {}s)",
+            biometric_type().name(),
+            elapsed.as_secs(),
+            timeout.as_secs()
+        ));
+        std::thread::sleep(std::time::Duration::from_secs(1).min(timeout - elapsed));
+    }
+}
+
+/// Sensor operations that need specific unit capabilities.
+#[derive(Clone, Copy)]
+pub enum SensorOperation {
+    /// Raw `WinBioCaptureSample` (capture, watch, export-template)
+    RawCapture,
+    /// `WinBioIdentify` (identify, list-fingerprints, delete, credential-state)
+    Identify,
+    Verify,
+    Enroll,
+    Indicator,
+}
+
+impl SensorOperation {
+    fn name(self) -> &'static str {
+        match self {
+            SensorOperation::RawCapture => "Raw capture",
+            SensorOperation::Identify => "Identify",
+            SensorOperation::Verify => "Verify",
+            SensorOperation::Enroll => "Enrollment",
+            SensorOperation::Indicator => "Indicator control",
+        }
+    }
+
+    /// `WINBIO_CAPABILITY_*` flags a unit must report to run this operation.
+    fn required_capabilities(self) -> u32 {
+        match self {
+            SensorOperation::RawCapture => WINBIO_CAPABILITY_SENSOR,
+            SensorOperation::Identify | SensorOperation::Verify | SensorOperation::Enroll => {
+                WINBIO_CAPABILITY_SENSOR | WINBIO_CAPABILITY_MATCHING
+            }
+            SensorOperation::Indicator => WINBIO_CAPABILITY_INDICATOR,
+        }
+    }
+}
+
+/// Pre-flight check: refuse `operation` when the requested unit (or, without
+/// `--unit`, every unit) lacks a capability it needs. Units reporting no
+/// capabilities at all are given the benefit of the doubt, and enumeration
+/// failures are left for the operation itself to report.
+pub fn require_capabilities(unit: Option<u32>, operation: SensorOperation) -> Result<()> {
+    let Ok(units) = enum_units() else {
+        return Ok(());
+    };
+    let candidates: Vec<&WINBIO_UNIT_SCHEMA> = units
+        .iter()
+        .filter(|u| unit.is_none_or(|id| u.UnitId == id))
+        .collect();
+
+    let required = operation.required_capabilities();
+    let capable =
+        |u: &&WINBIO_UNIT_SCHEMA| u.Capabilities == 0 || u.Capabilities & required == required;
+    match candidates.first() {
+        Some(first) if !candidates.iter().any(capable) => {
+            anyhow::bail!(
+                "{} is not supported by unit {} ({}): missing capability {} (reports: {})",
+                operation.name(),
+                first.UnitId,
+                wchar_to_string(&first.Description),
+                capabilities_string(required & !first.Capabilities),
+                capabilities_string(first.Capabilities)
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Resolve an optional `--unit` argument to a concrete unit ID.
+/// An explicit ID is validated against `WinBioEnumBiometricUnits`; when omitted,
+/// the first unit of the `--type` is selected and the choice is reported.
+pub fn resolve_unit_id(requested: Option<u32>) -> Result<u32> {
+    select_unit(requested, |_| true)
+}
+
+/// Like [`resolve_unit_id`] for an operation that runs on one specific unit:
+/// the unit must support `operation`, and without `--unit` the first unit that
+/// does is selected rather than simply the first unit.
+pub fn resolve_unit_for(requested: Option<u32>, operation: SensorOperation) -> Result<u32> {
+    require_capabilities(requested, operation)?;
+    let required = operation.required_capabilities();
+    select_unit(requested, |u| {
+        u.Capabilities == 0 || u.Capabilities & required == required
+    })
+}
+
+/// `resolve_unit_id`, auto-selecting the first unit for which `eligible` holds
+/// (or the first unit, if none does).
+fn select_unit(
+    requested: Option<u32>,
+    eligible: impl Fn(&WINBIO_UNIT_SCHEMA) -> bool,
+) -> Result<u32> {
+    let units = enum_units()?;
+    if units.is_empty() {
+        crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
+        anyhow::bail!("No {} biometric units found", biometric_type().name());
+    }
+
+    match requested {
+        Some(id) => {
+            let Some(unit) = units.iter().find(|u| u.UnitId == id) else {
+                let available: Vec<String> = units.iter().map(|u| u.UnitId.to_string()).collect();
+                anyhow::bail!(
+                    "Unit {} not found (available units: {})",
+                    id,
+                    available.join(", ")
+                );
+            };
+            crate::output::print_info(
+                "Using sensor unit",
+                &format!("{} ({})", id, wchar_to_string(&unit.Description)),
+            );
+            Ok(id)
+        }
+        None => {
+            let unit = units.iter().find(|u| eligible(u)).unwrap_or(&units[0]);
+            crate::output::print_info(
+                "Auto-selected sensor unit",
+                &format!(
+                    "{} ({}){}",
+                    unit.UnitId,
+                    wchar_to_string(&unit.Description),
+                    if units.len() > 1 {
+                        format!(" — {} units present, use --unit to choose", units.len())
+                    } else {
+                        String::new()
+                    }
+                ),
+            );
+            Ok(unit.UnitId)
+        }
+    }
+}
+
+/// Apply `--serial`: resolve it to the ID of the one unit whose `SerialNumber`
+/// matches (trimmed, case-insensitive). Without a serial, `unit` is passed
+/// through unchanged for [`resolve_unit_id`]. Unit IDs can change across
+/// reboots; serial numbers identify the physical sensor.
+pub fn unit_for_serial(unit: Option<u32>, serial: Option<&str>) -> Result<Option<u32>> {
+    let Some(serial) = serial else {
+        return Ok(unit);
+    };
+    let units = enum_units()?;
+    if units.is_empty() {
+        crate::error::set_exit_code(crate::error::ExitCode::NoHardware);
+        anyhow::bail!("No {} biometric units found", biometric_type().name());
+    }
+
+    let wanted = serial.trim();
+    let matches: Vec<u32> = units
+        .iter()
+        .filter(|u| {
+            wchar_to_string(&u.SerialNumber)
+                .trim()
+                .eq_ignore_ascii_case(wanted)
+        })
+        .map(|u| u.UnitId)
+        .collect();
+    match matches.as_slice() {
+        [id] => Ok(Some(*id)),
+        [] => {
+            let available: Vec<String> = units
+                .iter()
+                .map(|u| match wchar_to_string(&u.SerialNumber).trim() {
+                    "" => format!("unit {}: (none)", u.UnitId),
+                    sn => format!("unit {}: {}", u.UnitId, sn),
+                })
+                .collect();
+            anyhow::bail!(
+                "No unit has serial number '{}' (available: {})",
+                wanted,
+                available.join(", ")
+            );
+        }
+        ids => {
+            let ids: Vec<String> = ids.iter().map(u32::to_string).collect();
+            anyhow::bail!(
+                "Serial number '{}' is reported by {} units ({}); use --unit instead",
+                wanted,
+                ids.len(),
+                ids.join(", ")
+            );
+        }
     }
 }
 
@@ -38,7 +594,8 @@ pub fn close_session(session: u32) {
 /// Windows Terminal's pseudo-console doesn't participate in the Win32 focus
 /// system, so console apps need a real HWND for WinBio operations.
 pub struct FocusWindow {
-    hwnd_raw: isize,
+    /// ID of the thread that owns the window and runs its message pump.
+    thread_id: u32,
     thread: Option<std::thread::JoinHandle<()>>,
     has_winbio_focus: bool,
 }
@@ -52,28 +609,59 @@ unsafe extern "system" fn focus_wnd_proc(
     windows::Win32::UI::WindowsAndMessaging::DefWindowProcW(hwnd, msg, wparam, lparam)
 }
 
+/// Outcome of registering the focus window class, shared by every
+/// `FocusWindow`. Window classes are process-wide and are released when the
+/// process exits, so the class is registered once and never unregistered.
+static FOCUS_CLASS: std::sync::OnceLock<std::result::Result<(), String>> =
+    std::sync::OnceLock::new();
+
+fn register_focus_class() -> std::result::Result<(), String> {
+    use windows::Win32::Foundation::ERROR_CLASS_ALREADY_EXISTS;
+    use windows::Win32::UI::WindowsAndMessaging::*;
+
+    FOCUS_CLASS
+        .get_or_init(|| unsafe {
+            let wc = WNDCLASSW {
+                lpfnWndProc: Some(focus_wnd_proc),
+                lpszClassName: FOCUS_CLASS_NAME,
+                ..std::mem::zeroed()
+            };
+            if RegisterClassW(&wc) != 0 {
+                return Ok(());
+            }
+            // Registered by someone else in this process (e.g. an embedding host)
+            let e = windows::core::Error::from_win32();
+            if e.code() == ERROR_CLASS_ALREADY_EXISTS.to_hresult() {
+                Ok(())
+            } else {
+                Err(format!("RegisterClassW failed: {}", e))
+            }
+        })
+        .clone()
+}
+
+const FOCUS_CLASS_NAME: windows::core::PCWSTR = windows::core::w!("WinFpDebugFocus");
+
 impl FocusWindow {
     /// Create a hidden window on a background thread with a message pump,
     /// then bring it to the foreground and attempt WinBioAcquireFocus.
-    pub fn new() -> Option<Self> {
+    /// Fails with the Win32 error from `RegisterClassW`/`CreateWindowExW`;
+    /// a refused `SetForegroundWindow` is only warned about.
+    pub fn new() -> Result<Self> {
         use std::sync::mpsc;
         use windows::core::w;
+        use windows::Win32::System::Threading::GetCurrentThreadId;
         use windows::Win32::UI::WindowsAndMessaging::*;
 
-        let (tx, rx) = mpsc::channel::<isize>();
+        register_focus_class().map_err(|reason| anyhow::anyhow!(reason))?;
 
-        let thread = std::thread::spawn(move || unsafe {
-            let class_name = w!("WinFpDebugFocus");
-            let wc: WNDCLASSW = WNDCLASSW {
-                lpfnWndProc: Some(focus_wnd_proc),
-                lpszClassName: class_name,
-                ..std::mem::zeroed()
-            };
-            RegisterClassW(&wc);
+        // (owning thread ID, SetForegroundWindow succeeded) or the failure reason
+        let (tx, rx) = mpsc::channel::<std::result::Result<(u32, bool), String>>();
 
+        let thread = std::thread::spawn(move || unsafe {
             let hwnd = CreateWindowExW(
                 WINDOW_EX_STYLE::default(),
-                class_name,
+                FOCUS_CLASS_NAME,
                 w!("win-fp-debug"),
                 WINDOW_STYLE::default(),
                 0,
@@ -86,38 +674,69 @@ impl FocusWindow {
                 None,
             );
 
-            match hwnd {
-                Ok(h) if !h.is_invalid() => {
-                    // Show then immediately hide — this triggers WM_ACTIVATE
-                    let _ = ShowWindow(h, SW_SHOW);
-                    let _ = ShowWindow(h, SW_HIDE);
-                    let _ = SetForegroundWindow(h);
-                    let _ = tx.send(h.0 as isize);
+            let h = match hwnd {
+                Ok(h) if !h.is_invalid() => h,
+                Ok(_) => {
+                    let _ = tx.send(Err("CreateWindowExW returned an invalid window".to_string()));
+                    return;
                 }
-                _ => {
-                    let _ = tx.send(0);
+                Err(e) => {
+                    let _ = tx.send(Err(format!("CreateWindowExW failed: {}", e)));
                     return;
                 }
-            }
+            };
 
-            // Message pump — runs until WM_QUIT is posted
-            let mut msg = MSG::default();
-            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
-                let _ = TranslateMessage(&msg);
-                DispatchMessageW(&msg);
+            // Show then immediately hide — this triggers WM_ACTIVATE
+            let _ = ShowWindow(h, SW_SHOW);
+            let _ = ShowWindow(h, SW_HIDE);
+            let foreground = SetForegroundWindow(h).as_bool();
+            // The window gives this thread a message queue, so WM_QUIT can
+            // now be posted to the thread itself
+            if tx.send(Ok((GetCurrentThreadId(), foreground))).is_ok() {
+                // Message pump — runs until WM_QUIT is posted. GetMessageW
+                // returns -1 on error, which must end the loop, not spin it.
+                let mut msg = MSG::default();
+                loop {
+                    match GetMessageW(&mut msg, None, 0, 0).0 {
+                        0 | -1 => break,
+                        _ => {
+                            let _ = TranslateMessage(&msg);
+                            DispatchMessageW(&msg);
+                        }
+                    }
+                }
             }
+
+            // Only the owning thread can destroy the window
+            let _ = DestroyWindow(h);
         });
 
-        let hwnd_raw = rx.recv().ok()?;
-        if hwnd_raw == 0 {
-            return None;
+        let (thread_id, foreground) = match rx.recv() {
+            Ok(Ok(window)) => window,
+            Ok(Err(reason)) => {
+                let _ = thread.join();
+                anyhow::bail!(reason);
+            }
+            Err(_) => {
+                let _ = thread.join();
+                anyhow::bail!("focus window thread exited unexpectedly");
+            }
+        };
+
+        if !foreground {
+            crate::output::print_warn(
+                "SetForegroundWindow was refused — WinBio only delivers touches to the foreground app, \
+                 so this may hang (common over RDP or when launched from a background process). \
+                 Click this console window, or run locally, and try again.",
+            );
         }
 
         // Also try WinBioAcquireFocus for good measure
-        let has_winbio_focus = unsafe { WinBioAcquireFocus().is_ok() };
+        let has_winbio_focus =
+            unsafe { trace_call("WinBioAcquireFocus", "", WinBioAcquireFocus()).is_ok() };
 
-        Some(Self {
-            hwnd_raw,
+        Ok(Self {
+            thread_id,
             thread: Some(thread),
             has_winbio_focus,
         })
@@ -131,17 +750,19 @@ impl Drop for FocusWindow {
 
         if self.has_winbio_focus {
             unsafe {
-                let _ = WinBioReleaseFocus();
+                let _ = trace_call("WinBioReleaseFocus", "", WinBioReleaseFocus());
             }
         }
 
-        // Post WM_QUIT to stop the message pump
-        let hwnd = HWND(self.hwnd_raw as *mut _);
-        unsafe {
-            let _ = PostMessageW(Some(hwnd), WM_QUIT, WPARAM(0), LPARAM(0));
-        }
+        // Post WM_QUIT to the pump's thread. If that fails the pump may
+        // already be gone; if it is not, joining would block forever, so the
+        // thread is left to end with the process instead.
+        let posted =
+            unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)).is_ok() };
         if let Some(t) = self.thread.take() {
-            let _ = t.join();
+            if posted || t.is_finished() {
+                let _ = t.join();
+            }
         }
     }
 }
@@ -161,33 +782,211 @@ pub fn subfactor_name(subfactor: u8) -> String {
         8 => "Left Middle".to_string(),
         9 => "Left Ring".to_string(),
         10 => "Left Little".to_string(),
-        0xFF => "Any Finger".to_string(),
+        WINBIO_SUBTYPE_ANY => "Any Finger".to_string(),
         0 => "Unknown".to_string(),
         n => format!("Vendor-specific (0x{:02X})", n),
     }
 }
 
+/// Build a `WINBIO_ID_TYPE_SID` identity from a binary SID.
+/// Returns None if the SID does not fit in `WINBIO_IDENTITY`.
+pub fn sid_identity(sid: &[u8]) -> Option<WINBIO_IDENTITY> {
+    let mut account = WINBIO_IDENTITY_0_0::default();
+    if sid.len() > account.Data.len() {
+        return None;
+    }
+    account.Size = sid.len() as u32;
+    account.Data[..sid.len()].copy_from_slice(sid);
+
+    let mut identity = WINBIO_IDENTITY {
+        Type: WINBIO_ID_TYPE_SID,
+        ..Default::default()
+    };
+    identity.Value.AccountSid = account;
+    Some(identity)
+}
+
+/// `sid` as a `PSID` if it holds a complete, valid SID. The Win32 SID
+/// functions read as many sub-authorities as the header claims, so anything
+/// shorter must never reach them.
+fn checked_psid(sid: &[u8]) -> Option<PSID> {
+    // Revision, SubAuthorityCount, 6-byte authority, then 4 bytes per sub-authority
+    if sid.len() < 8 || sid.len() < 8 + 4 * sid[1] as usize {
+        return None;
+    }
+    let psid = PSID(sid.as_ptr() as *mut _);
+    unsafe { IsValidSid(psid) }.as_bool().then_some(psid)
+}
+
+/// The `S-1-5-...` string form of a binary SID, or None if it is not valid.
+pub fn sid_to_string(sid: &[u8]) -> Option<String> {
+    let psid = checked_psid(sid)?;
+    unsafe {
+        let mut string_sid = PWSTR::null();
+        ConvertSidToStringSidW(psid, &mut string_sid).ok()?;
+        let result = string_sid.to_string().unwrap_or_default();
+        let _ = LocalFree(Some(HLOCAL(string_sid.0 as *mut _)));
+        (!result.is_empty()).then_some(result)
+    }
+}
+
+/// Resolve a binary SID for display: `DOMAIN\user` via `LookupAccountSidW`,
+/// else the `S-1-5-...` string form, else a hex dump of the raw bytes.
+/// Bytes that are not a valid SID skip the lookups.
+pub fn sid_to_account_name(sid: &[u8]) -> String {
+    if sid.is_empty() {
+        return "(no SID)".to_string();
+    }
+    if let Some(psid) = checked_psid(sid) {
+        unsafe {
+            let mut name = [0u16; 256];
+            let mut domain = [0u16; 256];
+            let mut name_len = name.len() as u32;
+            let mut domain_len = domain.len() as u32;
+            let mut sid_use = SID_NAME_USE::default();
+
+            let lookup = LookupAccountSidW(
+                PCWSTR::null(),
+                psid,
+                Some(PWSTR(name.as_mut_ptr())),
+                &mut name_len,
+                Some(PWSTR(domain.as_mut_ptr())),
+                &mut domain_len,
+                &mut sid_use,
+            );
+            if lookup.is_ok() {
+                let name = wchar_to_string(&name);
+                let domain = wchar_to_string(&domain);
+                return if domain.is_empty() {
+                    name
+                } else {
+                    format!("{}\\{}", domain, name)
+                };
+            }
+        }
+    }
+    if let Some(string_sid) = sid_to_string(sid) {
+        return string_sid;
+    }
+
+    let hex: Vec<String> = sid.iter().map(|b| format!("{:02X}", b)).collect();
+    hex.join(" ")
+}
+
+/// Format a GUID the way the registry writes it: `{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}`.
+pub fn format_guid(guid: &windows::core::GUID) -> String {
+    format!(
+        "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+        guid.data1,
+        guid.data2,
+        guid.data3,
+        guid.data4[0],
+        guid.data4[1],
+        guid.data4[2],
+        guid.data4[3],
+        guid.data4[4],
+        guid.data4[5],
+        guid.data4[6],
+        guid.data4[7]
+    )
+}
+
+/// Parse a GUID in `format_guid`'s layout. The braces are optional and the hex
+/// digits may be lowercase, as in hand-edited or INF-written registry values.
+pub fn parse_guid(text: &str) -> Option<windows::core::GUID> {
+    let text = text.trim();
+    let text = match text.strip_prefix('{') {
+        Some(rest) => rest.strip_suffix('}')?,
+        None => text,
+    };
+    let groups: Vec<&str> = text.split('-').collect();
+    let lengths: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+    if lengths != [8, 4, 4, 4, 12] || !groups.concat().chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = u128::from_str_radix(&groups.concat(), 16).ok()?;
+    Some(windows::core::GUID::from_u128(value))
+}
+
+/// `{GUID}` in `format_guid`'s canonical form, for comparing database IDs
+/// from different sources; text that isn't a GUID is only upper-cased.
+pub fn normalize_guid(text: &str) -> String {
+    parse_guid(text)
+        .map(|guid| format_guid(&guid))
+        .unwrap_or_else(|| text.to_uppercase())
+}
+
+/// Name of a `WINBIO_ID_TYPE_*` value.
+pub fn identity_type_name(id_type: u32) -> String {
+    match id_type {
+        WINBIO_ID_TYPE_NULL => "Null".to_string(),
+        WINBIO_ID_TYPE_WILDCARD => "Wildcard".to_string(),
+        WINBIO_ID_TYPE_GUID => "GUID".to_string(),
+        WINBIO_ID_TYPE_SID => "SID".to_string(),
+        n => format!("Unknown ({})", n),
+    }
+}
+
+/// Readable form of an identity: the account name for a SID, the template
+/// GUID for adapters that key templates by GUID, or what a Null/Wildcard
+/// identity stands for.
+pub fn identity_description(identity: &WINBIO_IDENTITY) -> String {
+    unsafe {
+        match identity.Type {
+            WINBIO_ID_TYPE_SID => {
+                let sid_data = &identity.Value.AccountSid;
+                let size = sid_data.Size as usize;
+                sid_to_account_name(&sid_data.Data[..size.min(sid_data.Data.len())])
+            }
+            WINBIO_ID_TYPE_GUID => format!(
+                "{} (template GUID)",
+                format_guid(&identity.Value.TemplateGuid)
+            ),
+            WINBIO_ID_TYPE_WILDCARD => match identity.Value.Wildcard {
+                WINBIO_IDENTITY_WILDCARD => "Wildcard (any identity)".to_string(),
+                other => format!("Wildcard (unexpected value 0x{:08X})", other),
+            },
+            WINBIO_ID_TYPE_NULL => "Null (no identity)".to_string(),
+            other => format!("{} identity type", identity_type_name(other)),
+        }
+    }
+}
+
+/// Print the identity returned by Identify/EnrollCommit, resolving SIDs to
+/// account names and formatting template GUIDs.
+pub fn print_identity(identity: &WINBIO_IDENTITY) {
+    crate::output::print_info("Identity", &identity_description(identity));
+    if identity.Type != WINBIO_ID_TYPE_SID {
+        crate::output::print_info("Identity type", &identity_type_name(identity.Type));
+    }
+}
+
 /// Convert a null-terminated `[u16; N]` (UTF-16) buffer to a Rust String.
 pub fn wchar_to_string(buf: &[u16]) -> String {
     let end = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
     String::from_utf16_lossy(&buf[..end])
 }
 
-/// Translate a WINBIO_REJECT_DETAIL to a human-readable reason.
-pub fn reject_reason(detail: u32) -> &'static str {
-    match detail {
-        1 => "Too high",
-        2 => "Too low",
-        3 => "Too left",
-        4 => "Too right",
-        5 => "Too fast",
-        6 => "Too slow",
-        7 => "Poor quality",
-        8 => "Too skewed",
-        9 => "Too short",
-        10 => "Merge failure",
-        _ => "Unknown rejection reason",
-    }
+/// Translate a WINBIO_REJECT_DETAIL to a human-readable reason. These are the
+/// fingerprint values from winbio_types.h (`WINBIO_FP_TOO_HIGH` = 1 through
+/// `WINBIO_FP_MERGE_FAILURE` = 10); anything else is reported with its number.
+pub fn reject_reason(detail: u32) -> std::borrow::Cow<'static, str> {
+    let reason = match detail {
+        1 => "Too high",       // WINBIO_FP_TOO_HIGH
+        2 => "Too low",        // WINBIO_FP_TOO_LOW
+        3 => "Too left",       // WINBIO_FP_TOO_LEFT
+        4 => "Too right",      // WINBIO_FP_TOO_RIGHT
+        5 => "Too fast",       // WINBIO_FP_TOO_FAST
+        6 => "Too slow",       // WINBIO_FP_TOO_SLOW
+        7 => "Poor quality",   // WINBIO_FP_POOR_QUALITY
+        8 => "Too skewed",     // WINBIO_FP_TOO_SKEWED
+        9 => "Too short",      // WINBIO_FP_TOO_SHORT
+        10 => "Merge failure", // WINBIO_FP_MERGE_FAILURE
+        _ => {
+            return format!("Unknown rejection reason ({} / 0x{:08X})", detail, detail).into();
+        }
+    };
+    reason.into()
 }
 
 /// Free memory allocated by WinBio API calls.
@@ -199,6 +998,24 @@ pub unsafe fn winbio_free(ptr: *const std::ffi::c_void) {
     }
 }
 
+/// View an array returned by a WinBio enumeration as a slice. A successful
+/// call can still leave the array null, which `from_raw_parts` must not see.
+/// # Safety
+/// A non-null `array` must point to `count` initialized elements.
+pub unsafe fn winbio_slice<'a, T>(array: *const T, count: usize) -> &'a [T] {
+    if array.is_null() || count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(array, count)
+    }
+}
+
+/// True when a successful `WinBioCaptureSample` still returned no usable BIR:
+/// a null pointer, or fewer bytes than the `WINBIO_BIR` block table.
+pub fn is_empty_sample(sample: *const WINBIO_BIR, sample_size: usize) -> bool {
+    sample.is_null() || sample_size < std::mem::size_of::<WINBIO_BIR>()
+}
+
 /// Convert a `WINBIO_BIOMETRIC_SENSOR_SUBTYPE` to a readable string.
 pub fn sensor_subtype_name(subtype: u32) -> &'static str {
     match subtype {
@@ -242,3 +1059,56 @@ pub fn capabilities_string(caps: u32) -> String {
         parts.join(" | ")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::core::GUID;
+
+    const GUID_VALUE: GUID = GUID::from_u128(0x1d6a_ee4b_73f8_4b2e_9a1c_0f5d_3e7a_b2c4);
+
+    #[test]
+    fn guid_round_trips_through_format_and_parse() {
+        let text = format_guid(&GUID_VALUE);
+        assert_eq!(text, "{1D6AEE4B-73F8-4B2E-9A1C-0F5D3E7AB2C4}");
+        assert_eq!(parse_guid(&text), Some(GUID_VALUE));
+    }
+
+    #[test]
+    fn parse_guid_accepts_missing_braces_and_lowercase() {
+        assert_eq!(
+            parse_guid("1D6AEE4B-73F8-4B2E-9A1C-0F5D3E7AB2C4"),
+            Some(GUID_VALUE)
+        );
+        assert_eq!(
+            parse_guid("{1d6aee4b-73f8-4b2e-9a1c-0f5d3e7ab2c4}"),
+            Some(GUID_VALUE)
+        );
+        assert_eq!(
+            parse_guid(" 1d6aee4b-73f8-4b2e-9a1c-0f5d3e7ab2c4 "),
+            Some(GUID_VALUE)
+        );
+        assert_eq!(
+            normalize_guid("1d6aee4b-73f8-4b2e-9a1c-0f5d3e7ab2c4"),
+            "{1D6AEE4B-73F8-4B2E-9A1C-0F5D3E7AB2C4}"
+        );
+    }
+
+    #[test]
+    fn parse_guid_rejects_malformed_text() {
+        for text in [
+            "",
+            "{}",
+            "{1D6AEE4B-73F8-4B2E-9A1C-0F5D3E7AB2C4",
+            "1D6AEE4B-73F8-4B2E-9A1C-0F5D3E7AB2C4}",
+            "1D6AEE4B73F84B2E9A1C0F5D3E7AB2C4",
+            "1D6AEE4B-73F8-4B2E-9A1C-0F5D3E7AB2C",
+            "1D6AEE4B-73F8-4B2E-9A1C-0F5D3E7AB2C4-00",
+            "1D6AEE4B-73F8-4B2E-9A1C-0F5D3E7AB2CG",
+            "+D6AEE4B-73F8-4B2E-9A1C-0F5D3E7AB2C4",
+        ] {
+            assert_eq!(parse_guid(text), None, "{:?}", text);
+        }
+        assert_eq!(normalize_guid("not-a-guid"), "NOT-A-GUID");
+    }
+}